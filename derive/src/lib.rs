@@ -0,0 +1,175 @@
+//! Derive macros for `anymap`. Use them through the `derive` feature of the
+//! `anymap` crate rather than depending on this crate directly.
+
+use proc_macro::{Delimiter, Spacing, TokenStream, TokenTree};
+
+/// A struct with named fields, as far as the derives need to know it.
+struct Struct {
+    name: String,
+    fields: Vec<Field>,
+}
+
+struct Field {
+    /// The field identifier, possibly a raw identifier like `r#type`.
+    ident: String,
+    /// The field name without a raw identifier prefix, used as map key.
+    key: String,
+    ty: String,
+}
+
+fn parse_struct(input: TokenStream) -> Result<Struct, String> {
+    let mut tokens = input.into_iter().peekable();
+    let mut name = None;
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Ident(ident) if ident.to_string() == "struct" => match tokens.next() {
+                Some(TokenTree::Ident(ident)) => {
+                    name = Some(ident.to_string());
+                    break;
+                }
+                _ => return Err("expected a struct name".into()),
+            },
+            TokenTree::Ident(ident) if ident.to_string() == "enum" || ident.to_string() == "union" => {
+                return Err("only structs with named fields are supported".into());
+            }
+            _ => {}
+        }
+    }
+    let name = name.ok_or("expected a struct")?;
+    match tokens.next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => Ok(Struct {
+            name,
+            fields: parse_fields(group.stream())?,
+        }),
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '<' => {
+            Err("generic structs are not supported".into())
+        }
+        _ => Err("only structs with named fields are supported".into()),
+    }
+}
+
+fn parse_fields(input: TokenStream) -> Result<Vec<Field>, String> {
+    let mut fields = Vec::new();
+    let mut tokens = input.into_iter().peekable();
+    loop {
+        // Skip attributes and visibility.
+        let name = loop {
+            match tokens.next() {
+                None => return Ok(fields),
+                Some(TokenTree::Punct(punct)) if punct.as_char() == '#' => {
+                    tokens.next();
+                }
+                Some(TokenTree::Ident(ident)) if ident.to_string() == "pub" => {
+                    if let Some(TokenTree::Group(group)) = tokens.peek() {
+                        if group.delimiter() == Delimiter::Parenthesis {
+                            tokens.next();
+                        }
+                    }
+                }
+                Some(TokenTree::Ident(ident)) => break ident.to_string(),
+                Some(token) => return Err(format!("unexpected token `{token}` in struct fields")),
+            }
+        };
+        match tokens.next() {
+            Some(TokenTree::Punct(punct)) if punct.as_char() == ':' => {}
+            _ => return Err(format!("expected `:` after field `{name}`")),
+        }
+        let mut ty = TokenStream::new();
+        let mut depth = 0usize;
+        let mut after_dash = false;
+        for token in tokens.by_ref() {
+            if let TokenTree::Punct(punct) = &token {
+                match punct.as_char() {
+                    ',' if depth == 0 => break,
+                    '<' => depth += 1,
+                    '>' if !after_dash => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+                after_dash = punct.as_char() == '-' && punct.spacing() == Spacing::Joint;
+            } else {
+                after_dash = false;
+            }
+            ty.extend([token]);
+        }
+        fields.push(Field {
+            key: name.trim_start_matches("r#").to_owned(),
+            ident: name,
+            ty: ty.to_string(),
+        });
+    }
+}
+
+fn compile_error(message: &str) -> TokenStream {
+    format!("::core::compile_error!({message:?});").parse().unwrap()
+}
+
+/// Implements `TryFrom<&AnyMap<String>>` for a struct with named fields by
+/// looking up every field under its name and cloning the stored value.
+///
+/// Fails with `anymap::FromAnyMapError` if an entry is missing or holds a
+/// value of another type.
+#[proc_macro_derive(FromAnyMap)]
+pub fn derive_from_any_map(input: TokenStream) -> TokenStream {
+    let item = match parse_struct(input) {
+        Ok(item) => item,
+        Err(message) => return compile_error(&message),
+    };
+    let fields: String = item
+        .fields
+        .iter()
+        .map(|field| {
+            format!(
+                "{ident}: ::anymap::__private::get_field::<{ty}>(map, {key:?})?,",
+                ident = field.ident,
+                ty = field.ty,
+                key = field.key,
+            )
+        })
+        .collect();
+    format!(
+        "impl ::core::convert::TryFrom<&::anymap::AnyMap<::anymap::__private::String>> for {name} {{
+            type Error = ::anymap::FromAnyMapError;
+
+            fn try_from(map: &::anymap::AnyMap<::anymap::__private::String>) -> ::core::result::Result<Self, Self::Error> {{
+                ::core::result::Result::Ok({name} {{ {fields} }})
+            }}
+        }}",
+        name = item.name,
+    )
+    .parse()
+    .unwrap()
+}
+
+/// Implements `From<Struct> for AnyMap<String>` for a struct with named
+/// fields by inserting every field under its name.
+#[proc_macro_derive(IntoAnyMap)]
+pub fn derive_into_any_map(input: TokenStream) -> TokenStream {
+    let item = match parse_struct(input) {
+        Ok(item) => item,
+        Err(message) => return compile_error(&message),
+    };
+    let inserts: String = item
+        .fields
+        .iter()
+        .map(|field| {
+            format!(
+                "map.insert(::anymap::__private::String::from({key:?}), value.{ident});",
+                ident = field.ident,
+                key = field.key,
+            )
+        })
+        .collect();
+    format!(
+        "impl ::core::convert::From<{name}> for ::anymap::AnyMap<::anymap::__private::String> {{
+            fn from(value: {name}) -> Self {{
+                let mut map = ::anymap::AnyMap::with_capacity({len});
+                {inserts}
+                map
+            }}
+        }}",
+        name = item.name,
+        len = item.fields.len(),
+    )
+    .parse()
+    .unwrap()
+}
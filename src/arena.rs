@@ -0,0 +1,352 @@
+//! Maps allocating their values in a bump arena.
+
+use alloc::{alloc::Layout, string::String, vec::Vec};
+use core::{
+    any::Any,
+    borrow::Borrow,
+    hash::{BuildHasher, Hash},
+    marker::PhantomData,
+    mem,
+    ptr::{self, NonNull},
+};
+
+use crate::{hash::HashMap, DefaultHashBuilder, HashKey, Value};
+
+/// Size of the first chunk allocated by an arena. Later chunks double in size.
+const FIRST_CHUNK: usize = 4096;
+const CHUNK_ALIGN: usize = 16;
+
+struct Chunk {
+    ptr: NonNull<u8>,
+    layout: Layout,
+}
+
+impl Chunk {
+    fn new(size: usize) -> Self {
+        let layout = Layout::from_size_align(size, CHUNK_ALIGN).expect("arena chunk too large");
+        let ptr = unsafe { alloc::alloc::alloc(layout) };
+        let ptr = NonNull::new(ptr).unwrap_or_else(|| alloc::alloc::handle_alloc_error(layout));
+        Chunk { ptr, layout }
+    }
+}
+
+impl Drop for Chunk {
+    fn drop(&mut self) {
+        unsafe { alloc::alloc::dealloc(self.ptr.as_ptr(), self.layout) }
+    }
+}
+
+/// Hands out memory from a list of chunks, only freeing it all at once.
+#[derive(Default)]
+struct Bump {
+    // Allocations are taken from the end of the last chunk.
+    chunks: Vec<Chunk>,
+    used: usize,
+}
+
+impl Bump {
+    fn alloc(&mut self, layout: Layout) -> NonNull<u8> {
+        if layout.size() == 0 {
+            return NonNull::new(ptr::without_provenance_mut(layout.align())).unwrap();
+        }
+        if let Some(ptr) = self.alloc_in_last(layout) {
+            return ptr;
+        }
+        let next = self.chunks.last().map_or(FIRST_CHUNK, |chunk| chunk.layout.size() * 2);
+        self.chunks.push(Chunk::new(next.max(layout.size() + layout.align())));
+        self.used = 0;
+        self.alloc_in_last(layout).unwrap()
+    }
+
+    fn alloc_in_last(&mut self, layout: Layout) -> Option<NonNull<u8>> {
+        let chunk = self.chunks.last()?;
+        let base = chunk.ptr.as_ptr();
+        let start = (base as usize + self.used).next_multiple_of(layout.align()) - base as usize;
+        let end = start.checked_add(layout.size())?;
+        if end > chunk.layout.size() {
+            return None;
+        }
+        self.used = end;
+        NonNull::new(unsafe { base.add(start) })
+    }
+
+    /// Makes all memory available again, keeping only the largest chunk.
+    fn reset(&mut self) {
+        if let Some(last) = self.chunks.pop() {
+            self.chunks.clear();
+            self.chunks.push(last);
+        }
+        self.used = 0;
+    }
+
+    fn allocated(&self) -> usize {
+        self.chunks.iter().map(|chunk| chunk.layout.size()).sum()
+    }
+}
+
+/// Operations on a value of some type living in the arena.
+struct SlotVTable {
+    as_any: unsafe fn(*const u8) -> *const dyn Any,
+    as_any_mut: unsafe fn(*mut u8) -> *mut dyn Any,
+    take: unsafe fn(*mut u8) -> Value,
+    // `None` for types without drop glue, which need no work on `clear`.
+    drop: Option<unsafe fn(*mut u8)>,
+}
+
+struct VTableFor<T>(PhantomData<T>);
+
+impl<T: Any> VTableFor<T> {
+    const VTABLE: SlotVTable = SlotVTable {
+        as_any: |ptr| ptr.cast::<T>(),
+        as_any_mut: |ptr| ptr.cast::<T>(),
+        take: |ptr| Value::new(unsafe { ptr.cast::<T>().read() }),
+        drop: if mem::needs_drop::<T>() {
+            Some(|ptr| unsafe { ptr.cast::<T>().drop_in_place() })
+        } else {
+            None
+        },
+    };
+}
+
+/// A value owned by the map, stored in its arena.
+struct Slot {
+    ptr: NonNull<u8>,
+    vtable: &'static SlotVTable,
+}
+
+impl Slot {
+    fn as_any(&self) -> &dyn Any {
+        unsafe { &*(self.vtable.as_any)(self.ptr.as_ptr()) }
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        unsafe { &mut *(self.vtable.as_any_mut)(self.ptr.as_ptr()) }
+    }
+
+    /// Moves the value out of the arena. Its memory is only reused once the
+    /// arena is reset.
+    fn take(self) -> Value {
+        unsafe { (self.vtable.take)(self.ptr.as_ptr()) }
+    }
+
+    /// # Safety
+    ///
+    /// The value must not be used afterwards.
+    unsafe fn drop_value(&mut self) {
+        if let Some(drop) = self.vtable.drop {
+            drop(self.ptr.as_ptr());
+        }
+    }
+}
+
+/// A map with values of any type, all allocated in an arena owned by the
+/// map.
+///
+/// Values sit next to each other in memory, and clearing or dropping the map
+/// frees them in one go; only values of types with a destructor are visited.
+/// Memory of removed or replaced values is not reused until the map is
+/// cleared, so this suits short-lived scratch maps that are filled and then
+/// cleared as a whole.
+///
+/// ```
+/// use anymap::ArenaAnyMap;
+///
+/// let mut frame = ArenaAnyMap::<&str>::new();
+/// for i in 0..3 {
+///     frame.insert("frame", i);
+///     frame.insert("name", String::from("scratch"));
+///     assert_eq!(frame.get_typed::<i32>("frame"), Some(&i));
+///     frame.clear();
+/// }
+/// ```
+pub struct ArenaAnyMap<K = String, S = DefaultHashBuilder> {
+    slots: HashMap<K, Slot, S>,
+    arena: Bump,
+}
+
+impl<K> ArenaAnyMap<K> {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K, S: Default> Default for ArenaAnyMap<K, S> {
+    #[inline]
+    fn default() -> Self {
+        ArenaAnyMap {
+            slots: HashMap::default(),
+            arena: Bump::default(),
+        }
+    }
+}
+
+impl<K, S> ArenaAnyMap<K, S> {
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Returns the number of bytes held by the arena, including space of
+    /// values that were removed.
+    #[inline]
+    pub fn allocated_bytes(&self) -> usize {
+        self.arena.allocated()
+    }
+
+    /// Removes all values and makes the arena's memory available again.
+    pub fn clear(&mut self) {
+        for (_, mut slot) in self.slots.drain() {
+            unsafe { slot.drop_value() }
+        }
+        self.arena.reset();
+    }
+
+    #[inline]
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.slots.keys()
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &dyn Any)> {
+        self.slots.iter().map(|(k, slot)| (k, slot.as_any()))
+    }
+
+    #[inline]
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut dyn Any)> {
+        self.slots.iter_mut().map(|(k, slot)| (k, slot.as_any_mut()))
+    }
+}
+
+impl<K: Eq + Hash, S: BuildHasher> ArenaAnyMap<K, S> {
+    /// Moves `value` into the arena, returning the value previously stored
+    /// under `key`.
+    pub fn insert<T: Any>(&mut self, key: K, value: T) -> Option<Value> {
+        let ptr = self.arena.alloc(Layout::new::<T>());
+        unsafe { ptr.cast::<T>().write(value) };
+        let slot = Slot {
+            ptr,
+            vtable: &VTableFor::<T>::VTABLE,
+        };
+        self.slots.insert(key, slot).map(Slot::take)
+    }
+
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<&dyn Any>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.slots.get(key).map(Slot::as_any)
+    }
+
+    #[inline]
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut dyn Any>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.slots.get_mut(key).map(Slot::as_any_mut)
+    }
+
+    #[inline]
+    pub fn get_typed<T: Any>(&self, key: &(impl ?Sized + HashKey<K>)) -> Option<&T> {
+        key.get_in(&self.slots).map(Slot::as_any)?.downcast_ref::<T>()
+    }
+
+    #[inline]
+    pub fn get_typed_mut<T: Any>(&mut self, key: &(impl ?Sized + HashKey<K>)) -> Option<&mut T> {
+        key.get_mut_in(&mut self.slots).map(Slot::as_any_mut)?.downcast_mut::<T>()
+    }
+
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.slots.contains_key(key)
+    }
+
+    /// Moves the value out of the arena into a [`Value`].
+    #[inline]
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<Value>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.slots.remove(key).map(Slot::take)
+    }
+}
+
+impl<K, S> Drop for ArenaAnyMap<K, S> {
+    fn drop(&mut self) {
+        for slot in self.slots.values_mut() {
+            unsafe { slot.drop_value() }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+
+    struct Guard(Rc<Cell<u32>>);
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn arena_values() {
+        #[repr(align(64))]
+        struct Aligned(u8);
+
+        let mut map = ArenaAnyMap::<u32>::new();
+        map.insert(1, 1u8);
+        map.insert(2, Aligned(2));
+        map.insert(3, ());
+        map.insert(4, vec![0u64; 1024]);
+        assert_eq!(map.get_typed::<Aligned>(&2).unwrap().0, 2);
+        assert!((map.get_typed::<Aligned>(&2).unwrap() as *const Aligned).is_aligned());
+        assert_eq!(map.get_typed::<Vec<u64>>(&4).unwrap().len(), 1024);
+
+        *map.get_typed_mut::<u8>(&1).unwrap() += 1;
+        let old = map.insert(1, "one").unwrap();
+        assert_eq!(old.downcast::<u8>().unwrap(), 2);
+        assert!(map.remove(&3).unwrap().is::<()>());
+        assert!(!map.contains_key(&3));
+        assert_eq!(map.iter().filter(|(_, v)| v.is::<&str>()).count(), 1);
+    }
+
+    #[test]
+    fn arena_clear_reuses_memory() {
+        let drops = Rc::new(Cell::new(0));
+        let mut map = ArenaAnyMap::<usize>::new();
+        for round in 0..3 {
+            for i in 0..1000 {
+                map.insert(i, Guard(drops.clone()));
+                map.insert(i + 1000, [round as u64; 4]);
+            }
+            assert_eq!(map.len(), 2000);
+            map.clear();
+            assert_eq!(drops.get(), 1000 * (round + 1));
+        }
+        let allocated = map.allocated_bytes();
+        map.insert(0, 0u64);
+        assert_eq!(map.allocated_bytes(), allocated);
+
+        map.insert(1, Guard(drops.clone()));
+        drop(map);
+        assert_eq!(drops.get(), 3001);
+    }
+}
@@ -0,0 +1,542 @@
+//! Storage of the entries of an [`AnyMap`](crate::AnyMap).
+
+use alloc::collections::{btree_map, BTreeMap};
+use core::{
+    borrow::Borrow,
+    hash::{BuildHasher, Hash},
+    ops::RangeFull,
+};
+
+use crate::{
+    hash::{self, hash_map, HashMap},
+    DefaultHashBuilder, Entry, OccupiedEntry, VacantEntry, Value,
+};
+
+/// A container mapping keys of type `K` to [`Value`]s, used as the storage of
+/// an [`AnyMap`](crate::AnyMap).
+///
+/// Lookups by a borrowed form of the key are in [`MapLookup`], so that each
+/// backend only asks of keys what it uses itself.
+pub trait MapBackend<K> {
+    type Iter<'a>: Iterator<Item = (&'a K, &'a Value)>
+    where
+        Self: 'a,
+        K: 'a;
+    type IterMut<'a>: Iterator<Item = (&'a K, &'a mut Value)>
+    where
+        Self: 'a,
+        K: 'a;
+    type IntoIter: Iterator<Item = (K, Value)>;
+    type Occupied<'a>: OccupiedBackendEntry<'a, K>
+    where
+        Self: 'a,
+        K: 'a;
+    type Vacant<'a>: VacantBackendEntry<'a, K>
+    where
+        Self: 'a,
+        K: 'a;
+    type ExtractIf<'a, F>: Iterator<Item = (K, Value)>
+    where
+        Self: 'a,
+        K: 'a,
+        F: FnMut(&K, &mut Value) -> bool;
+
+    fn len(&self) -> usize;
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn insert(&mut self, key: K, value: Value) -> Option<Value>;
+
+    fn clear(&mut self);
+
+    fn iter(&self) -> Self::Iter<'_>;
+
+    fn iter_mut(&mut self) -> Self::IterMut<'_>;
+
+    fn into_entries(self) -> Self::IntoIter;
+
+    /// Returns the entry for `key`, wrapped with [`OccupiedEntry::new`] or
+    /// [`VacantEntry::new`].
+    fn entry(&mut self, key: K) -> Entry<'_, K, Self>
+    where
+        Self: Sized;
+
+    /// Returns an iterator which removes and yields every entry for which
+    /// `pred` returns `true`, keeping those not yet reached when it is
+    /// dropped.
+    fn extract_if<F>(&mut self, pred: F) -> Self::ExtractIf<'_, F>
+    where
+        F: FnMut(&K, &mut Value) -> bool;
+
+    #[inline]
+    fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut Value) -> bool,
+    {
+        self.extract_if(|k, v| !f(k, v)).for_each(drop)
+    }
+}
+
+/// An occupied entry of a [`MapBackend`], wrapped by [`OccupiedEntry`].
+pub trait OccupiedBackendEntry<'a, K> {
+    fn key(&self) -> &K;
+
+    fn get(&self) -> &Value;
+
+    fn get_mut(&mut self) -> &mut Value;
+
+    fn into_mut(self) -> &'a mut Value;
+
+    fn remove_entry(self) -> (K, Value);
+}
+
+/// A vacant entry of a [`MapBackend`], wrapped by [`VacantEntry`].
+pub trait VacantBackendEntry<'a, K> {
+    fn key(&self) -> &K;
+
+    fn into_key(self) -> K;
+
+    fn insert(self, value: Value) -> &'a mut Value;
+}
+
+/// Lookups in a [`MapBackend`] by `Q`, a borrowed form of its keys.
+///
+/// Implemented for whichever `Q` the backend can find keys by: hashing
+/// backends need `Q: Hash + Eq`, a `BTreeMap` needs `Q: Ord`.
+pub trait MapLookup<K, Q: ?Sized>: MapBackend<K> {
+    fn get(&self, key: &Q) -> Option<&Value>;
+
+    fn get_mut(&mut self, key: &Q) -> Option<&mut Value>;
+
+    fn remove(&mut self, key: &Q) -> Option<Value>;
+}
+
+/// A key that a map with the backend `B` can be looked up by.
+///
+/// Implemented for every `Q` that `B` implements [`MapLookup<K, Q>`] for.
+/// Typed accessors take their key as `&impl LookupKey<K, B>`, so that only
+/// the value type has to be named, as in `map.get_typed::<i32>("key")`.
+pub trait LookupKey<K, B> {
+    fn get_in<'a>(&self, backend: &'a B) -> Option<&'a Value>;
+
+    fn get_mut_in<'a>(&self, backend: &'a mut B) -> Option<&'a mut Value>;
+
+    fn remove_from(&self, backend: &mut B) -> Option<Value>;
+}
+
+impl<K, Q: ?Sized, B: MapLookup<K, Q>> LookupKey<K, B> for Q {
+    #[inline]
+    fn get_in<'a>(&self, backend: &'a B) -> Option<&'a Value> {
+        backend.get(self)
+    }
+
+    #[inline]
+    fn get_mut_in<'a>(&self, backend: &'a mut B) -> Option<&'a mut Value> {
+        backend.get_mut(self)
+    }
+
+    #[inline]
+    fn remove_from(&self, backend: &mut B) -> Option<Value> {
+        backend.remove(self)
+    }
+}
+
+/// Like [`LookupKey`], for maps that keep their entries in a plain
+/// `HashMap<K, V, S>`, such as an [`AnyMultiTypeMap`](crate::AnyMultiTypeMap).
+///
+/// Implemented for every `Q: Hash + Eq` that `K` borrows as.
+pub trait HashKey<K> {
+    fn get_in<'a, V, S: BuildHasher>(&self, map: &'a HashMap<K, V, S>) -> Option<&'a V>;
+
+    fn get_mut_in<'a, V, S: BuildHasher>(&self, map: &'a mut HashMap<K, V, S>) -> Option<&'a mut V>;
+
+    fn remove_from<V, S: BuildHasher>(&self, map: &mut HashMap<K, V, S>) -> Option<V>;
+}
+
+impl<K: Eq + Hash + Borrow<Q>, Q: ?Sized + Hash + Eq> HashKey<K> for Q {
+    #[inline]
+    fn get_in<'a, V, S: BuildHasher>(&self, map: &'a HashMap<K, V, S>) -> Option<&'a V> {
+        map.get(self)
+    }
+
+    #[inline]
+    fn get_mut_in<'a, V, S: BuildHasher>(&self, map: &'a mut HashMap<K, V, S>) -> Option<&'a mut V> {
+        map.get_mut(self)
+    }
+
+    #[inline]
+    fn remove_from<V, S: BuildHasher>(&self, map: &mut HashMap<K, V, S>) -> Option<V> {
+        map.remove(self)
+    }
+}
+
+/// The storage [`AnyMap`](crate::AnyMap) uses unless told otherwise.
+pub type HashBackend<K, S = DefaultHashBuilder> = HashMap<K, Value, S>;
+
+impl<K: Eq + Hash, S: BuildHasher> MapBackend<K> for HashMap<K, Value, S> {
+    type Iter<'a>
+        = hash_map::Iter<'a, K, Value>
+    where
+        Self: 'a,
+        K: 'a;
+    type IterMut<'a>
+        = hash_map::IterMut<'a, K, Value>
+    where
+        Self: 'a,
+        K: 'a;
+    type IntoIter = hash_map::IntoIter<K, Value>;
+    type Occupied<'a>
+        = hash_map::OccupiedEntry<'a, K, Value>
+    where
+        Self: 'a,
+        K: 'a;
+    type Vacant<'a>
+        = hash_map::VacantEntry<'a, K, Value>
+    where
+        Self: 'a,
+        K: 'a;
+    type ExtractIf<'a, F>
+        = hash_map::ExtractIf<'a, K, Value, F>
+    where
+        Self: 'a,
+        K: 'a,
+        F: FnMut(&K, &mut Value) -> bool;
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    #[inline]
+    fn insert(&mut self, key: K, value: Value) -> Option<Value> {
+        self.insert(key, value)
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.clear()
+    }
+
+    #[inline]
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
+    }
+
+    #[inline]
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        self.iter_mut()
+    }
+
+    #[inline]
+    fn into_entries(self) -> Self::IntoIter {
+        self.into_iter()
+    }
+
+    #[inline]
+    fn entry(&mut self, key: K) -> Entry<'_, K, Self> {
+        match hash::entry(self, key) {
+            hash_map::Entry::Occupied(entry) => Entry::Occupied(OccupiedEntry::new(entry)),
+            hash_map::Entry::Vacant(entry) => Entry::Vacant(VacantEntry::new(entry)),
+        }
+    }
+
+    #[inline]
+    fn extract_if<F>(&mut self, pred: F) -> Self::ExtractIf<'_, F>
+    where
+        F: FnMut(&K, &mut Value) -> bool,
+    {
+        self.extract_if(pred)
+    }
+
+    #[inline]
+    fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&K, &mut Value) -> bool,
+    {
+        self.retain(f)
+    }
+}
+
+impl<'a, K> OccupiedBackendEntry<'a, K> for hash_map::OccupiedEntry<'a, K, Value> {
+    #[inline]
+    fn key(&self) -> &K {
+        self.key()
+    }
+
+    #[inline]
+    fn get(&self) -> &Value {
+        self.get()
+    }
+
+    #[inline]
+    fn get_mut(&mut self) -> &mut Value {
+        self.get_mut()
+    }
+
+    #[inline]
+    fn into_mut(self) -> &'a mut Value {
+        self.into_mut()
+    }
+
+    #[inline]
+    fn remove_entry(self) -> (K, Value) {
+        self.remove_entry()
+    }
+}
+
+impl<'a, K> VacantBackendEntry<'a, K> for hash_map::VacantEntry<'a, K, Value> {
+    #[inline]
+    fn key(&self) -> &K {
+        self.key()
+    }
+
+    #[inline]
+    fn into_key(self) -> K {
+        self.into_key()
+    }
+
+    #[inline]
+    fn insert(self, value: Value) -> &'a mut Value {
+        self.insert(value)
+    }
+}
+
+impl<K: Eq + Hash, S: BuildHasher, Q> MapLookup<K, Q> for HashMap<K, Value, S>
+where
+    K: Borrow<Q>,
+    Q: ?Sized + Hash + Eq,
+{
+    #[inline]
+    fn get(&self, key: &Q) -> Option<&Value> {
+        self.get(key)
+    }
+
+    #[inline]
+    fn get_mut(&mut self, key: &Q) -> Option<&mut Value> {
+        self.get_mut(key)
+    }
+
+    #[inline]
+    fn remove(&mut self, key: &Q) -> Option<Value> {
+        self.remove(key)
+    }
+}
+
+impl<K: Ord> MapBackend<K> for BTreeMap<K, Value> {
+    type Iter<'a>
+        = btree_map::Iter<'a, K, Value>
+    where
+        Self: 'a,
+        K: 'a;
+    type IterMut<'a>
+        = btree_map::IterMut<'a, K, Value>
+    where
+        Self: 'a,
+        K: 'a;
+    type IntoIter = btree_map::IntoIter<K, Value>;
+    type Occupied<'a>
+        = btree_map::OccupiedEntry<'a, K, Value>
+    where
+        Self: 'a,
+        K: 'a;
+    type Vacant<'a>
+        = btree_map::VacantEntry<'a, K, Value>
+    where
+        Self: 'a,
+        K: 'a;
+    type ExtractIf<'a, F>
+        = btree_map::ExtractIf<'a, K, Value, RangeFull, F>
+    where
+        Self: 'a,
+        K: 'a,
+        F: FnMut(&K, &mut Value) -> bool;
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    #[inline]
+    fn insert(&mut self, key: K, value: Value) -> Option<Value> {
+        self.insert(key, value)
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.clear()
+    }
+
+    #[inline]
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
+    }
+
+    #[inline]
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        self.iter_mut()
+    }
+
+    #[inline]
+    fn into_entries(self) -> Self::IntoIter {
+        self.into_iter()
+    }
+
+    #[inline]
+    fn entry(&mut self, key: K) -> Entry<'_, K, Self> {
+        match self.entry(key) {
+            btree_map::Entry::Occupied(entry) => Entry::Occupied(OccupiedEntry::new(entry)),
+            btree_map::Entry::Vacant(entry) => Entry::Vacant(VacantEntry::new(entry)),
+        }
+    }
+
+    #[inline]
+    fn extract_if<F>(&mut self, pred: F) -> Self::ExtractIf<'_, F>
+    where
+        F: FnMut(&K, &mut Value) -> bool,
+    {
+        self.extract_if(.., pred)
+    }
+
+    #[inline]
+    fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&K, &mut Value) -> bool,
+    {
+        self.retain(f)
+    }
+}
+
+impl<'a, K: Ord> OccupiedBackendEntry<'a, K> for btree_map::OccupiedEntry<'a, K, Value> {
+    #[inline]
+    fn key(&self) -> &K {
+        self.key()
+    }
+
+    #[inline]
+    fn get(&self) -> &Value {
+        self.get()
+    }
+
+    #[inline]
+    fn get_mut(&mut self) -> &mut Value {
+        self.get_mut()
+    }
+
+    #[inline]
+    fn into_mut(self) -> &'a mut Value {
+        self.into_mut()
+    }
+
+    #[inline]
+    fn remove_entry(self) -> (K, Value) {
+        self.remove_entry()
+    }
+}
+
+impl<'a, K: Ord> VacantBackendEntry<'a, K> for btree_map::VacantEntry<'a, K, Value> {
+    #[inline]
+    fn key(&self) -> &K {
+        self.key()
+    }
+
+    #[inline]
+    fn into_key(self) -> K {
+        self.into_key()
+    }
+
+    #[inline]
+    fn insert(self, value: Value) -> &'a mut Value {
+        self.insert(value)
+    }
+}
+
+impl<K: Ord, Q> MapLookup<K, Q> for BTreeMap<K, Value>
+where
+    K: Borrow<Q>,
+    Q: ?Sized + Ord,
+{
+    #[inline]
+    fn get(&self, key: &Q) -> Option<&Value> {
+        self.get(key)
+    }
+
+    #[inline]
+    fn get_mut(&mut self, key: &Q) -> Option<&mut Value> {
+        self.get_mut(key)
+    }
+
+    #[inline]
+    fn remove(&mut self, key: &Q) -> Option<Value> {
+        self.remove(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AnyMap;
+
+    fn exercise<B: MapLookup<&'static str, str> + Default>() {
+        let mut map = AnyMap::<&str, B>::default();
+        map.insert("a", 1);
+        map.insert("b", "two");
+        *map.get_typed_mut::<i32>("a").unwrap() += 1;
+        assert_eq!(map.get_typed::<i32>("a"), Some(&2));
+        assert_eq!(map.remove_typed::<i32>("b"), None);
+        assert!(map.contains_key("b"));
+        assert!(map.remove("b").is_some());
+        assert_eq!(map.len(), 1);
+
+        assert!(map.entry("c").or_insert(Value::new(3)).is::<i32>());
+        *map.entry_typed::<i32>("a").or_insert(0).unwrap() += 1;
+        assert_eq!(map.get_typed::<i32>("a"), Some(&3));
+        match map.entry("c") {
+            Entry::Occupied(entry) => assert!(entry.remove().is::<i32>()),
+            Entry::Vacant(_) => unreachable!(),
+        }
+        map.insert("d", "four");
+        map.insert("e", 5);
+        assert_eq!(map.values_typed::<i32>().sum::<i32>(), 8);
+        assert_eq!(map.iter_typed::<&str>().map(|(k, _)| *k).collect::<Vec<_>>(), ["d"]);
+
+        assert_eq!(map.extract_if(|_, v| v.is::<&str>()).count(), 1);
+        map.retain(|k, _| *k != "e");
+        assert_eq!(map.into_iter().count(), 1);
+    }
+
+    #[test]
+    fn backends() {
+        exercise::<HashBackend<&str>>();
+        exercise::<BTreeMap<&str, Value>>();
+        exercise::<crate::OrderedBackend<&str>>();
+        exercise::<crate::SmallBackend<&str, DefaultHashBuilder, 2>>();
+        exercise::<crate::FixedBackend<&str, 4>>();
+    }
+
+    #[test]
+    fn keys_without_ord() {
+        #[derive(PartialEq, Eq, Hash)]
+        struct Id(u32);
+
+        fn exercise<B: MapLookup<Id, Id> + Default>() {
+            let mut map = AnyMap::<Id, B>::default();
+            map.insert(Id(1), 1);
+            map.insert(Id(2), "two");
+            *map.get_typed_mut::<i32>(&Id(1)).unwrap() += 1;
+            assert_eq!(map.get_typed::<i32>(&Id(1)), Some(&2));
+            assert!(map.remove(&Id(2)).is_some());
+            assert!(!map.contains_key(&Id(2)));
+            assert_eq!(*map.entry_typed::<i32>(Id(3)).or_insert(3).unwrap(), 3);
+            assert_eq!(map.extract_if(|k, _| k.0 == 1).count(), 1);
+            assert_eq!(map.values_typed::<i32>().collect::<Vec<_>>(), [&3]);
+        }
+
+        exercise::<HashBackend<Id>>();
+        exercise::<crate::OrderedBackend<Id>>();
+        exercise::<crate::SmallBackend<Id, DefaultHashBuilder, 1>>();
+        exercise::<crate::FixedBackend<Id, 2>>();
+    }
+}
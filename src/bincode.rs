@@ -0,0 +1,90 @@
+//! Bincode checkpoints of maps of registered types.
+
+use bincode::{
+    config,
+    error::{DecodeError, EncodeError},
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{AnyMap, MapBackend};
+
+impl<K: Serialize, B: MapBackend<K>> AnyMap<K, B> {
+    /// Encodes the map with bincode's standard configuration. Requires the
+    /// `bincode` feature.
+    ///
+    /// Every value's type must be registered with
+    /// [`register_serde`](crate::registry::register_serde). The output is not
+    /// self-describing, so it can only be read back by
+    /// [`from_bincode`](Self::from_bincode) with the same types registered.
+    ///
+    /// ```
+    /// use anymap::AnyMap;
+    ///
+    /// let mut map = AnyMap::<String>::new();
+    /// map.insert("port".to_owned(), 8080u16);
+    /// let bytes = map.to_bincode().unwrap();
+    /// let loaded = AnyMap::<String>::from_bincode(&bytes).unwrap();
+    /// assert_eq!(loaded.get_typed::<u16>("port"), Some(&8080));
+    /// ```
+    pub fn to_bincode(&self) -> Result<Vec<u8>, EncodeError> {
+        bincode::serde::encode_to_vec(self, config::standard())
+    }
+}
+
+impl<K: DeserializeOwned, B: MapBackend<K> + Default> AnyMap<K, B> {
+    /// Decodes a map written by [`to_bincode`](Self::to_bincode). Requires
+    /// the `bincode` feature.
+    pub fn from_bincode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let (map, read) = bincode::serde::decode_from_slice(bytes, config::standard())?;
+        if read != bytes.len() {
+            return Err(DecodeError::Other("trailing bytes after map"));
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::registry;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Session {
+        user: String,
+        expires: Option<u64>,
+    }
+
+    #[test]
+    fn round_trip() {
+        registry::register_serde::<Session>("bincode::Session");
+
+        let session = Session {
+            user: "alice".to_owned(),
+            expires: Some(1_700_000_000),
+        };
+        let mut map = AnyMap::<String>::new();
+        map.insert("retries".to_owned(), 3u8);
+        map.insert("name".to_owned(), "static");
+        map.insert("session".to_owned(), session);
+        let bytes = map.to_bincode().unwrap();
+
+        let loaded = AnyMap::<String>::from_bincode(&bytes).unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded.get_typed::<u8>("retries"), Some(&3));
+        assert_eq!(loaded.get_typed::<String>("name").unwrap(), "static");
+        assert_eq!(loaded.get_typed::<Session>("session").unwrap().expires, Some(1_700_000_000));
+    }
+
+    #[test]
+    fn malformed() {
+        let mut map = AnyMap::<String>::new();
+        map.insert("retries".to_owned(), 3u8);
+        let mut bytes = map.to_bincode().unwrap();
+        bytes.push(0);
+        assert!(matches!(AnyMap::<String>::from_bincode(&bytes), Err(DecodeError::Other(_))));
+        bytes.truncate(bytes.len() - 2);
+        assert!(AnyMap::<String>::from_bincode(&bytes).is_err());
+    }
+}
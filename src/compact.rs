@@ -0,0 +1,215 @@
+//! String keys that store short strings inline.
+
+use alloc::{borrow::ToOwned, boxed::Box, string::String};
+use core::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    str,
+};
+
+use crate::{AnyMap, DefaultHashBuilder, HashBackend};
+
+/// Longest string stored without an allocation. Keeps `CompactKey` as large
+/// as a `String`.
+const INLINE: usize = 22;
+
+/// An immutable string key that stores strings of up to 22 bytes inline,
+/// and longer ones in a `Box<str>`.
+///
+/// Hashes, compares and borrows like a `str`, so maps keyed by it can be
+/// queried with plain `&str`s.
+#[derive(Clone)]
+pub struct CompactKey(Repr);
+
+#[derive(Clone)]
+enum Repr {
+    // `buf[..len]` is valid UTF-8.
+    Inline { len: u8, buf: [u8; INLINE] },
+    Heap(Box<str>),
+}
+
+/// A map with values of any type keyed by [`CompactKey`]s, saving an
+/// allocation per entry for short keys.
+///
+/// ```
+/// use anymap::SmallKeyAnyMap;
+///
+/// let mut map = SmallKeyAnyMap::new();
+/// map.insert("user_id".into(), 7u64);
+/// assert_eq!(map.get_typed::<u64>("user_id"), Some(&7));
+/// assert!(map.keys().all(|key| key.is_inline()));
+/// ```
+pub type SmallKeyAnyMap<S = DefaultHashBuilder> = AnyMap<CompactKey, HashBackend<CompactKey, S>>;
+
+impl CompactKey {
+    #[inline]
+    pub fn new(key: &str) -> Self {
+        if key.len() > INLINE {
+            return CompactKey(Repr::Heap(key.into()));
+        }
+        let mut buf = [0; INLINE];
+        buf[..key.len()].copy_from_slice(key.as_bytes());
+        CompactKey(Repr::Inline {
+            len: key.len() as u8,
+            buf,
+        })
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        match &self.0 {
+            Repr::Inline { len, buf } => unsafe { str::from_utf8_unchecked(&buf[..*len as usize]) },
+            Repr::Heap(key) => key,
+        }
+    }
+
+    /// Returns whether the key is stored without an allocation.
+    #[inline]
+    pub fn is_inline(&self) -> bool {
+        matches!(self.0, Repr::Inline { .. })
+    }
+}
+
+impl Default for CompactKey {
+    #[inline]
+    fn default() -> Self {
+        CompactKey::new("")
+    }
+}
+
+impl Deref for CompactKey {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for CompactKey {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Borrow<str> for CompactKey {
+    #[inline]
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<&str> for CompactKey {
+    #[inline]
+    fn from(key: &str) -> Self {
+        CompactKey::new(key)
+    }
+}
+
+/// Reuses the allocation of long strings.
+impl From<String> for CompactKey {
+    #[inline]
+    fn from(key: String) -> Self {
+        if key.len() > INLINE {
+            CompactKey(Repr::Heap(key.into_boxed_str()))
+        } else {
+            CompactKey::new(&key)
+        }
+    }
+}
+
+impl From<CompactKey> for String {
+    #[inline]
+    fn from(key: CompactKey) -> Self {
+        match key.0 {
+            Repr::Heap(key) => key.into(),
+            Repr::Inline { .. } => key.as_str().to_owned(),
+        }
+    }
+}
+
+impl PartialEq for CompactKey {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for CompactKey {}
+
+impl PartialEq<str> for CompactKey {
+    #[inline]
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for CompactKey {
+    #[inline]
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialOrd for CompactKey {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CompactKey {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Hash for CompactKey {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl fmt::Debug for CompactKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for CompactKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compact_keys() {
+        assert_eq!(std::mem::size_of::<CompactKey>(), std::mem::size_of::<String>());
+
+        let short = CompactKey::from("héllo");
+        let long = CompactKey::from(String::from("a key longer than twenty-two bytes"));
+        assert!(short.is_inline());
+        assert!(!long.is_inline());
+        assert!(CompactKey::new(&"x".repeat(INLINE)).is_inline());
+        assert_eq!(short, "héllo");
+        assert_eq!(String::from(long.clone()), "a key longer than twenty-two bytes");
+        assert!(long < short);
+
+        let mut map = SmallKeyAnyMap::new();
+        map.insert(short, 1);
+        map.insert(long, 2);
+        assert_eq!(map.get_typed::<i32>("héllo"), Some(&1));
+        assert_eq!(map.get_typed::<i32>("a key longer than twenty-two bytes"), Some(&2));
+        assert_eq!(format!("{:?}", map.keys().min().unwrap()), "\"a key longer than twenty-two bytes\"");
+    }
+}
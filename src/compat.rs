@@ -0,0 +1,520 @@
+//! Drop-in replacements for the APIs of the classic type-keyed map crates.
+//!
+//! [`AnyMap`] mirrors `anymap::AnyMap`, with values keyed by their own type,
+//! and [`TypeMap`] mirrors `typemap::TypeMap`, with values keyed by a marker
+//! type implementing [`Key`]. [`Extensions`] mirrors `http::Extensions`, as
+//! used by middleware stacks. Switching the import is usually all that is
+//! needed to migrate.
+//!
+//! The variants of the classic crates whose values are `Send`, or `Send` and
+//! `Sync`, are [`SendAnyMap`] and [`SyncAnyMap`] for `anymap`'s
+//! `Map<dyn Any + Send>` and `Map<dyn Any + Send + Sync>`, and [`SendMap`]
+//! and [`ShareMap`] for `typemap`'s maps of the same names.
+//!
+//! ```
+//! use anymap::compat::{AnyMap, Entry};
+//!
+//! let mut map = AnyMap::new();
+//! map.insert(8080u16);
+//! *map.entry::<u16>().or_insert(0) += 1;
+//! assert_eq!(map.get::<u16>(), Some(&8081));
+//! assert!(matches!(map.entry::<u8>(), Entry::Vacant(_)));
+//! ```
+
+use core::{
+    any::{Any, TypeId},
+    marker::PhantomData,
+};
+
+use crate::{
+    hash::{self, hash_map, HashMap},
+    value::SLOT_INVARIANT,
+    Value,
+};
+
+/// A map keyed by the type of its values, like `anymap::AnyMap`.
+pub type AnyMap = crate::TypeMap;
+
+/// A map keyed by the type of its values, like `http::Extensions`.
+pub use crate::Extensions;
+
+/// A view into the slot for a single type, which may be either vacant or
+/// occupied.
+///
+/// Constructed by [`AnyMap::entry`](crate::TypeMap::entry) and
+/// [`TypeMap::entry`].
+pub enum Entry<'a, T> {
+    Occupied(OccupiedEntry<'a, T>),
+    Vacant(VacantEntry<'a, T>),
+}
+
+pub struct OccupiedEntry<'a, T> {
+    inner: hash_map::OccupiedEntry<'a, TypeId, Value>,
+    marker: PhantomData<T>,
+}
+
+pub struct VacantEntry<'a, T> {
+    inner: hash_map::VacantEntry<'a, TypeId, Value>,
+    marker: PhantomData<T>,
+}
+
+impl<'a, T: Any> Entry<'a, T> {
+    fn from_std(entry: hash_map::Entry<'a, TypeId, Value>) -> Self {
+        match entry {
+            hash_map::Entry::Occupied(inner) => Entry::Occupied(OccupiedEntry {
+                inner,
+                marker: PhantomData,
+            }),
+            hash_map::Entry::Vacant(inner) => Entry::Vacant(VacantEntry {
+                inner,
+                marker: PhantomData,
+            }),
+        }
+    }
+
+    #[inline]
+    pub fn or_insert(self, default: T) -> &'a mut T {
+        self.or_insert_with(|| default)
+    }
+
+    #[inline]
+    pub fn or_insert_with<F: FnOnce() -> T>(self, default: F) -> &'a mut T {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    #[inline]
+    pub fn or_default(self) -> &'a mut T
+    where
+        T: Default,
+    {
+        self.or_insert_with(T::default)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn and_modify<F: FnOnce(&mut T)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+}
+
+impl<'a, T: Any> OccupiedEntry<'a, T> {
+    #[inline]
+    pub fn get(&self) -> &T {
+        self.inner.get().as_type::<T>().expect(SLOT_INVARIANT)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.inner.get_mut().as_type_mut::<T>().expect(SLOT_INVARIANT)
+    }
+
+    #[inline]
+    pub fn into_mut(self) -> &'a mut T {
+        self.inner.into_mut().as_type_mut::<T>().expect(SLOT_INVARIANT)
+    }
+
+    #[inline]
+    pub fn insert(&mut self, value: T) -> T {
+        self.inner.insert(Value::new(value)).downcast::<T>().expect(SLOT_INVARIANT)
+    }
+
+    #[inline]
+    pub fn remove(self) -> T {
+        self.inner.remove().downcast::<T>().expect(SLOT_INVARIANT)
+    }
+}
+
+impl<'a, T: Any> VacantEntry<'a, T> {
+    #[inline]
+    pub fn insert(self, value: T) -> &'a mut T {
+        self.inner.insert(Value::new(value)).as_type_mut::<T>().expect(SLOT_INVARIANT)
+    }
+}
+
+impl crate::TypeMap {
+    /// Returns the slot for values of type `T`.
+    #[inline]
+    pub fn entry<T: Any>(&mut self) -> Entry<'_, T> {
+        Entry::from_std(hash::entry(&mut self.map, TypeId::of::<T>()))
+    }
+}
+
+/// A marker type naming a slot in a [`TypeMap`], like `typemap::Key`.
+pub trait Key: Any {
+    type Value: Any;
+}
+
+/// A map keyed by marker types implementing [`Key`], like
+/// `typemap::TypeMap`.
+///
+/// ```
+/// use anymap::compat::{Key, TypeMap};
+///
+/// struct Port;
+///
+/// impl Key for Port {
+///     type Value = u16;
+/// }
+///
+/// let mut map = TypeMap::new();
+/// map.insert::<Port>(8080);
+/// assert_eq!(map.get::<Port>(), Some(&8080));
+/// ```
+#[derive(Default)]
+pub struct TypeMap {
+    map: HashMap<TypeId, Value>,
+}
+
+impl TypeMap {
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        TypeMap {
+            map: HashMap::new(),
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+
+    #[inline]
+    pub fn get<K: Key>(&self) -> Option<&K::Value> {
+        self.map.get(&TypeId::of::<K>()).and_then(|v| v.as_type::<K::Value>())
+    }
+
+    #[inline]
+    pub fn get_mut<K: Key>(&mut self) -> Option<&mut K::Value> {
+        self.map.get_mut(&TypeId::of::<K>()).and_then(|v| v.as_type_mut::<K::Value>())
+    }
+
+    #[inline]
+    pub fn contains<K: Key>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<K>())
+    }
+
+    /// Inserts `value` under `K`, returning the previous value.
+    #[inline]
+    pub fn insert<K: Key>(&mut self, value: K::Value) -> Option<K::Value> {
+        self.map
+            .insert(TypeId::of::<K>(), Value::new(value))
+            .and_then(|v| v.downcast::<K::Value>().ok())
+    }
+
+    #[inline]
+    pub fn remove<K: Key>(&mut self) -> Option<K::Value> {
+        self.map.remove(&TypeId::of::<K>()).and_then(|v| v.downcast::<K::Value>().ok())
+    }
+
+    #[inline]
+    pub fn entry<K: Key>(&mut self) -> Entry<'_, K::Value> {
+        Entry::from_std(hash::entry(&mut self.map, TypeId::of::<K>()))
+    }
+}
+
+macro_rules! bounded_any_map {
+    ($(#[$attr:meta])* $name:ident: $first:ident $(+ $rest:ident)*) => {
+        $(#[$attr])*
+        #[derive(Default)]
+        pub struct $name {
+            // Only holds values with all of the map's bounds, which is why no
+            // `&mut Value` is handed out.
+            map: AnyMap,
+        }
+
+        // SAFETY: every method storing a value requires these bounds of it.
+        unsafe impl $first for $name {}
+        $(unsafe impl $rest for $name {})*
+
+        impl $name {
+            #[inline]
+            #[must_use]
+            pub fn new() -> Self {
+                Self::default()
+            }
+
+            #[inline]
+            pub fn len(&self) -> usize {
+                self.map.len()
+            }
+
+            #[inline]
+            pub fn is_empty(&self) -> bool {
+                self.map.is_empty()
+            }
+
+            #[inline]
+            pub fn clear(&mut self) {
+                self.map.clear()
+            }
+
+            #[inline]
+            pub fn get<T: Any>(&self) -> Option<&T> {
+                self.map.get::<T>()
+            }
+
+            #[inline]
+            pub fn get_mut<T: Any>(&mut self) -> Option<&mut T> {
+                self.map.get_mut::<T>()
+            }
+
+            #[inline]
+            pub fn contains<T: Any>(&self) -> bool {
+                self.map.contains::<T>()
+            }
+
+            #[inline]
+            pub fn insert<T: Any + $first $(+ $rest)*>(&mut self, value: T) -> Option<T> {
+                self.map.insert(value)
+            }
+
+            #[inline]
+            pub fn remove<T: Any>(&mut self) -> Option<T> {
+                self.map.remove::<T>()
+            }
+
+            #[inline]
+            pub fn entry<T: Any + $first $(+ $rest)*>(&mut self) -> Entry<'_, T> {
+                self.map.entry::<T>()
+            }
+
+            #[inline]
+            pub fn into_inner(self) -> AnyMap {
+                self.map
+            }
+        }
+    };
+}
+
+macro_rules! bounded_type_map {
+    ($(#[$attr:meta])* $name:ident: $first:ident $(+ $rest:ident)*) => {
+        $(#[$attr])*
+        #[derive(Default)]
+        pub struct $name {
+            // Only holds values with all of the map's bounds, which is why no
+            // `&mut Value` is handed out.
+            map: TypeMap,
+        }
+
+        // SAFETY: every method storing a value requires these bounds of it.
+        unsafe impl $first for $name {}
+        $(unsafe impl $rest for $name {})*
+
+        impl $name {
+            #[inline]
+            #[must_use]
+            pub fn new() -> Self {
+                Self::default()
+            }
+
+            #[inline]
+            pub fn len(&self) -> usize {
+                self.map.len()
+            }
+
+            #[inline]
+            pub fn is_empty(&self) -> bool {
+                self.map.is_empty()
+            }
+
+            #[inline]
+            pub fn clear(&mut self) {
+                self.map.clear()
+            }
+
+            #[inline]
+            pub fn get<K: Key>(&self) -> Option<&K::Value> {
+                self.map.get::<K>()
+            }
+
+            #[inline]
+            pub fn get_mut<K: Key>(&mut self) -> Option<&mut K::Value> {
+                self.map.get_mut::<K>()
+            }
+
+            #[inline]
+            pub fn contains<K: Key>(&self) -> bool {
+                self.map.contains::<K>()
+            }
+
+            #[inline]
+            pub fn insert<K: Key>(&mut self, value: K::Value) -> Option<K::Value>
+            where
+                K::Value: $first $(+ $rest)*,
+            {
+                self.map.insert::<K>(value)
+            }
+
+            #[inline]
+            pub fn remove<K: Key>(&mut self) -> Option<K::Value> {
+                self.map.remove::<K>()
+            }
+
+            #[inline]
+            pub fn entry<K: Key>(&mut self) -> Entry<'_, K::Value>
+            where
+                K::Value: $first $(+ $rest)*,
+            {
+                self.map.entry::<K>()
+            }
+
+            #[inline]
+            pub fn into_inner(self) -> TypeMap {
+                self.map
+            }
+        }
+    };
+}
+
+bounded_any_map! {
+    /// A map keyed by the type of its `Send` values, like
+    /// `anymap::Map<dyn Any + Send>`.
+    ///
+    /// ```
+    /// use anymap::compat::SendAnyMap;
+    ///
+    /// let mut map = SendAnyMap::new();
+    /// map.insert(vec![1u8]);
+    /// let map = std::thread::spawn(move || map).join().unwrap();
+    /// assert_eq!(map.get::<Vec<u8>>(), Some(&vec![1]));
+    /// ```
+    SendAnyMap: Send
+}
+
+bounded_any_map! {
+    /// A map keyed by the type of its `Send + Sync` values, like
+    /// `anymap::Map<dyn Any + Send + Sync>`.
+    SyncAnyMap: Send + Sync
+}
+
+bounded_type_map! {
+    /// A map keyed by marker types with `Send` values, like
+    /// `typemap::SendMap`.
+    SendMap: Send
+}
+
+bounded_type_map! {
+    /// A map keyed by marker types with `Send + Sync` values, like
+    /// `typemap::ShareMap`.
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use anymap::compat::{Key, ShareMap};
+    ///
+    /// struct Name;
+    ///
+    /// impl Key for Name {
+    ///     type Value = String;
+    /// }
+    ///
+    /// let mut map = ShareMap::new();
+    /// map.insert::<Name>("shared".into());
+    /// let map = Arc::new(map);
+    /// let other = Arc::clone(&map);
+    /// std::thread::spawn(move || assert_eq!(other.get::<Name>().unwrap(), "shared"))
+    ///     .join()
+    ///     .unwrap();
+    /// ```
+    ShareMap: Send + Sync
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn any_map_entry() {
+        let mut map = AnyMap::new();
+        map.entry::<Vec<i32>>().or_default().push(1);
+        map.entry::<Vec<i32>>().and_modify(|v| v.push(2)).or_default().push(3);
+        assert_eq!(map.get::<Vec<i32>>().unwrap(), &[1, 2, 3]);
+
+        let Entry::Occupied(mut entry) = map.entry::<Vec<i32>>() else {
+            panic!("expected an occupied entry");
+        };
+        assert_eq!(entry.insert(vec![4]), [1, 2, 3]);
+        assert_eq!(entry.remove(), [4]);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn type_map_keys() {
+        struct Width;
+        struct Height;
+
+        impl Key for Width {
+            type Value = u32;
+        }
+
+        impl Key for Height {
+            type Value = u32;
+        }
+
+        let mut map = TypeMap::new();
+        map.insert::<Width>(640);
+        map.insert::<Height>(480);
+        *map.entry::<Height>().or_insert(0) *= 2;
+        assert_eq!(map.get::<Width>(), Some(&640));
+        assert_eq!(map.get::<Height>(), Some(&960));
+        assert_eq!(map.remove::<Width>(), Some(640));
+        assert!(!map.contains::<Width>());
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn value_slots() {
+        let mut map = AnyMap::new();
+        map.insert(Value::new(1));
+        *map.entry::<Value>().or_insert(Value::new(())) = Value::new("a");
+        let Entry::Occupied(entry) = map.entry::<Value>() else {
+            panic!("expected an occupied entry");
+        };
+        assert!(entry.remove().is::<&str>());
+        assert!(map.entry::<Value>().or_insert(Value::new(2)).is::<i32>());
+    }
+
+    #[test]
+    fn bounded_variants() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+        struct Count;
+
+        impl Key for Count {
+            type Value = u32;
+        }
+
+        let mut map = SyncAnyMap::new();
+        map.insert(1u8);
+        *map.entry::<u8>().or_insert(0) += 1;
+        assert_send_sync(&map);
+        let map = std::thread::spawn(move || map).join().unwrap();
+        assert_eq!(map.get::<u8>(), Some(&2));
+
+        let mut map = SendMap::new();
+        map.insert::<Count>(1);
+        *map.entry::<Count>().or_default() += 1;
+        assert_eq!(map.remove::<Count>(), Some(2));
+        assert!(map.into_inner().is_empty());
+    }
+}
@@ -0,0 +1,412 @@
+use core::{any::Any, borrow::Borrow, marker::PhantomData, mem};
+
+use crate::{HashBackend, MapBackend, MapLookup, OccupiedBackendEntry, TypeMismatchError, VacantBackendEntry, Value};
+
+/// A view into a single entry of an [`AnyMap`](crate::AnyMap), which may be
+/// either vacant or occupied.
+///
+/// Constructed by [`AnyMap::entry`](crate::AnyMap::entry).
+pub enum Entry<'a, K: 'a, B: MapBackend<K> + 'a = HashBackend<K>> {
+    Occupied(OccupiedEntry<'a, K, B>),
+    Vacant(VacantEntry<'a, K, B>),
+}
+
+pub struct OccupiedEntry<'a, K: 'a, B: MapBackend<K> + 'a = HashBackend<K>> {
+    inner: B::Occupied<'a>,
+}
+
+pub struct VacantEntry<'a, K: 'a, B: MapBackend<K> + 'a = HashBackend<K>> {
+    inner: B::Vacant<'a>,
+}
+
+impl<'a, K, B: MapBackend<K>> Entry<'a, K, B> {
+    #[inline]
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    #[inline]
+    pub fn or_insert(self, default: Value) -> &'a mut Value {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    #[inline]
+    pub fn or_insert_with<F: FnOnce() -> Value>(self, default: F) -> &'a mut Value {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    #[inline]
+    pub fn or_insert_with_key<F: FnOnce(&K) -> Value>(self, default: F) -> &'a mut Value {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let value = default(entry.key());
+                entry.insert(value)
+            }
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn and_modify<F: FnOnce(&mut Value)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+}
+
+impl<'a, K, B: MapBackend<K>> OccupiedEntry<'a, K, B> {
+    /// Wraps an entry of the backend, for implementing [`MapBackend::entry`].
+    #[inline]
+    pub fn new(inner: B::Occupied<'a>) -> Self {
+        OccupiedEntry { inner }
+    }
+
+    #[inline]
+    pub fn key(&self) -> &K {
+        self.inner.key()
+    }
+
+    #[inline]
+    pub fn get(&self) -> &Value {
+        self.inner.get()
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut Value {
+        self.inner.get_mut()
+    }
+
+    #[inline]
+    pub fn into_mut(self) -> &'a mut Value {
+        self.inner.into_mut()
+    }
+
+    #[inline]
+    pub fn insert(&mut self, value: Value) -> Value {
+        mem::replace(self.inner.get_mut(), value)
+    }
+
+    #[inline]
+    pub fn remove(self) -> Value {
+        self.inner.remove_entry().1
+    }
+
+    #[inline]
+    pub fn remove_entry(self) -> (K, Value) {
+        self.inner.remove_entry()
+    }
+}
+
+impl<'a, K, B: MapBackend<K>> VacantEntry<'a, K, B> {
+    /// Wraps an entry of the backend, for implementing [`MapBackend::entry`].
+    #[inline]
+    pub fn new(inner: B::Vacant<'a>) -> Self {
+        VacantEntry { inner }
+    }
+
+    #[inline]
+    pub fn key(&self) -> &K {
+        self.inner.key()
+    }
+
+    #[inline]
+    pub fn into_key(self) -> K {
+        self.inner.into_key()
+    }
+
+    #[inline]
+    pub fn insert(self, value: Value) -> &'a mut Value {
+        self.inner.insert(value)
+    }
+}
+
+/// A view into a single entry of an [`AnyMap`](crate::AnyMap), looked up by
+/// a borrowed key.
+///
+/// Constructed by [`AnyMap::entry_ref`](crate::AnyMap::entry_ref). The key
+/// is only converted into an owned `K` when a vacant entry is filled.
+pub enum EntryRef<'a, 'b, K, Q: ?Sized, B = HashBackend<K>> {
+    Occupied(OccupiedEntryRef<'a, 'b, K, Q, B>),
+    Vacant(VacantEntryRef<'a, 'b, K, Q, B>),
+}
+
+pub struct OccupiedEntryRef<'a, 'b, K, Q: ?Sized, B = HashBackend<K>> {
+    // `key` is known to be in `map`.
+    map: &'a mut B,
+    key: &'b Q,
+    marker: PhantomData<K>,
+}
+
+pub struct VacantEntryRef<'a, 'b, K, Q: ?Sized, B = HashBackend<K>> {
+    map: &'a mut B,
+    key: &'b Q,
+    marker: PhantomData<K>,
+}
+
+impl<'a, 'b, K, Q, B> EntryRef<'a, 'b, K, Q, B>
+where
+    K: 'a + Borrow<Q> + From<&'b Q>,
+    Q: ?Sized,
+    B: MapLookup<K, Q>,
+{
+    pub(crate) fn new(map: &'a mut B, key: &'b Q) -> Self {
+        let marker = PhantomData;
+        if map.get(key).is_some() {
+            EntryRef::Occupied(OccupiedEntryRef { map, key, marker })
+        } else {
+            EntryRef::Vacant(VacantEntryRef { map, key, marker })
+        }
+    }
+
+    #[inline]
+    pub fn key(&self) -> &'b Q {
+        match self {
+            EntryRef::Occupied(entry) => entry.key,
+            EntryRef::Vacant(entry) => entry.key,
+        }
+    }
+
+    #[inline]
+    pub fn or_insert(self, default: Value) -> &'a mut Value {
+        self.or_insert_with(|| default)
+    }
+
+    #[inline]
+    pub fn or_insert_with<F: FnOnce() -> Value>(self, default: F) -> &'a mut Value {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_mut(),
+            EntryRef::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn and_modify<F: FnOnce(&mut Value)>(self, f: F) -> Self {
+        match self {
+            EntryRef::Occupied(mut entry) => {
+                f(entry.get_mut());
+                EntryRef::Occupied(entry)
+            }
+            EntryRef::Vacant(entry) => EntryRef::Vacant(entry),
+        }
+    }
+}
+
+const OCCUPIED: &str = "the key of an occupied entry is in the map";
+
+impl<'a, K, Q, B> OccupiedEntryRef<'a, '_, K, Q, B>
+where
+    Q: ?Sized,
+    B: MapLookup<K, Q>,
+{
+    #[inline]
+    pub fn key(&self) -> &Q {
+        self.key
+    }
+
+    #[inline]
+    pub fn get(&self) -> &Value {
+        self.map.get(self.key).expect(OCCUPIED)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut Value {
+        self.map.get_mut(self.key).expect(OCCUPIED)
+    }
+
+    #[inline]
+    pub fn into_mut(self) -> &'a mut Value {
+        self.map.get_mut(self.key).expect(OCCUPIED)
+    }
+
+    #[inline]
+    pub fn insert(&mut self, value: Value) -> Value {
+        mem::replace(self.get_mut(), value)
+    }
+
+    #[inline]
+    pub fn remove(self) -> Value {
+        self.map.remove(self.key).expect(OCCUPIED)
+    }
+}
+
+impl<'a, 'b, K, Q, B> VacantEntryRef<'a, 'b, K, Q, B>
+where
+    K: 'a + From<&'b Q>,
+    Q: ?Sized,
+    B: MapBackend<K>,
+{
+    #[inline]
+    pub fn key(&self) -> &'b Q {
+        self.key
+    }
+
+    /// Converts the key into a `K` and inserts `value` under it.
+    #[inline]
+    pub fn insert(self, value: Value) -> &'a mut Value {
+        self.map.entry(K::from(self.key)).or_insert(value)
+    }
+}
+
+/// A typed view into a single entry of an [`AnyMap`](crate::AnyMap).
+///
+/// Constructed by [`AnyMap::entry_typed`](crate::AnyMap::entry_typed). Methods
+/// returning a reference fail with [`TypeMismatchError`] if the entry is
+/// occupied by a value of a type other than `T`.
+pub struct TypedEntry<'a, K: 'a, T, B: MapBackend<K> + 'a = HashBackend<K>> {
+    entry: Entry<'a, K, B>,
+    marker: PhantomData<T>,
+}
+
+impl<'a, K, T: Any, B: MapBackend<K>> TypedEntry<'a, K, T, B> {
+    pub(crate) fn new(entry: Entry<'a, K, B>) -> Self {
+        TypedEntry {
+            entry,
+            marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn key(&self) -> &K {
+        self.entry.key()
+    }
+
+    #[inline]
+    pub fn or_insert(self, default: T) -> Result<&'a mut T, TypeMismatchError> {
+        self.or_insert_with(|| default)
+    }
+
+    #[inline]
+    pub fn or_insert_with<F: FnOnce() -> T>(self, default: F) -> Result<&'a mut T, TypeMismatchError> {
+        let value = match self.entry {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(Value::new(default())),
+        };
+        if !value.is::<T>() {
+            return Err(TypeMismatchError::new::<T>(value));
+        }
+        Ok(value.as_type_mut::<T>().unwrap())
+    }
+
+    #[inline]
+    pub fn or_default(self) -> Result<&'a mut T, TypeMismatchError>
+    where
+        T: Default,
+    {
+        self.or_insert_with(T::default)
+    }
+
+    /// Calls `f` with the stored value if the entry is occupied by a `T`.
+    #[inline]
+    #[must_use]
+    pub fn and_modify<F: FnOnce(&mut T)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self.entry {
+            if let Some(value) = entry.get_mut().as_type_mut::<T>() {
+                f(value);
+            }
+        }
+        self
+    }
+
+    #[inline]
+    pub fn into_entry(self) -> Entry<'a, K, B> {
+        self.entry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::AnyMap;
+    use super::*;
+
+    #[test]
+    fn entry_inserts_if_absent() {
+        let mut map = AnyMap::new();
+        map.entry("a").or_insert(Value::new(1));
+        map.entry("a").or_insert(Value::new(2));
+        map.entry("b").or_insert_with(|| Value::new("b"));
+        assert_eq!(map.get("a").unwrap().as_type::<i32>().unwrap(), &1);
+        assert_eq!(map.get("b").unwrap().as_type::<&str>().unwrap(), &"b");
+    }
+
+    #[test]
+    fn entry_and_modify() {
+        let mut map = AnyMap::new();
+        for _ in 0..3 {
+            map.entry("count")
+                .and_modify(|v| *v.as_type_mut::<i32>().unwrap() += 1)
+                .or_insert(Value::new(1));
+        }
+        assert_eq!(map.get("count").unwrap().as_type::<i32>().unwrap(), &3);
+    }
+
+    #[test]
+    fn entry_variants() {
+        let mut map = AnyMap::new();
+        map.insert("a", 1);
+        match map.entry("a") {
+            Entry::Occupied(entry) => {
+                assert_eq!(entry.remove().as_type::<i32>().unwrap(), &1);
+            }
+            Entry::Vacant(_) => unreachable!(),
+        }
+        assert!(matches!(map.entry("a"), Entry::Vacant(_)));
+    }
+
+    #[test]
+    fn typed_entry() {
+        let mut map = AnyMap::new();
+        *map.entry_typed::<i32>("count").or_insert(0).unwrap() += 5;
+        map.entry_typed::<i32>("count").and_modify(|v| *v *= 2).or_insert(0).unwrap();
+        assert_eq!(map.get("count").unwrap().as_type::<i32>().unwrap(), &10);
+        map.entry_typed::<Vec<u8>>("list").or_default().unwrap().push(1);
+        assert_eq!(map.get("list").unwrap().as_type::<Vec<u8>>().unwrap(), &[1]);
+    }
+
+    #[test]
+    fn typed_entry_mismatch() {
+        let mut map = AnyMap::new();
+        map.insert("count", "not a number");
+        let err = map.entry_typed::<i32>("count").or_insert(0).unwrap_err();
+        assert_eq!(err.expected(), "i32");
+        #[cfg(feature = "type-names")]
+        assert_eq!(err.found(), "&str");
+        assert_eq!(map.get("count").unwrap().as_type::<&str>().unwrap(), &"not a number");
+    }
+
+    #[test]
+    fn entry_ref() {
+        let mut map: AnyMap<String> = AnyMap::new();
+        map.entry_ref("a").or_insert(Value::new(1));
+        map.entry_ref("a").or_insert_with(|| unreachable!());
+        for _ in 0..2 {
+            map.entry_ref("b")
+                .and_modify(|v| *v.as_type_mut::<i32>().unwrap() += 1)
+                .or_insert(Value::new(0));
+        }
+        assert_eq!(map.get_typed::<i32>("a"), Some(&1));
+        assert_eq!(map.get_typed::<i32>("b"), Some(&1));
+
+        let EntryRef::Occupied(mut entry) = map.entry_ref("a") else {
+            unreachable!()
+        };
+        assert_eq!(entry.key(), "a");
+        assert!(entry.insert(Value::new("one")).is::<i32>());
+        assert!(entry.remove().is::<&str>());
+        assert!(matches!(map.entry_ref("a"), EntryRef::Vacant(_)));
+    }
+}
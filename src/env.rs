@@ -0,0 +1,80 @@
+//! Loading maps from environment variables.
+
+use std::env;
+
+use crate::{registry, AnyMap, ParseValueError};
+
+impl AnyMap<String> {
+    /// Collects every environment variable whose name starts with `prefix`
+    /// as a `String`, keyed by the name with the prefix removed. Variables
+    /// that are not valid Unicode are skipped.
+    ///
+    /// ```
+    /// use anymap::AnyMap;
+    ///
+    /// std::env::set_var("DOCTEST_FROM_ENV_PORT", "8080");
+    /// let config = AnyMap::from_env("DOCTEST_FROM_ENV_");
+    /// assert_eq!(config.get_typed::<String>("PORT").unwrap(), "8080");
+    /// ```
+    pub fn from_env(prefix: &str) -> Self {
+        env::vars_os()
+            .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+            .filter_map(|(name, value)| Some((name.strip_prefix(prefix)?.to_owned(), value)))
+            .collect()
+    }
+
+    /// Like [`from_env`](Self::from_env), but parses the variables named in
+    /// `schema` as the type registered under the given name with
+    /// [`registry::register_parse`]. Variables not in the schema are kept as
+    /// `String`s.
+    ///
+    /// ```
+    /// use anymap::AnyMap;
+    ///
+    /// std::env::set_var("DOCTEST_FROM_ENV_WITH_PORT", "8080");
+    /// let config = AnyMap::from_env_with("DOCTEST_FROM_ENV_WITH_", &[("PORT", "u16")]).unwrap();
+    /// assert_eq!(config.get_typed::<u16>("PORT"), Some(&8080));
+    /// ```
+    pub fn from_env_with(prefix: &str, schema: &[(&str, &str)]) -> Result<Self, ParseValueError> {
+        let mut map = Self::from_env(prefix);
+        for &(key, type_name) in schema {
+            let Some(value) = map.get_typed::<String>(key) else {
+                continue;
+            };
+            let parse = registry::lookup_parser(type_name)
+                .ok_or_else(|| ParseValueError::UnknownType(type_name.to_owned()))?;
+            let value = parse(value).map_err(|message| ParseValueError::Invalid {
+                type_name: type_name.to_owned(),
+                message,
+            })?;
+            map.insert_val(key.to_owned(), value);
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_env_schema() {
+        env::set_var("ANYMAP_TEST_ENV_HOST", "localhost");
+        env::set_var("ANYMAP_TEST_ENV_PORT", "8080");
+        env::set_var("ANYMAP_TEST_ENV_DEBUG", "yes");
+
+        let map = AnyMap::from_env("ANYMAP_TEST_ENV_");
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get_typed::<String>("PORT").unwrap(), "8080");
+
+        let map = AnyMap::from_env_with("ANYMAP_TEST_ENV_", &[("PORT", "u16"), ("MISSING", "u8")]).unwrap();
+        assert_eq!(map.get_typed::<u16>("PORT"), Some(&8080));
+        assert_eq!(map.get_typed::<String>("HOST").unwrap(), "localhost");
+        assert!(!map.contains_key("MISSING"));
+
+        let err = AnyMap::from_env_with("ANYMAP_TEST_ENV_", &[("DEBUG", "bool")]).unwrap_err();
+        assert!(matches!(err, ParseValueError::Invalid { .. }));
+        let err = AnyMap::from_env_with("ANYMAP_TEST_ENV_", &[("HOST", "Url")]).unwrap_err();
+        assert!(matches!(err, ParseValueError::UnknownType(name) if name == "Url"));
+    }
+}
@@ -0,0 +1,251 @@
+use alloc::string::String;
+use core::{any::type_name, error::Error, fmt};
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::{HashBackend, MapBackend, OccupiedEntry, Value};
+
+/// Returned when a stored value does not have the type requested by the
+/// caller.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeMismatchError {
+    expected: &'static str,
+    #[cfg(feature = "type-names")]
+    found: &'static str,
+}
+
+impl TypeMismatchError {
+    pub(crate) fn new<T: ?Sized>(found: &Value) -> Self {
+        #[cfg(not(feature = "type-names"))]
+        let _ = found;
+        TypeMismatchError {
+            expected: type_name::<T>(),
+            #[cfg(feature = "type-names")]
+            found: found.type_name(),
+        }
+    }
+
+    #[inline]
+    pub fn expected(&self) -> &'static str {
+        self.expected
+    }
+
+    #[cfg(feature = "type-names")]
+    #[inline]
+    pub fn found(&self) -> &'static str {
+        self.found
+    }
+}
+
+impl fmt::Display for TypeMismatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "stored value is not of type `{}`", self.expected)?;
+        #[cfg(feature = "type-names")]
+        write!(f, " (found `{}`)", self.found)?;
+        Ok(())
+    }
+}
+
+impl Error for TypeMismatchError {}
+
+/// Returned by [`AnyMap::try_insert`](crate::AnyMap::try_insert) when the key
+/// is already present. Holds the occupied entry and the value that was not
+/// inserted.
+pub struct OccupiedError<'a, K: 'a, T, B: MapBackend<K> + 'a = HashBackend<K>> {
+    pub entry: OccupiedEntry<'a, K, B>,
+    pub value: T,
+}
+
+impl<K: fmt::Debug, T: fmt::Debug, B: MapBackend<K>> fmt::Debug for OccupiedError<'_, K, T, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OccupiedError")
+            .field("key", self.entry.key())
+            .field("old_value", self.entry.get())
+            .field("new_value", &self.value)
+            .finish()
+    }
+}
+
+impl<K: fmt::Debug, T, B: MapBackend<K>> fmt::Display for OccupiedError<'_, K, T, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to insert, key {:?} already exists", self.entry.key())
+    }
+}
+
+impl<K: fmt::Debug, T: fmt::Debug, B: MapBackend<K>> Error for OccupiedError<'_, K, T, B> {}
+
+/// Returned by [`AnyMap::insert_parsed_as`](crate::AnyMap::insert_parsed_as).
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseValueError {
+    /// No type was registered under the given name.
+    UnknownType(String),
+    /// The input could not be parsed as the named type.
+    Invalid { type_name: String, message: String },
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for ParseValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseValueError::UnknownType(name) => write!(f, "no parser registered for type `{name}`"),
+            ParseValueError::Invalid { type_name, message } => {
+                write!(f, "invalid value for type `{type_name}`: {message}")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for ParseValueError {}
+
+/// Returned when a struct deriving `FromAnyMap` cannot be extracted from a
+/// map.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FromAnyMapError {
+    /// The map has no entry for the field.
+    Missing { field: &'static str },
+    /// The entry for the field holds a value of another type.
+    Mismatch { field: &'static str, error: TypeMismatchError },
+}
+
+impl fmt::Display for FromAnyMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FromAnyMapError::Missing { field } => write!(f, "missing entry for field `{field}`"),
+            FromAnyMapError::Mismatch { field, error } => write!(f, "invalid entry for field `{field}`: {error}"),
+        }
+    }
+}
+
+impl Error for FromAnyMapError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FromAnyMapError::Missing { .. } => None,
+            FromAnyMapError::Mismatch { error, .. } => Some(error),
+        }
+    }
+}
+
+/// Returned when saving or loading a snapshot with
+/// [`AnyMap::save_to`](crate::AnyMap::save_to) and
+/// [`AnyMap::load_from`](crate::AnyMap::load_from).
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    /// The input does not start with a snapshot header.
+    InvalidHeader,
+    /// The snapshot was written in a format version this crate can't read.
+    UnsupportedVersion(u32),
+    /// The value under `key` can't be saved because its type was not
+    /// registered with both [`register_parse`](crate::registry::register_parse)
+    /// and [`register_display`](crate::registry::register_display).
+    Unregistered { key: String, type_name: &'static str },
+    /// The snapshot's type names map to different types in the current
+    /// registry than they did when it was saved.
+    RegistryMismatch,
+    /// Line `line` is not a valid entry.
+    Malformed { line: usize },
+    /// The value on line `line` could not be parsed.
+    Parse { line: usize, error: ParseValueError },
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(error) => write!(f, "snapshot i/o failed: {error}"),
+            SnapshotError::InvalidHeader => f.write_str("missing snapshot header"),
+            SnapshotError::UnsupportedVersion(version) => write!(f, "unsupported snapshot version {version}"),
+            SnapshotError::Unregistered { key, type_name } => {
+                write!(f, "value for key {key:?} of type `{type_name}` is not registered for snapshots")
+            }
+            SnapshotError::RegistryMismatch => f.write_str("snapshot was saved with an incompatible type registry"),
+            SnapshotError::Malformed { line } => write!(f, "malformed snapshot entry on line {line}"),
+            SnapshotError::Parse { line, error } => write!(f, "invalid snapshot entry on line {line}: {error}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SnapshotError::Io(error) => Some(error),
+            SnapshotError::Parse { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for SnapshotError {
+    #[inline]
+    fn from(error: io::Error) -> Self {
+        SnapshotError::Io(error)
+    }
+}
+
+/// Returned by [`AnyMap::from_toml`](crate::AnyMap::from_toml) and
+/// [`AnyMap::from_yaml`](crate::AnyMap::from_yaml) when the input is invalid
+/// or uses a part of the format those loaders don't support.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    line: usize,
+    message: String,
+}
+
+impl ConfigError {
+    pub(crate) fn new(line: usize, message: impl Into<String>) -> Self {
+        ConfigError {
+            line,
+            message: message.into(),
+        }
+    }
+
+    /// The line the error was found on, starting at 1.
+    #[inline]
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    #[inline]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ConfigError {}
+
+/// Returned by [`AnyMap::to_json`](crate::AnyMap::to_json) and
+/// [`AnyMap::from_json`](crate::AnyMap::from_json).
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonError {
+    /// The JSON value to convert into a map is not an object.
+    NotAnObject,
+    /// The value at `path` has a type with no JSON counterpart.
+    Unsupported { path: String, type_name: &'static str },
+}
+
+#[cfg(feature = "json")]
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::NotAnObject => f.write_str("JSON value is not an object"),
+            JsonError::Unsupported { path, type_name } => {
+                write!(f, "value at `{path}` of type `{type_name}` can't be converted to JSON")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl Error for JsonError {}
@@ -0,0 +1,295 @@
+//! Type-keyed extensions in the style of `http::Extensions`.
+
+use alloc::string::String;
+use core::{
+    any::{Any, TypeId},
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+use crate::{hash::HashMap, value::SLOT_INVARIANT, AnyMap, HashBackend, MapBackend, Value};
+
+/// A map holding at most one value of each type, like `http::Extensions`.
+///
+/// Values must be `Clone + Send + Sync`, so the map is too, and it can be
+/// passed through middleware stacks with requests. An [`ExtendedAnyMap`]
+/// carries one next to its keyed entries.
+///
+/// ```
+/// use anymap::Extensions;
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct RequestId(u64);
+///
+/// let mut extensions = Extensions::new();
+/// extensions.insert(RequestId(7));
+/// let copy = extensions.clone();
+/// assert_eq!(extensions.remove::<RequestId>(), Some(RequestId(7)));
+/// assert_eq!(copy.get::<RequestId>(), Some(&RequestId(7)));
+/// ```
+#[derive(Default)]
+pub struct Extensions {
+    // Only holds values that are `Clone + Send + Sync`, in the slot of their
+    // own type. No `&mut Value` is handed out, so this can't be broken.
+    map: HashMap<TypeId, Extension>,
+}
+
+struct Extension {
+    value: Value,
+    clone: fn(&Value) -> Value,
+}
+
+// SAFETY: `insert` only accepts values that are `Send + Sync`.
+unsafe impl Send for Extensions {}
+unsafe impl Sync for Extensions {}
+
+fn clone_value<T: Any + Clone>(value: &Value) -> Value {
+    Value::new(value.as_type::<T>().expect(SLOT_INVARIANT).clone())
+}
+
+impl Extensions {
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts `value`, returning the previous value of the same type.
+    pub fn insert<T: Any + Clone + Send + Sync>(&mut self, value: T) -> Option<T> {
+        let extension = Extension {
+            value: Value::new(value),
+            clone: clone_value::<T>,
+        };
+        let old = self.map.insert(TypeId::of::<T>(), extension)?;
+        Some(old.value.downcast::<T>().expect(SLOT_INVARIANT))
+    }
+
+    #[inline]
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.map.get(&TypeId::of::<T>()).and_then(|e| e.value.as_type::<T>())
+    }
+
+    #[inline]
+    pub fn get_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.map.get_mut(&TypeId::of::<T>()).and_then(|e| e.value.as_type_mut::<T>())
+    }
+
+    /// Returns the `T`, inserting the result of `default` first if there is
+    /// none.
+    pub fn get_or_insert_with<T: Any + Clone + Send + Sync, F: FnOnce() -> T>(&mut self, default: F) -> &mut T {
+        let extension = self.map.entry(TypeId::of::<T>()).or_insert_with(|| Extension {
+            value: Value::new(default()),
+            clone: clone_value::<T>,
+        });
+        extension.value.as_type_mut::<T>().expect(SLOT_INVARIANT)
+    }
+
+    #[inline]
+    pub fn get_or_insert<T: Any + Clone + Send + Sync>(&mut self, value: T) -> &mut T {
+        self.get_or_insert_with(|| value)
+    }
+
+    #[inline]
+    pub fn get_or_insert_default<T: Any + Clone + Send + Sync + Default>(&mut self) -> &mut T {
+        self.get_or_insert_with(T::default)
+    }
+
+    #[inline]
+    pub fn remove<T: Any>(&mut self) -> Option<T> {
+        let extension = self.map.remove(&TypeId::of::<T>())?;
+        Some(extension.value.downcast::<T>().expect(SLOT_INVARIANT))
+    }
+
+    #[inline]
+    pub fn contains<T: Any>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+
+    /// Moves all values of `other` into `self`, replacing values of the same
+    /// type.
+    #[inline]
+    pub fn extend(&mut self, other: Extensions) {
+        self.map.extend(other.map)
+    }
+}
+
+impl Clone for Extensions {
+    fn clone(&self) -> Self {
+        let map = self.map.iter().map(|(&id, extension)| {
+            let clone = Extension {
+                value: (extension.clone)(&extension.value),
+                clone: extension.clone,
+            };
+            (id, clone)
+        });
+        Extensions { map: map.collect() }
+    }
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions").field("len", &self.len()).finish_non_exhaustive()
+    }
+}
+
+/// An [`AnyMap`] carrying [`Extensions`] next to its keyed entries.
+///
+/// The extensions are kept apart, so no key of the map is taken by them. The
+/// wrapper dereferences to the map for everything else.
+///
+/// ```
+/// use anymap::ExtendedAnyMap;
+///
+/// #[derive(Clone)]
+/// struct User(&'static str);
+///
+/// let mut request = ExtendedAnyMap::<String>::new();
+/// request.insert("path".into(), "/index.html");
+/// request.extensions_mut().insert(User("alice"));
+/// assert_eq!(request.extensions().get::<User>().unwrap().0, "alice");
+/// assert_eq!(request.len(), 1);
+/// ```
+pub struct ExtendedAnyMap<K = String, B = HashBackend<K>> {
+    map: AnyMap<K, B>,
+    extensions: Extensions,
+}
+
+impl<K> ExtendedAnyMap<K> {
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K, B> ExtendedAnyMap<K, B> {
+    #[inline]
+    pub fn from_parts(map: AnyMap<K, B>, extensions: Extensions) -> Self {
+        ExtendedAnyMap { map, extensions }
+    }
+
+    #[inline]
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    #[inline]
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    #[inline]
+    pub fn into_parts(self) -> (AnyMap<K, B>, Extensions) {
+        (self.map, self.extensions)
+    }
+}
+
+impl<K, B: Default> Default for ExtendedAnyMap<K, B> {
+    #[inline]
+    fn default() -> Self {
+        AnyMap::default().into()
+    }
+}
+
+impl<K, B> Clone for ExtendedAnyMap<K, B>
+where
+    AnyMap<K, B>: Clone,
+{
+    fn clone(&self) -> Self {
+        ExtendedAnyMap::from_parts(self.map.clone(), self.extensions.clone())
+    }
+}
+
+impl<K, B> From<AnyMap<K, B>> for ExtendedAnyMap<K, B> {
+    #[inline]
+    fn from(map: AnyMap<K, B>) -> Self {
+        ExtendedAnyMap::from_parts(map, Extensions::new())
+    }
+}
+
+impl<K, B> Deref for ExtendedAnyMap<K, B> {
+    type Target = AnyMap<K, B>;
+
+    #[inline]
+    fn deref(&self) -> &AnyMap<K, B> {
+        &self.map
+    }
+}
+
+impl<K, B> DerefMut for ExtendedAnyMap<K, B> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut AnyMap<K, B> {
+        &mut self.map
+    }
+}
+
+impl<K: fmt::Debug, B: MapBackend<K>> fmt::Debug for ExtendedAnyMap<K, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtendedAnyMap")
+            .field("map", &self.map)
+            .field("extensions", &self.extensions)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct RequestId(u64);
+
+    #[test]
+    fn extensions() {
+        let mut extensions = Extensions::new();
+        assert_eq!(extensions.insert(RequestId(1)), None);
+        assert_eq!(extensions.insert(RequestId(2)), Some(RequestId(1)));
+        extensions.get_or_insert_default::<Vec<u8>>().push(1);
+        extensions.get_mut::<RequestId>().unwrap().0 += 1;
+
+        let copy = extensions.clone();
+        extensions.clear();
+        let copy = thread::spawn(move || copy).join().unwrap();
+        assert_eq!(copy.get::<RequestId>(), Some(&RequestId(3)));
+        assert_eq!(copy.get::<Vec<u8>>(), Some(&vec![1]));
+        assert!(extensions.is_empty());
+
+        extensions.extend(copy);
+        assert_eq!(extensions.remove::<Vec<u8>>(), Some(vec![1]));
+        assert!(extensions.contains::<RequestId>());
+        assert_eq!(format!("{extensions:?}"), "Extensions { len: 1, .. }");
+    }
+
+    #[test]
+    fn extended_any_map() {
+        let mut map = ExtendedAnyMap::<String>::new();
+        map.insert("anymap::Extensions".into(), "kept");
+        map.extensions_mut().insert(RequestId(1));
+        map.insert("path".into(), "/");
+        assert_eq!(map.extensions().get::<RequestId>(), Some(&RequestId(1)));
+        assert_eq!(map.get_typed::<&str>("anymap::Extensions"), Some(&"kept"));
+        assert_eq!(map.len(), 2);
+
+        let (map, extensions) = map.into_parts();
+        let map = ExtendedAnyMap::from(map);
+        assert!(map.extensions().is_empty());
+        assert_eq!(extensions.len(), 1);
+    }
+}
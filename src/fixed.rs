@@ -0,0 +1,359 @@
+//! Maps with a fixed capacity, storing their entries inline.
+
+use core::{
+    any::Any,
+    array,
+    borrow::Borrow,
+    iter::{self, Flatten},
+    mem, slice,
+};
+
+use crate::{AnyMap, Entry, MapBackend, MapLookup, OccupiedBackendEntry, OccupiedEntry, VacantBackendEntry, VacantEntry, Value};
+
+/// Storage holding up to `N` entries in an inline array, searched linearly.
+///
+/// Never allocates for the table itself; values that are not stored inline
+/// by [`Value`] are still boxed.
+///
+/// # Panics
+///
+/// Inserting a new key into a full backend panics. Use
+/// [`AnyMap::try_insert_fixed`] to handle that case instead.
+pub struct FixedBackend<K, const N: usize> {
+    // The first `len` slots are occupied.
+    entries: [Option<(K, Value)>; N],
+    len: usize,
+}
+
+/// A map with values of any type holding at most `N` entries, without a heap
+/// allocated table.
+///
+/// ```
+/// use anymap::FixedAnyMap;
+///
+/// let mut map = FixedAnyMap::<&str, 2>::default();
+/// map.insert("a", 1);
+/// map.insert("b", 2);
+/// assert!(map.try_insert_fixed("c", 3).is_err());
+/// assert_eq!(map.get_typed::<i32>("b"), Some(&2));
+/// ```
+pub type FixedAnyMap<K, const N: usize> = AnyMap<K, FixedBackend<K, N>>;
+
+impl<K, const N: usize> Default for FixedBackend<K, N> {
+    #[inline]
+    fn default() -> Self {
+        FixedBackend {
+            entries: [const { None }; N],
+            len: 0,
+        }
+    }
+}
+
+impl<K, const N: usize> FixedBackend<K, N> {
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    pub(crate) fn position<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Eq,
+    {
+        self.entries[..self.len]
+            .iter()
+            .position(|entry| entry.as_ref().is_some_and(|(k, _)| k.borrow() == key))
+    }
+
+    /// Inserts `value` under `key`, or gives both back if `key` is new and
+    /// the backend is full.
+    pub(crate) fn try_insert(&mut self, key: K, value: Value) -> Result<Option<Value>, (K, Value)>
+    where
+        K: Eq,
+    {
+        if let Some(i) = self.position(&key) {
+            return Ok(Some(mem::replace(&mut self.slot_mut(i).1, value)));
+        }
+        if self.is_full() {
+            return Err((key, value));
+        }
+        self.push(key, value);
+        Ok(None)
+    }
+
+    /// Appends an entry for a key that is not present. The backend must not
+    /// be full.
+    fn push(&mut self, key: K, value: Value) -> &mut Value {
+        let (_, value) = self.entries[self.len].insert((key, value));
+        self.len += 1;
+        value
+    }
+
+    fn slot(&self, index: usize) -> &(K, Value) {
+        self.entries[index].as_ref().expect(OCCUPIED)
+    }
+
+    fn slot_mut(&mut self, index: usize) -> &mut (K, Value) {
+        self.entries[index].as_mut().expect(OCCUPIED)
+    }
+
+    /// Removes the entry at `index` by moving the last entry into its place.
+    fn remove_index(&mut self, index: usize) -> (K, Value) {
+        self.len -= 1;
+        self.entries.swap(index, self.len);
+        self.entries[self.len].take().expect(OCCUPIED)
+    }
+
+    /// Moves all entries out, leaving the backend empty.
+    pub(crate) fn drain(&mut self) -> impl Iterator<Item = (K, Value)> + '_ {
+        self.len = 0;
+        self.entries.iter_mut().filter_map(Option::take)
+    }
+}
+
+const OCCUPIED: &str = "the first `len` slots of a `FixedBackend` are occupied";
+
+fn full<const N: usize>() -> ! {
+    panic!("FixedBackend is full ({N} entries)")
+}
+
+pub(crate) type Iter<'a, K> = iter::Map<
+    Flatten<slice::Iter<'a, Option<(K, Value)>>>,
+    fn(&(K, Value)) -> (&K, &Value),
+>;
+pub(crate) type IterMut<'a, K> = iter::Map<
+    Flatten<slice::IterMut<'a, Option<(K, Value)>>>,
+    fn(&mut (K, Value)) -> (&K, &mut Value),
+>;
+pub(crate) type IntoIter<K, const N: usize> = Flatten<array::IntoIter<Option<(K, Value)>, N>>;
+
+pub struct Occupied<'a, K, const N: usize> {
+    pub(crate) backend: &'a mut FixedBackend<K, N>,
+    pub(crate) index: usize,
+}
+
+pub struct Vacant<'a, K, const N: usize> {
+    pub(crate) backend: &'a mut FixedBackend<K, N>,
+    pub(crate) key: K,
+}
+
+pub struct ExtractIf<'a, K, const N: usize, F> {
+    backend: &'a mut FixedBackend<K, N>,
+    index: usize,
+    pred: F,
+}
+
+impl<'a, K, const N: usize> OccupiedBackendEntry<'a, K> for Occupied<'a, K, N> {
+    #[inline]
+    fn key(&self) -> &K {
+        &self.backend.slot(self.index).0
+    }
+
+    #[inline]
+    fn get(&self) -> &Value {
+        &self.backend.slot(self.index).1
+    }
+
+    #[inline]
+    fn get_mut(&mut self) -> &mut Value {
+        &mut self.backend.slot_mut(self.index).1
+    }
+
+    #[inline]
+    fn into_mut(self) -> &'a mut Value {
+        &mut self.backend.slot_mut(self.index).1
+    }
+
+    #[inline]
+    fn remove_entry(self) -> (K, Value) {
+        self.backend.remove_index(self.index)
+    }
+}
+
+impl<'a, K, const N: usize> VacantBackendEntry<'a, K> for Vacant<'a, K, N> {
+    #[inline]
+    fn key(&self) -> &K {
+        &self.key
+    }
+
+    #[inline]
+    fn into_key(self) -> K {
+        self.key
+    }
+
+    fn insert(self, value: Value) -> &'a mut Value {
+        if self.backend.is_full() {
+            full::<N>()
+        }
+        self.backend.push(self.key, value)
+    }
+}
+
+impl<K, const N: usize, F> Iterator for ExtractIf<'_, K, N, F>
+where
+    F: FnMut(&K, &mut Value) -> bool,
+{
+    type Item = (K, Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.backend.len {
+            let (key, value) = self.backend.slot_mut(self.index);
+            if (self.pred)(key, value) {
+                // The last entry moves into the gap and is visited next.
+                return Some(self.backend.remove_index(self.index));
+            }
+            self.index += 1;
+        }
+        None
+    }
+}
+
+impl<K: Eq, const N: usize> MapBackend<K> for FixedBackend<K, N> {
+    type Iter<'a>
+        = Iter<'a, K>
+    where
+        Self: 'a,
+        K: 'a;
+    type IterMut<'a>
+        = IterMut<'a, K>
+    where
+        Self: 'a,
+        K: 'a;
+    type IntoIter = IntoIter<K, N>;
+    type Occupied<'a>
+        = Occupied<'a, K, N>
+    where
+        Self: 'a,
+        K: 'a;
+    type Vacant<'a>
+        = Vacant<'a, K, N>
+    where
+        Self: 'a,
+        K: 'a;
+    type ExtractIf<'a, F>
+        = ExtractIf<'a, K, N, F>
+    where
+        Self: 'a,
+        K: 'a,
+        F: FnMut(&K, &mut Value) -> bool;
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn insert(&mut self, key: K, value: Value) -> Option<Value> {
+        match self.try_insert(key, value) {
+            Ok(old) => old,
+            Err(_) => full::<N>(),
+        }
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.drain().for_each(drop);
+    }
+
+    #[inline]
+    fn iter(&self) -> Self::Iter<'_> {
+        self.entries.iter().flatten().map(|(k, v)| (k, v))
+    }
+
+    #[inline]
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        self.entries.iter_mut().flatten().map(|(k, v)| (&*k, v))
+    }
+
+    #[inline]
+    fn into_entries(self) -> Self::IntoIter {
+        self.entries.into_iter().flatten()
+    }
+
+    #[inline]
+    fn entry(&mut self, key: K) -> Entry<'_, K, Self> {
+        match self.position(&key) {
+            Some(index) => Entry::Occupied(OccupiedEntry::new(Occupied { backend: self, index })),
+            None => Entry::Vacant(VacantEntry::new(Vacant { backend: self, key })),
+        }
+    }
+
+    #[inline]
+    fn extract_if<F>(&mut self, pred: F) -> Self::ExtractIf<'_, F>
+    where
+        F: FnMut(&K, &mut Value) -> bool,
+    {
+        ExtractIf {
+            backend: self,
+            index: 0,
+            pred,
+        }
+    }
+}
+
+impl<K: Eq, const N: usize, Q> MapLookup<K, Q> for FixedBackend<K, N>
+where
+    K: Borrow<Q>,
+    Q: ?Sized + Eq,
+{
+    #[inline]
+    fn get(&self, key: &Q) -> Option<&Value> {
+        self.entries[self.position(key)?].as_ref().map(|(_, v)| v)
+    }
+
+    #[inline]
+    fn get_mut(&mut self, key: &Q) -> Option<&mut Value> {
+        let i = self.position(key)?;
+        self.entries[i].as_mut().map(|(_, v)| v)
+    }
+
+    fn remove(&mut self, key: &Q) -> Option<Value> {
+        let i = self.position(key)?;
+        Some(self.remove_index(i).1)
+    }
+}
+
+impl<K: Eq, const N: usize> AnyMap<K, FixedBackend<K, N>> {
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Inserts `value` under `key`, or gives both back if `key` is new and
+    /// the map is full.
+    pub fn try_insert_fixed<T: Any>(&mut self, key: K, value: T) -> Result<Option<Value>, (K, Value)> {
+        self.map.try_insert(key, Value::new(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_capacity() {
+        let mut map = FixedAnyMap::<u8, 2>::default();
+        assert_eq!(map.capacity(), 2);
+        map.insert(1, "one");
+        map.insert(2, 2);
+        assert!(map.backend().is_full());
+        assert!(map.try_insert_fixed(2, "two").unwrap().is_some());
+
+        let Err((key, value)) = map.try_insert_fixed(3, 3) else {
+            panic!("expected the map to be full");
+        };
+        assert_eq!((key, value.as_type::<i32>()), (3, Some(&3)));
+
+        map.remove(&1);
+        map.insert(3, 3);
+        assert_eq!(map.get_typed::<&str>(&2), Some(&"two"));
+        assert_eq!(map.iter().count(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "FixedBackend is full")]
+    fn fixed_insert_panics_when_full() {
+        let mut map = FixedAnyMap::<u8, 1>::default();
+        map.insert(1, 1);
+        map.insert(2, 2);
+    }
+}
@@ -0,0 +1,212 @@
+//! Hashers for [`AnyMap`](crate::AnyMap).
+
+use alloc::string::String;
+use core::{
+    hash::{BuildHasher, BuildHasherDefault, Hash, Hasher},
+    ops::Deref,
+};
+
+#[cfg(not(feature = "std"))]
+pub(crate) use hashbrown::{HashMap, HashSet, TryReserveError};
+#[cfg(feature = "std")]
+pub(crate) use std::collections::{hash_map, HashMap, HashSet, TryReserveError};
+
+/// `hashbrown`'s map types, with the entry API the standard library is built
+/// on standing in for its own, whose entries also name the hasher.
+#[cfg(not(feature = "std"))]
+pub(crate) mod hash_map {
+    pub(crate) use hashbrown::hash_map::{
+        Drain, ExtractIf, IntoIter, Iter, IterMut, RustcEntry as Entry, RustcOccupiedEntry as OccupiedEntry,
+        RustcVacantEntry as VacantEntry, Values,
+    };
+}
+
+/// Returns the entry for `key` as a [`hash_map::Entry`], which doesn't name
+/// the hasher.
+#[inline]
+pub(crate) fn entry<K: Eq + Hash, V, S: BuildHasher>(map: &mut HashMap<K, V, S>, key: K) -> hash_map::Entry<'_, K, V> {
+    #[cfg(feature = "std")]
+    return map.entry(key);
+    #[cfg(not(feature = "std"))]
+    return map.rustc_entry(key);
+}
+
+use crate::{AnyMap, HashBackend};
+
+/// The hasher [`AnyMap`](crate::AnyMap) uses unless another one is named:
+/// the standard library's SipHash-based [`RandomState`].
+///
+/// [`RandomState`]: std::hash::RandomState
+#[cfg(feature = "std")]
+pub type DefaultHashBuilder = std::hash::RandomState;
+
+/// The hasher [`AnyMap`](crate::AnyMap) uses unless another one is named:
+/// `hashbrown`'s default, since there is no `RandomState` without `std`.
+#[cfg(not(feature = "std"))]
+pub type DefaultHashBuilder = hashbrown::DefaultHashBuilder;
+
+/// The fast, non-cryptographic hasher used by rustc, from the `rustc-hash`
+/// crate.
+///
+/// Much faster than SipHash for short keys such as identifiers, but offers no
+/// protection against inputs crafted to collide, so don't use it for keys an
+/// attacker controls.
+pub use rustc_hash::{FxBuildHasher, FxHasher};
+
+/// A map with values of any type hashing its keys with [`FxHasher`].
+///
+/// ```
+/// use anymap::FxAnyMap;
+///
+/// let mut map = FxAnyMap::<&str>::default();
+/// map.insert("frame", 1u64);
+/// assert_eq!(map.get_typed::<u64>("frame"), Some(&1));
+/// ```
+pub type FxAnyMap<K = String> = AnyMap<K, HashBackend<K, FxBuildHasher>>;
+
+/// A key bundled with its hash, so that repeated lookups don't hash it again.
+///
+/// Use it in maps built with [`PrehashedBuildHasher`], such as
+/// [`HashedAnyMap`], which take the stored hash as is. All keys of one map
+/// must be hashed the same way, so stick to either [`new`](Self::new) or a
+/// single hasher passed to [`with_hasher`](Self::with_hasher).
+///
+/// ```
+/// use anymap::{HashedAnyMap, HashedKey};
+///
+/// let mut map = HashedAnyMap::<&str>::default();
+/// let key = HashedKey::new("frame");
+/// map.insert(key, 0u64);
+/// for _ in 0..10 {
+///     *map.get_typed_mut::<u64>(&key).unwrap() += 1;
+/// }
+/// assert_eq!(map.get_typed::<u64>(&key), Some(&10));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct HashedKey<K> {
+    hash: u64,
+    key: K,
+}
+
+impl<K: Hash> HashedKey<K> {
+    /// Hashes `key` with [`FxHasher`].
+    #[inline]
+    pub fn new(key: K) -> Self {
+        Self::with_hasher(key, &FxBuildHasher)
+    }
+
+    #[inline]
+    pub fn with_hasher<S: BuildHasher>(key: K, hasher: &S) -> Self {
+        HashedKey {
+            hash: hasher.hash_one(&key),
+            key,
+        }
+    }
+}
+
+impl<K> HashedKey<K> {
+    #[inline]
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    #[inline]
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    #[inline]
+    pub fn into_key(self) -> K {
+        self.key
+    }
+}
+
+impl<K> Deref for HashedKey<K> {
+    type Target = K;
+
+    #[inline]
+    fn deref(&self) -> &K {
+        &self.key
+    }
+}
+
+impl<K: PartialEq> PartialEq for HashedKey<K> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash && self.key == other.key
+    }
+}
+
+impl<K: Eq> Eq for HashedKey<K> {}
+
+impl<K> Hash for HashedKey<K> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+/// A hasher passing through the hash of a [`HashedKey`].
+///
+/// Anything else written to it is mixed in as by [`FxHasher`].
+#[derive(Default, Clone, Copy)]
+pub struct PrehashedHasher {
+    hash: u64,
+}
+
+impl Hasher for PrehashedHasher {
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        let mut fx = FxHasher::default();
+        fx.write_u64(self.hash);
+        fx.write(bytes);
+        self.hash = fx.finish();
+    }
+
+    #[inline]
+    fn write_u64(&mut self, i: u64) {
+        self.hash = i;
+    }
+
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+pub type PrehashedBuildHasher = BuildHasherDefault<PrehashedHasher>;
+
+/// A map with values of any type whose keys carry their own hash.
+pub type HashedAnyMap<K = String> = AnyMap<HashedKey<K>, HashBackend<HashedKey<K>, PrehashedBuildHasher>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fx_hasher() {
+        let hasher = FxBuildHasher;
+        assert_eq!(hasher.hash_one("key"), hasher.hash_one("key"));
+        assert_ne!(hasher.hash_one("key"), hasher.hash_one("kez"));
+
+        let mut map = FxAnyMap::default();
+        map.insert("key", 1);
+        assert_eq!(map.get_typed::<i32>("key"), Some(&1));
+    }
+
+    #[test]
+    fn hashed_keys() {
+        let hasher = FxBuildHasher;
+        let key = HashedKey::new(String::from("key"));
+        assert_eq!(key.hash(), hasher.hash_one("key"));
+        assert_eq!(PrehashedBuildHasher::default().hash_one(&key), key.hash());
+
+        let mut map = HashedAnyMap::default();
+        assert!(map.insert(key.clone(), 1).is_none());
+        assert!(map.insert(HashedKey::new(String::from("other")), 2).is_none());
+        assert_eq!(map.get_typed::<i32>(&key), Some(&1));
+        assert!(map.get(&HashedKey::with_hasher(String::from("key"), &std::hash::RandomState::new())).is_none());
+        assert_eq!(map.remove(&key).unwrap().downcast::<i32>().unwrap(), 1);
+        assert_eq!(map.len(), 1);
+    }
+}
@@ -0,0 +1,249 @@
+use core::{
+    any::{Any, TypeId},
+    borrow::Borrow,
+    hash::{BuildHasher, Hash},
+};
+
+use crate::{
+    hash::{hash_map::Iter, HashMap, HashSet},
+    AnyMap, DefaultHashBuilder, HashBackend, LookupKey, Value,
+};
+
+/// An [`AnyMap`] that additionally maintains an index from stored types to
+/// keys, so [`keys_of_type`](Self::keys_of_type) costs O(number of matches)
+/// instead of O(len).
+///
+/// To keep the index exact, mutable access is only given out as `&mut T`,
+/// which cannot change the type of a stored value. The index holds a clone of
+/// every key and hashes them with a clone of the map's hasher.
+pub struct IndexedAnyMap<K, S = DefaultHashBuilder> {
+    map: AnyMap<K, HashBackend<K, S>>,
+    index: HashMap<TypeId, HashSet<K, S>>,
+}
+
+impl<K> IndexedAnyMap<K> {
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        IndexedAnyMap {
+            map: AnyMap::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        IndexedAnyMap {
+            map: AnyMap::with_capacity(capacity),
+            index: HashMap::new(),
+        }
+    }
+}
+
+impl<K, S> IndexedAnyMap<K, S> {
+    #[inline]
+    #[must_use]
+    pub fn with_hasher(hasher: S) -> Self {
+        IndexedAnyMap {
+            map: AnyMap::with_hasher(hasher),
+            index: HashMap::new(),
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+        IndexedAnyMap {
+            map: AnyMap::with_capacity_and_hasher(capacity, hasher),
+            index: HashMap::new(),
+        }
+    }
+
+    #[inline]
+    pub fn hasher(&self) -> &S {
+        self.map.hasher()
+    }
+
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, K, Value> {
+        self.map.map.iter()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.map.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.map.is_empty()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.map.map.clear();
+        self.index.clear();
+    }
+
+    #[inline]
+    pub fn as_map(&self) -> &AnyMap<K, HashBackend<K, S>> {
+        &self.map
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> AnyMap<K, HashBackend<K, S>> {
+        self.map
+    }
+}
+
+impl<K: Eq + Hash + Clone, S: BuildHasher + Clone> IndexedAnyMap<K, S> {
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<&Value>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.map.get(key)
+    }
+
+    #[inline]
+    pub fn get_typed<T: Any>(&self, key: &(impl ?Sized + LookupKey<K, HashBackend<K, S>>)) -> Option<&T> {
+        self.map.get_typed(key)
+    }
+
+    #[inline]
+    pub fn get_typed_mut<T: Any>(&mut self, key: &(impl ?Sized + LookupKey<K, HashBackend<K, S>>)) -> Option<&mut T> {
+        self.map.get_typed_mut(key)
+    }
+
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.map.contains_key(key)
+    }
+
+    pub fn insert_val(&mut self, key: K, value: Value) -> Option<Value> {
+        let type_id = value.type_id();
+        let old = self.map.insert_val(key.clone(), value);
+        if let Some(old) = &old {
+            self.unindex(old, &key);
+        }
+        let hasher = self.map.hasher();
+        self.index
+            .entry(type_id)
+            .or_insert_with(|| HashSet::with_hasher(hasher.clone()))
+            .insert(key);
+        old
+    }
+
+    #[inline]
+    pub fn insert<T: Any>(&mut self, key: K, value: T) -> Option<Value> {
+        self.insert_val(key, Value::new(value))
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<Value>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        let value = self.map.remove(key)?;
+        self.unindex(&value, key);
+        Some(value)
+    }
+
+    /// Returns an iterator over the keys whose value is a `T`.
+    pub fn keys_of_type<T: Any>(&self) -> impl Iterator<Item = &K> + '_ {
+        self.index.get(&TypeId::of::<T>()).into_iter().flatten()
+    }
+
+    fn unindex<Q>(&mut self, value: &Value, key: &Q)
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        let type_id = value.type_id();
+        if let Some(keys) = self.index.get_mut(&type_id) {
+            keys.remove(key);
+            if keys.is_empty() {
+                self.index.remove(&type_id);
+            }
+        }
+    }
+}
+
+impl<K, S: Default> Default for IndexedAnyMap<K, S> {
+    #[inline]
+    fn default() -> Self {
+        IndexedAnyMap::with_hasher(S::default())
+    }
+}
+
+impl<K: Eq + Hash + Clone, S: BuildHasher + Clone> From<AnyMap<K, HashBackend<K, S>>> for IndexedAnyMap<K, S> {
+    fn from(map: AnyMap<K, HashBackend<K, S>>) -> Self {
+        let mut index: HashMap<TypeId, HashSet<K, S>> = HashMap::new();
+        for (key, value) in map.iter() {
+            index
+                .entry(value.type_id())
+                .or_insert_with(|| HashSet::with_hasher(map.hasher().clone()))
+                .insert(key.clone());
+        }
+        IndexedAnyMap { map, index }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FxBuildHasher;
+
+    fn sorted_keys<T: Any>(map: &IndexedAnyMap<&'static str>) -> Vec<&'static str> {
+        let mut keys: Vec<_> = map.keys_of_type::<T>().copied().collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn keys_of_type_tracks_inserts_and_removals() {
+        let mut map = IndexedAnyMap::new();
+        map.insert("a", 1);
+        map.insert("b", "b");
+        map.insert("c", 3);
+        assert_eq!(sorted_keys::<i32>(&map), ["a", "c"]);
+        assert_eq!(sorted_keys::<&str>(&map), ["b"]);
+
+        map.insert("a", "now a str");
+        assert_eq!(sorted_keys::<i32>(&map), ["c"]);
+        assert_eq!(sorted_keys::<&str>(&map), ["a", "b"]);
+
+        map.remove("c");
+        assert!(sorted_keys::<i32>(&map).is_empty());
+        assert!(!map.index.contains_key(&TypeId::of::<i32>()));
+    }
+
+    #[test]
+    fn from_any_map_builds_index() {
+        let mut plain = AnyMap::new();
+        plain.insert("a", 1u8);
+        plain.insert("b", 2u8);
+        let mut map = IndexedAnyMap::from(plain);
+        assert_eq!(sorted_keys::<u8>(&map), ["a", "b"]);
+        *map.get_typed_mut::<u8>("a").unwrap() = 5;
+        assert_eq!(map.get_typed::<u8>("a"), Some(&5));
+        assert_eq!(sorted_keys::<u8>(&map), ["a", "b"]);
+    }
+
+    #[test]
+    fn custom_hasher() {
+        let mut map = IndexedAnyMap::<u32, FxBuildHasher>::default();
+        map.insert(1, 1u8);
+        map.insert(2, 2u8);
+        map.insert(1, "one");
+        assert_eq!(map.keys_of_type::<u8>().collect::<Vec<_>>(), [&2]);
+        let map = IndexedAnyMap::from(map.into_inner());
+        assert_eq!(map.keys_of_type::<&str>().collect::<Vec<_>>(), [&1]);
+    }
+}
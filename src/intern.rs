@@ -0,0 +1,120 @@
+//! Sharing string keys between maps.
+
+use std::{
+    any::Any,
+    collections::HashSet,
+    fmt,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use crate::{AnyMap, DefaultHashBuilder, HashBackend, MapLookup, Value};
+
+/// A shared set of string keys, handing out one `Arc<str>` per distinct
+/// string.
+///
+/// Clones of an interner are handles to the same set. Maps with many common
+/// keys can thus share one allocation per key instead of owning a copy each.
+#[derive(Clone, Default)]
+pub struct KeyInterner {
+    keys: Arc<RwLock<HashSet<Arc<str>>>>,
+}
+
+/// A map with values of any type whose keys can be shared through a
+/// [`KeyInterner`].
+///
+/// ```
+/// use anymap::{InternedAnyMap, KeyInterner};
+///
+/// let interner = KeyInterner::new();
+/// let mut entities: Vec<InternedAnyMap> = (0..1000).map(|_| InternedAnyMap::new()).collect();
+/// for (id, entity) in entities.iter_mut().enumerate() {
+///     entity.insert_interned(&interner, "id", id);
+///     entity.insert_interned(&interner, "name", "entity");
+/// }
+/// assert_eq!(interner.len(), 2);
+/// assert_eq!(entities[7].get_typed::<usize>("id"), Some(&7));
+/// ```
+pub type InternedAnyMap<S = DefaultHashBuilder> = AnyMap<Arc<str>, HashBackend<Arc<str>, S>>;
+
+impl KeyInterner {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the shared copy of `key`, adding it to the set first if
+    /// needed.
+    pub fn intern(&self, key: &str) -> Arc<str> {
+        if let Some(key) = self.keys.read().unwrap_or_else(PoisonError::into_inner).get(key) {
+            return key.clone();
+        }
+        let mut keys = self.keys.write().unwrap_or_else(PoisonError::into_inner);
+        match keys.get(key) {
+            Some(key) => key.clone(),
+            None => {
+                let key: Arc<str> = key.into();
+                keys.insert(key.clone());
+                key
+            }
+        }
+    }
+
+    /// Returns the number of distinct keys in the set.
+    pub fn len(&self) -> usize {
+        self.keys.read().unwrap_or_else(PoisonError::into_inner).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops keys that are no longer used outside the interner.
+    pub fn purge(&self) {
+        let mut keys = self.keys.write().unwrap_or_else(PoisonError::into_inner);
+        keys.retain(|key| Arc::strong_count(key) > 1);
+    }
+}
+
+impl fmt::Debug for KeyInterner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyInterner").field("len", &self.len()).finish()
+    }
+}
+
+impl<B: MapLookup<Arc<str>, str>> AnyMap<Arc<str>, B> {
+    /// Inserts `value` under the copy of `key` held by `interner`.
+    ///
+    /// If the map already holds `key`, its existing allocation is kept.
+    pub fn insert_interned<T: Any>(&mut self, interner: &KeyInterner, key: &str, value: T) -> Option<Value> {
+        match self.map.get_mut(key) {
+            Some(slot) => Some(slot.replace(value)),
+            None => self.map.insert(interner.intern(key), Value::new(value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interned_keys() {
+        let interner = KeyInterner::new();
+        let mut a = InternedAnyMap::new();
+        let mut b = InternedAnyMap::new();
+        assert!(a.insert_interned(&interner, "key", 1).is_none());
+        assert!(b.insert_interned(&interner, "key", "two").is_none());
+        assert!(a.insert_interned(&interner, "key", 3).unwrap().is::<i32>());
+
+        let (key_a, key_b) = (a.keys().next().unwrap(), b.keys().next().unwrap());
+        assert!(Arc::ptr_eq(key_a, key_b));
+        assert_eq!(a.get_typed::<i32>("key"), Some(&3));
+
+        b.insert_interned(&interner, "other", ());
+        assert_eq!(interner.len(), 2);
+        drop(b);
+        interner.purge();
+        assert_eq!(interner.len(), 1);
+        assert!(Arc::ptr_eq(&interner.intern("key"), a.keys().next().unwrap()));
+    }
+}
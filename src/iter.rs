@@ -0,0 +1,112 @@
+use core::{any::Any, iter::{self, FusedIterator}, marker::PhantomData};
+
+use crate::{HashBackend, MapBackend, Value};
+
+/// Iterator over the keys of an [`AnyMap`](crate::AnyMap).
+pub type Keys<'a, K, B = HashBackend<K>> =
+    iter::Map<<B as MapBackend<K>>::Iter<'a>, fn((&'a K, &'a Value)) -> &'a K>;
+
+/// Iterator over the values of an [`AnyMap`](crate::AnyMap).
+pub type Values<'a, K, B = HashBackend<K>> =
+    iter::Map<<B as MapBackend<K>>::Iter<'a>, fn((&'a K, &'a Value)) -> &'a Value>;
+
+/// Mutable iterator over the values of an [`AnyMap`](crate::AnyMap).
+pub type ValuesMut<'a, K, B = HashBackend<K>> =
+    iter::Map<<B as MapBackend<K>>::IterMut<'a>, fn((&'a K, &'a mut Value)) -> &'a mut Value>;
+
+/// Iterator over the entries of an [`AnyMap`](crate::AnyMap) holding a `T`.
+///
+/// Created by [`AnyMap::iter_typed`](crate::AnyMap::iter_typed).
+pub struct IterTyped<'a, K: 'a, T, B: MapBackend<K> + 'a = HashBackend<K>> {
+    pub(crate) inner: B::Iter<'a>,
+    pub(crate) marker: PhantomData<&'a T>,
+}
+
+impl<'a, K, T: Any, B: MapBackend<K>> Iterator for IterTyped<'a, K, T, B> {
+    type Item = (&'a K, &'a T);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.by_ref().find_map(|(k, v)| Some((k, v.as_type::<T>()?)))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.inner.size_hint().1)
+    }
+}
+
+impl<'a, K, T: Any, B: MapBackend<K>> FusedIterator for IterTyped<'a, K, T, B> where B::Iter<'a>: FusedIterator {}
+
+impl<'a, K, T, B: MapBackend<K>> Clone for IterTyped<'a, K, T, B>
+where
+    B::Iter<'a>: Clone,
+{
+    fn clone(&self) -> Self {
+        IterTyped {
+            inner: self.inner.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Iterator over the values of an [`AnyMap`](crate::AnyMap) that are a `T`.
+///
+/// Created by [`AnyMap::values_typed`](crate::AnyMap::values_typed).
+pub struct ValuesTyped<'a, K: 'a, T, B: MapBackend<K> + 'a = HashBackend<K>> {
+    pub(crate) inner: B::Iter<'a>,
+    pub(crate) marker: PhantomData<&'a T>,
+}
+
+impl<'a, K, T: Any, B: MapBackend<K>> Iterator for ValuesTyped<'a, K, T, B> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.by_ref().find_map(|(_, v)| v.as_type::<T>())
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.inner.size_hint().1)
+    }
+}
+
+impl<'a, K, T: Any, B: MapBackend<K>> FusedIterator for ValuesTyped<'a, K, T, B> where B::Iter<'a>: FusedIterator {}
+
+impl<'a, K, T, B: MapBackend<K>> Clone for ValuesTyped<'a, K, T, B>
+where
+    B::Iter<'a>: Clone,
+{
+    fn clone(&self) -> Self {
+        ValuesTyped {
+            inner: self.inner.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Mutable iterator over the values of an [`AnyMap`](crate::AnyMap) that are
+/// a `T`.
+///
+/// Created by [`AnyMap::values_typed_mut`](crate::AnyMap::values_typed_mut).
+pub struct ValuesTypedMut<'a, K: 'a, T, B: MapBackend<K> + 'a = HashBackend<K>> {
+    pub(crate) inner: B::IterMut<'a>,
+    pub(crate) marker: PhantomData<&'a mut T>,
+}
+
+impl<'a, K, T: Any, B: MapBackend<K>> Iterator for ValuesTypedMut<'a, K, T, B> {
+    type Item = &'a mut T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.by_ref().find_map(|(_, v)| v.as_type_mut::<T>())
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.inner.size_hint().1)
+    }
+}
+
+impl<'a, K, T: Any, B: MapBackend<K>> FusedIterator for ValuesTypedMut<'a, K, T, B> where B::IterMut<'a>: FusedIterator {}
@@ -0,0 +1,170 @@
+//! Conversion between maps and `serde_json::Value`.
+
+use serde_json::{Map, Number, Value as Json};
+
+use crate::{AnyMap, JsonError, Value};
+
+impl AnyMap<String> {
+    /// Converts the map into a JSON object. Requires the `json` feature.
+    ///
+    /// Supported are `()` as `null`, `bool`, the primitive integer and float
+    /// types, `String`, `&'static str`, `Vec<Value>` as arrays, nested
+    /// `AnyMap<String>`s as objects and `serde_json::Value` itself. Non-finite
+    /// floats become `null`, as with `serde_json`.
+    ///
+    /// ```
+    /// use anymap::AnyMap;
+    /// use serde_json::json;
+    ///
+    /// let mut server = AnyMap::new();
+    /// server.insert("port".to_owned(), 8080u16);
+    /// let mut config = AnyMap::new();
+    /// config.insert("name".to_owned(), "example");
+    /// config.insert("server".to_owned(), server);
+    /// assert_eq!(config.to_json().unwrap(), json!({ "name": "example", "server": { "port": 8080 } }));
+    /// ```
+    pub fn to_json(&self) -> Result<Json, JsonError> {
+        map_to_json(self, "")
+    }
+
+    /// Builds a map from a JSON object. Requires the `json` feature.
+    ///
+    /// `null` is stored as `()`, numbers as `i64` if they fit, then `u64`,
+    /// then `f64`, strings as `String`, arrays as `Vec<Value>` and objects as
+    /// nested `AnyMap<String>`s.
+    ///
+    /// ```
+    /// use anymap::AnyMap;
+    /// use serde_json::json;
+    ///
+    /// let config = AnyMap::from_json(json!({ "name": "example", "server": { "port": 8080 } })).unwrap();
+    /// assert_eq!(config.get_typed::<String>("name").unwrap(), "example");
+    /// let server = config.get_typed::<AnyMap>("server").unwrap();
+    /// assert_eq!(server.get_typed::<i64>("port"), Some(&8080));
+    /// ```
+    pub fn from_json(json: Json) -> Result<Self, JsonError> {
+        match json {
+            Json::Object(object) => Ok(map_from_json(object)),
+            _ => Err(JsonError::NotAnObject),
+        }
+    }
+}
+
+fn map_to_json(map: &AnyMap<String>, path: &str) -> Result<Json, JsonError> {
+    let mut object = Map::with_capacity(map.len());
+    for (key, value) in map.iter() {
+        let path = if path.is_empty() { key.clone() } else { format!("{path}.{key}") };
+        object.insert(key.clone(), value_to_json(value, &path)?);
+    }
+    Ok(Json::Object(object))
+}
+
+fn value_to_json(value: &Value, path: &str) -> Result<Json, JsonError> {
+    let json = value
+        .downcast_switch()
+        .case(|()| Json::Null)
+        .case(|&v: &bool| v.into())
+        .case(|&v: &i8| v.into())
+        .case(|&v: &i16| v.into())
+        .case(|&v: &i32| v.into())
+        .case(|&v: &i64| v.into())
+        .case(|&v: &isize| v.into())
+        .case(|&v: &u8| v.into())
+        .case(|&v: &u16| v.into())
+        .case(|&v: &u32| v.into())
+        .case(|&v: &u64| v.into())
+        .case(|&v: &usize| v.into())
+        .case(|&v: &f32| Number::from_f64(v.into()).map_or(Json::Null, Json::Number))
+        .case(|&v: &f64| Number::from_f64(v).map_or(Json::Null, Json::Number))
+        .case(|v: &String| v.as_str().into())
+        .case(|&v: &&'static str| v.into())
+        .case(|v: &Json| v.clone())
+        .run();
+    if let Some(json) = json {
+        return Ok(json);
+    }
+    if let Some(items) = value.as_type::<Vec<Value>>() {
+        let items = items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| value_to_json(item, &format!("{path}[{i}]")))
+            .collect::<Result<_, _>>()?;
+        return Ok(Json::Array(items));
+    }
+    if let Some(map) = value.as_type::<AnyMap<String>>() {
+        return map_to_json(map, path);
+    }
+    Err(JsonError::Unsupported {
+        path: path.to_owned(),
+        type_name: value.describe_type(),
+    })
+}
+
+fn map_from_json(object: Map<String, Json>) -> AnyMap<String> {
+    let mut map = AnyMap::with_capacity(object.len());
+    for (key, json) in object {
+        map.insert_val(key, value_from_json(json));
+    }
+    map
+}
+
+fn value_from_json(json: Json) -> Value {
+    match json {
+        Json::Null => Value::new(()),
+        Json::Bool(v) => Value::new(v),
+        Json::Number(v) => match (v.as_i64(), v.as_u64()) {
+            (Some(v), _) => Value::new(v),
+            (None, Some(v)) => Value::new(v),
+            // Without `arbitrary_precision` every number fits one of the three.
+            (None, None) => Value::new(v.as_f64().unwrap_or(f64::NAN)),
+        },
+        Json::String(v) => Value::new(v),
+        Json::Array(items) => Value::new(items.into_iter().map(value_from_json).collect::<Vec<_>>()),
+        Json::Object(object) => Value::new(map_from_json(object)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let json = json!({
+            "null": null,
+            "flag": true,
+            "neg": -3,
+            "big": u64::MAX,
+            "ratio": 0.5,
+            "name": "example",
+            "list": [1, "two", [3.0], { "four": 4 }],
+            "nested": { "deeper": { "x": 1 } },
+        });
+        let map = AnyMap::from_json(json.clone()).unwrap();
+        assert_eq!(map.get_typed::<()>("null"), Some(&()));
+        assert_eq!(map.get_typed::<bool>("flag"), Some(&true));
+        assert_eq!(map.get_typed::<i64>("neg"), Some(&-3));
+        assert_eq!(map.get_typed::<u64>("big"), Some(&u64::MAX));
+        assert_eq!(map.get_typed::<f64>("ratio"), Some(&0.5));
+        assert_eq!(map.get_typed::<Vec<Value>>("list").unwrap().len(), 4);
+        assert_eq!(map.to_json().unwrap(), json);
+    }
+
+    #[test]
+    fn unsupported() {
+        struct Opaque;
+
+        assert_eq!(AnyMap::from_json(json!([1])).unwrap_err(), JsonError::NotAnObject);
+
+        let mut inner = AnyMap::new();
+        inner.insert("items".to_owned(), vec![Value::new(1u8), Value::new(Opaque)]);
+        let mut map = AnyMap::new();
+        map.insert("inner".to_owned(), inner);
+        match map.to_json().unwrap_err() {
+            JsonError::Unsupported { path, .. } => assert_eq!(path, "inner.items[1]"),
+            error => panic!("unexpected error: {error}"),
+        }
+    }
+}
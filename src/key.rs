@@ -0,0 +1,202 @@
+use core::{any::Any, fmt, marker::PhantomData};
+
+use crate::{AnyMap, MapBackend, MapLookup};
+
+/// A key that carries the type of the value stored under it.
+///
+/// Define typed keys once as constants and use them with
+/// [`AnyMap::get_by_key`] and friends to skip the turbofish and the downcast
+/// failure case at call sites.
+///
+/// ```
+/// use anymap::{AnyMap, Key};
+///
+/// const PORT: Key<u16> = Key::new("port");
+///
+/// let mut map = AnyMap::<String>::new();
+/// map.insert_by_key(&PORT, 8080);
+/// assert_eq!(map.get_by_key(&PORT), Some(&8080));
+/// ```
+pub struct Key<T, Q: ?Sized + 'static = str> {
+    name: &'static Q,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T, Q: ?Sized + 'static> Key<T, Q> {
+    #[inline]
+    #[must_use]
+    pub const fn new(name: &'static Q) -> Self {
+        Key {
+            name,
+            marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub const fn name(&self) -> &'static Q {
+        self.name
+    }
+}
+
+impl<T, Q: ?Sized + 'static> Clone for Key<T, Q> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, Q: ?Sized + 'static> Copy for Key<T, Q> {}
+
+impl<T, Q: ?Sized + fmt::Debug + 'static> fmt::Debug for Key<T, Q> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Key").field(&self.name).finish()
+    }
+}
+
+/// A key defined as a type, whose value type is fixed at compile time.
+///
+/// Lets libraries define extension slots in a map that may also hold
+/// ordinary string keys. The entry is stored under [`NAME`](Self::NAME), so
+/// pick a name that won't clash, e.g. by prefixing it with the crate name.
+///
+/// ```
+/// use anymap::{AnyMap, MapKey};
+///
+/// struct RequestId;
+///
+/// impl MapKey for RequestId {
+///     type Value = u64;
+///     const NAME: &'static str = "my_crate::RequestId";
+/// }
+///
+/// let mut map = AnyMap::<String>::new();
+/// map.insert("user".into(), "alice");
+/// map.insert_key::<RequestId>(7);
+/// assert_eq!(map.get_key::<RequestId>(), Some(&7));
+/// ```
+pub trait MapKey: 'static {
+    type Value: Any;
+    const NAME: &'static str;
+}
+
+impl<K, B: MapLookup<K, str>> AnyMap<K, B> {
+    #[inline]
+    pub fn get_key<M: MapKey>(&self) -> Option<&M::Value> {
+        self.get_typed::<M::Value>(M::NAME)
+    }
+
+    #[inline]
+    pub fn get_key_mut<M: MapKey>(&mut self) -> Option<&mut M::Value> {
+        self.get_typed_mut::<M::Value>(M::NAME)
+    }
+
+    /// Inserts the value of the slot `M`, returning the previous one.
+    #[inline]
+    pub fn insert_key<M: MapKey>(&mut self, value: M::Value) -> Option<M::Value>
+    where
+        K: From<&'static str>,
+    {
+        self.insert_replace(K::from(M::NAME), value)
+    }
+
+    #[inline]
+    pub fn remove_key<M: MapKey>(&mut self) -> Option<M::Value> {
+        self.remove_typed::<M::Value>(M::NAME)
+    }
+
+    #[inline]
+    pub fn contains_key_of<M: MapKey>(&self) -> bool {
+        self.contains_typed::<M::Value>(M::NAME)
+    }
+}
+
+impl<K, B: MapBackend<K>> AnyMap<K, B> {
+    #[inline]
+    pub fn get_by_key<T: Any, Q>(&self, key: &Key<T, Q>) -> Option<&T>
+    where
+        B: MapLookup<K, Q>,
+        Q: ?Sized,
+    {
+        self.get_typed::<T>(key.name)
+    }
+
+    #[inline]
+    pub fn get_by_key_mut<T: Any, Q>(&mut self, key: &Key<T, Q>) -> Option<&mut T>
+    where
+        B: MapLookup<K, Q>,
+        Q: ?Sized,
+    {
+        self.get_typed_mut::<T>(key.name)
+    }
+
+    /// Inserts `value` under `key`, returning the previous value if it was
+    /// also a `T`.
+    #[inline]
+    pub fn insert_by_key<T: Any, Q>(&mut self, key: &Key<T, Q>, value: T) -> Option<T>
+    where
+        K: From<&'static Q>,
+        Q: ?Sized,
+    {
+        self.insert_replace(K::from(key.name), value)
+    }
+
+    /// Removes the value stored under `key` if it is a `T`.
+    #[inline]
+    pub fn remove_by_key<T: Any, Q>(&mut self, key: &Key<T, Q>) -> Option<T>
+    where
+        B: MapLookup<K, Q>,
+        Q: ?Sized,
+    {
+        self.remove_typed::<T>(key.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAME: Key<String> = Key::new("name");
+    const RETRIES: Key<u8> = Key::new("retries");
+
+    #[test]
+    fn typed_keys() {
+        let mut map = AnyMap::<String>::new();
+        assert_eq!(map.insert_by_key(&NAME, "anymap".into()), None);
+        map.insert_by_key(&RETRIES, 3);
+        *map.get_by_key_mut(&RETRIES).unwrap() += 1;
+        assert_eq!(map.get_by_key(&RETRIES), Some(&4));
+        assert_eq!(map.insert_by_key(&RETRIES, 0), Some(4));
+        assert_eq!(map.remove_by_key(&NAME).unwrap(), "anymap");
+        assert_eq!(map.get_by_key(&NAME), None);
+    }
+
+    struct Session;
+
+    impl MapKey for Session {
+        type Value = Vec<&'static str>;
+        const NAME: &'static str = "anymap::tests::Session";
+    }
+
+    #[test]
+    fn map_key_slots() {
+        let mut map = AnyMap::<String>::new();
+        map.insert("user".into(), "alice");
+        assert!(!map.contains_key_of::<Session>());
+        assert_eq!(map.insert_key::<Session>(vec!["a"]), None);
+        map.get_key_mut::<Session>().unwrap().push("b");
+        assert_eq!(map.get_key::<Session>().unwrap(), &["a", "b"]);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.remove_key::<Session>().unwrap(), ["a", "b"]);
+        assert_eq!(map.get_key::<Session>(), None);
+    }
+
+    #[test]
+    fn typed_keys_with_str_map() {
+        let mut map = AnyMap::<&'static str>::new();
+        map.insert_by_key(&RETRIES, 1);
+        assert_eq!(map.get_typed::<u8>("retries"), Some(&1));
+        map.insert("name", 1);
+        assert_eq!(map.get_by_key(&NAME), None);
+        assert_eq!(format!("{NAME:?}"), "Key(\"name\")");
+    }
+}
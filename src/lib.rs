@@ -1,95 +1,368 @@
-/// HashMap with any type for values like JavaScript objects.
-/// 
-/// # Examples
-/// ```
-/// use anymap::AnyMap;
-/// 
-/// let mut map = AnyMap::new();
-/// map.insert("key", "value");
-/// map.insert("key2", 1);
-/// assert_eq!(map.get_typed::<&str>("key").unwrap(), "value");
-/// assert_eq!(map.get_typed::<i32>("key2").unwrap(), 1);
-/// ```
-/// 
-/// ```
-/// use anymap::{AnyMap};
-/// 
-/// let mut map = AnyMap::new();
-/// map.insert("key", "value");
-/// assert!(map.get("key").unwrap().is::<&str>());
-/// ```
+//! HashMap with any type for values like JavaScript objects.
+//! 
+//! # Examples
+//! ```
+//! use anymap::AnyMap;
+//! 
+//! let mut map = AnyMap::new();
+//! map.insert("key", "value");
+//! map.insert("key2", 1);
+//! assert_eq!(map.get_typed::<&str>("key").unwrap(), &"value");
+//! assert_eq!(map.get_typed::<i32>("key2").unwrap(), &1);
+//! ```
+//! 
+//! ```
+//! use anymap::{AnyMap};
+//! 
+//! let mut map = AnyMap::new();
+//! map.insert("key", "value");
+//! assert!(map.get("key").unwrap().is::<&str>());
+//! ```
+//!
+//! # Features
+//!
+//! The default `std` feature enables everything that needs the standard
+//! library: the [`registry`] and the cloning, comparing and formatting of
+//! values built on it, [`AnyMap::from_env`], snapshots and the thread-safe
+//! maps. Without it the crate only needs `alloc`, and maps hash
+//! with `hashbrown` instead of `std::collections::HashMap`.
 
-use std::{any::Any, collections::{HashMap, hash_map::{Keys, Values, Iter}}, borrow::Borrow};
-use core::hash::Hash;
+// Tests always have the standard library.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
-#[derive(Debug)]
-pub struct Value {
-    inner: Box<dyn Any>
-}
+extern crate alloc;
+
+pub mod compat;
+#[cfg(feature = "std")]
+pub mod registry;
+
+mod arena;
+mod backend;
+#[cfg(feature = "bincode")]
+mod bincode;
+mod compact;
+mod entry;
+#[cfg(feature = "std")]
+mod env;
+mod error;
+mod extensions;
+mod fixed;
+mod hash;
+mod indexed;
+#[cfg(feature = "std")]
+mod intern;
+mod iter;
+#[cfg(feature = "json")]
+mod json;
+mod key;
+#[cfg(feature = "msgpack")]
+mod msgpack;
+mod multi;
+mod number;
+mod ordered;
+#[cfg(feature = "ron")]
+mod ron;
+#[cfg(feature = "serde")]
+mod serialize;
+mod small;
+#[cfg(feature = "std")]
+mod snapshot;
+mod sorted;
+#[cfg(feature = "std")]
+mod sync;
+mod toml;
+mod type_map;
+mod typed;
+mod value;
+mod yaml;
 
-impl Value {
-    pub(crate) fn new<T: Any>(value: T) -> Self {
-        Self {
-            inner: Box::new(value),
+pub use arena::ArenaAnyMap;
+pub use backend::{HashBackend, HashKey, LookupKey, MapBackend, MapLookup, OccupiedBackendEntry, VacantBackendEntry};
+pub use compact::{CompactKey, SmallKeyAnyMap};
+pub use entry::{Entry, EntryRef, OccupiedEntry, OccupiedEntryRef, VacantEntry, VacantEntryRef, TypedEntry};
+pub use error::{TypeMismatchError, OccupiedError, FromAnyMapError, ConfigError};
+#[cfg(feature = "std")]
+pub use error::{ParseValueError, SnapshotError};
+#[cfg(feature = "json")]
+pub use error::JsonError;
+pub use extensions::{ExtendedAnyMap, Extensions};
+pub use fixed::{FixedAnyMap, FixedBackend};
+pub use hash::{DefaultHashBuilder, FxAnyMap, FxBuildHasher, FxHasher, HashedAnyMap, HashedKey, PrehashedBuildHasher, PrehashedHasher};
+pub use indexed::IndexedAnyMap;
+#[cfg(feature = "std")]
+pub use intern::{InternedAnyMap, KeyInterner};
+pub use iter::{IterTyped, Keys, Values, ValuesMut, ValuesTyped, ValuesTypedMut};
+pub use key::{Key, MapKey};
+pub use multi::AnyMultiTypeMap;
+pub use number::Number;
+pub use ordered::{OrderedAnyMap, OrderedBackend};
+#[cfg(feature = "serde")]
+pub use serialize::{UnknownTypes, UnknownValue};
+pub use small::{SmallAnyMap, SmallBackend};
+#[cfg(feature = "std")]
+pub use snapshot::SnapshotReader;
+pub use sorted::SortedAnyMap;
+#[cfg(feature = "std")]
+pub use sync::{SendAnyMap, SyncAnyMap};
+pub use type_map::TypeMap;
+pub use typed::TypeCount;
+#[cfg(feature = "std")]
+pub use value::HashableValue;
+pub use value::{Value, DowncastSwitch};
+
+#[cfg(feature = "derive")]
+pub use anymap_derive::{FromAnyMap, IntoAnyMap};
+
+/// Support code for the derive macros. Not public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::string::String;
+    use core::any::Any;
+
+    use crate::{AnyMap, FromAnyMapError, TypeMismatchError};
+
+    pub fn get_field<T: Any + Clone>(map: &AnyMap<String>, field: &'static str) -> Result<T, FromAnyMapError> {
+        let value = map.get(field).ok_or(FromAnyMapError::Missing { field })?;
+        match value.as_type::<T>() {
+            Some(value) => Ok(value.clone()),
+            None => Err(FromAnyMapError::Mismatch {
+                field,
+                error: TypeMismatchError::new::<T>(value),
+            }),
         }
     }
+}
 
-    pub fn as_type<T: Any>(&self) -> Option<&T> {
-        (*self.inner).downcast_ref::<T>()
-    }
+use alloc::{borrow::Cow, boxed::Box, string::String, sync::Arc, vec::Vec};
+use core::{any::Any, borrow::Borrow, fmt, hash::{BuildHasher, Hash}, marker::PhantomData, ops::Index, str::FromStr};
 
-    pub fn is<T: Any>(&self) -> bool {
-        (*self.inner).is::<T>()
-    }
+use hash::{hash_map::Drain, HashMap, TryReserveError};
 
-    pub fn into_inner(self) -> Box<dyn Any> {
-        self.inner
-    }
+/// Creates an [`AnyMap`] containing the given entries.
+///
+/// ```
+/// let map = anymap::anymap! {
+///     "host" => "localhost",
+///     "port" => 8080u16,
+///     "debug" => true,
+/// };
+/// assert_eq!(map.get_typed::<u16>("port"), Some(&8080));
+/// ```
+#[macro_export]
+macro_rules! anymap {
+    () => {
+        $crate::AnyMap::new()
+    };
+    ($($key:expr => $value:expr),+ $(,)?) => {{
+        let mut map = $crate::AnyMap::with_capacity([$(stringify!($key)),+].len());
+        $(
+            map.insert($key, $value);
+        )+
+        map
+    }};
 }
 
-pub struct AnyMap<K> {
-    pub(crate) map: HashMap<K, Value>,
+/// A map from keys of type `K` to values of any type, storing its entries in
+/// the backend `B`.
+///
+/// Keys are `String`s and entries live in a `HashMap` unless specified
+/// otherwise. Other [`MapBackend`]s keep keys sorted, in insertion order or
+/// inline, e.g. for a [`SortedAnyMap`] or an [`OrderedAnyMap`]. Maps with
+/// another backend are created with [`Default`] rather than `new`.
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use anymap::{AnyMap, Value};
+///
+/// let mut map = AnyMap::<&str, BTreeMap<_, Value>>::default();
+/// map.insert("b", 2);
+/// map.insert("a", "one");
+/// assert_eq!(map.get_typed::<i32>("b"), Some(&2));
+/// assert_eq!(map.keys().collect::<Vec<_>>(), [&"a", &"b"]);
+/// ```
+pub struct AnyMap<K = String, B = HashBackend<K>> {
+    pub(crate) map: B,
+    marker: PhantomData<K>,
 }
 
 impl<K> AnyMap<K> {
     #[inline]
     #[must_use]
     pub fn new() -> Self {
-        AnyMap {
-            map: HashMap::new(),
-        }
+        Self::with_hasher(DefaultHashBuilder::default())
     }
 
     #[inline]
     #[must_use]
     pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, DefaultHashBuilder::default())
+    }
+}
+
+impl<K> AnyMap<K, HashBackend<K, FxBuildHasher>> {
+    /// Creates an empty map in const context, hashing keys with
+    /// [`FxBuildHasher`] since the default hasher can't be created there.
+    ///
+    /// ```
+    /// use std::cell::RefCell;
+    ///
+    /// use anymap::FxAnyMap;
+    ///
+    /// thread_local! {
+    ///     static SCRATCH: RefCell<FxAnyMap<&'static str>> = const { RefCell::new(FxAnyMap::const_new()) };
+    /// }
+    ///
+    /// SCRATCH.with_borrow_mut(|map| map.insert("frame", 1u64));
+    /// assert!(SCRATCH.with_borrow(|map| map.contains_key("frame")));
+    /// ```
+    #[inline]
+    #[must_use]
+    pub const fn const_new() -> Self {
+        Self::with_hasher(FxBuildHasher)
+    }
+}
+
+impl<K, S> AnyMap<K, HashBackend<K, S>> {
+    /// Creates an empty map that hashes keys with `hasher`.
+    #[inline]
+    #[must_use]
+    pub const fn with_hasher(hasher: S) -> Self {
         AnyMap {
-            map: HashMap::with_capacity(capacity),
+            map: HashMap::with_hasher(hasher),
+            marker: PhantomData,
         }
     }
 
+    #[inline]
+    #[must_use]
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+        Self::from_backend(HashMap::with_capacity_and_hasher(capacity, hasher))
+    }
+
+    #[inline]
+    pub fn hasher(&self) -> &S {
+        self.map.hasher()
+    }
+
     #[inline]
     pub fn capacity(&self) -> usize {
         self.map.capacity()
     }
 
     #[inline]
-    pub fn keys(&self) -> Keys<'_, K, Value> {
-        self.map.keys()
+    pub fn drain(&mut self) -> Drain<'_, K, Value> {
+        self.map.drain()
+    }
+}
+
+impl<K, B> AnyMap<K, B> {
+    #[inline]
+    pub const fn from_backend(backend: B) -> Self {
+        AnyMap {
+            map: backend,
+            marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub const fn backend(&self) -> &B {
+        &self.map
+    }
+
+    #[inline]
+    pub fn into_backend(self) -> B {
+        self.map
     }
+}
 
+impl<K, B: MapBackend<K>> AnyMap<K, B> {
     #[inline]
-    pub fn values(&self) -> Values<'_, K, Value> {
-        self.map.values()
+    pub fn keys(&self) -> Keys<'_, K, B> {
+        self.map.iter().map(|(k, _)| k)
     }
 
-    // TODO: values_mut
+    #[inline]
+    pub fn values(&self) -> Values<'_, K, B> {
+        self.map.iter().map(|(_, v)| v)
+    }
+
+    #[inline]
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, B> {
+        self.map.iter_mut().map(|(_, v)| v)
+    }
 
+    /// Returns an iterator over the values that are a `T`.
     #[inline]
-    pub fn iter(&self) -> Iter<'_, K, Value> {
+    pub fn values_typed<T: Any>(&self) -> ValuesTyped<'_, K, T, B> {
+        ValuesTyped {
+            inner: self.map.iter(),
+            marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn values_typed_mut<T: Any>(&mut self) -> ValuesTypedMut<'_, K, T, B> {
+        ValuesTypedMut {
+            inner: self.map.iter_mut(),
+            marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn iter(&self) -> B::Iter<'_> {
         self.map.iter()
     }
 
+    /// Returns an iterator over the entries whose value is a `T`.
+    #[inline]
+    pub fn iter_typed<T: Any>(&self) -> IterTyped<'_, K, T, B> {
+        IterTyped {
+            inner: self.map.iter(),
+            marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn iter_mut(&mut self) -> B::IterMut<'_> {
+        self.map.iter_mut()
+    }
+
+    /// Renders every value with the function registered through
+    /// [`registry::register_display`]. Values without one are rendered as
+    /// their type name in angle brackets.
+    #[cfg(feature = "std")]
+    pub fn to_string_map(&self) -> HashMap<&K, String>
+    where
+        K: Eq + Hash,
+    {
+        self.map
+            .iter()
+            .map(|(k, v)| {
+                let rendered = match v.display() {
+                    Some(display) => display.to_string(),
+                    None => format!("<{}>", v.describe_type()),
+                };
+                (k, rendered)
+            })
+            .collect()
+    }
+
+    /// Consumes the map into its entries, in the order of the backend.
+    #[inline]
+    pub fn into_entries(self) -> Vec<(K, Value)> {
+        self.map.into_entries().collect()
+    }
+
+    /// Returns the entries sorted by key, for deterministic output.
+    pub fn to_sorted_entries(&self) -> Vec<(&K, &Value)>
+    where
+        K: Ord,
+    {
+        let mut entries: Vec<_> = self.map.iter().collect();
+        entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        entries
+    }
+
     #[inline]
     pub fn len(&self) -> usize {
         self.map.len()
@@ -104,34 +377,210 @@ impl<K> AnyMap<K> {
     pub fn clear(&mut self) {
         self.map.clear()
     }
+
+    #[inline]
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&K, &mut Value) -> bool,
+    {
+        self.map.retain(f)
+    }
+
+    /// Returns an iterator which removes and yields every entry for which
+    /// `pred` returns `true`. Entries not yielded before the iterator is
+    /// dropped are kept.
+    #[inline]
+    pub fn extract_if<F>(&mut self, pred: F) -> B::ExtractIf<'_, F>
+    where
+        F: FnMut(&K, &mut Value) -> bool,
+    {
+        self.map.extract_if(pred)
+    }
 }
 
-impl<K: Eq + Hash> AnyMap<K> {
+impl<K: Eq + Hash, S: BuildHasher> AnyMap<K, HashBackend<K, S>> {
+    /// Reserves space for at least `additional` more entries.
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.map.reserve(additional)
+    }
+
+    #[inline]
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.map.try_reserve(additional)
+    }
+
     #[inline]
-    pub fn get<Q: ?Sized>(&self, key: &Q) -> Option<&Value>
+    pub fn shrink_to_fit(&mut self) {
+        self.map.shrink_to_fit()
+    }
+
+    /// Shrinks the capacity as much as possible, but not below
+    /// `min_capacity`.
+    #[inline]
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        self.map.shrink_to(min_capacity)
+    }
+
+    /// Returns mutable references to the values of several distinct keys at
+    /// once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any two keys are equal.
+    #[inline]
+    pub fn get_many_mut<Q, const N: usize>(&mut self, keys: [&Q; N]) -> [Option<&mut Value>; N]
     where
         K: Borrow<Q>,
-        Q: Hash + Eq,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.map.get_disjoint_mut(keys)
+    }
+
+    /// Clones the map through the [`registry`], or returns the keys whose
+    /// values have a type that was not registered with
+    /// [`registry::register_clone`].
+    #[cfg(feature = "std")]
+    pub fn try_clone(&self) -> Result<Self, Vec<&K>>
+    where
+        K: Clone,
+        S: Clone,
+    {
+        let mut map = HashMap::with_capacity_and_hasher(self.map.len(), self.map.hasher().clone());
+        let mut failed = Vec::new();
+        for (key, value) in &self.map {
+            match value.try_clone() {
+                Some(value) => {
+                    map.insert(key.clone(), value);
+                }
+                None => failed.push(key),
+            }
+        }
+        if failed.is_empty() {
+            Ok(AnyMap::from_backend(map))
+        } else {
+            Err(failed)
+        }
+    }
+}
+
+impl<K, B: MapBackend<K>> AnyMap<K, B> {
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<&Value>
+    where
+        B: MapLookup<K, Q>,
+        Q: ?Sized,
     {
         self.map.get(key)
     }
 
     #[inline]
-    pub fn get_typed<T: Any>(&self, key: &K) -> Option<&'_ T>
+    pub fn get_typed<T: Any>(&self, key: &(impl ?Sized + LookupKey<K, B>)) -> Option<&T> {
+        key.get_in(&self.map).and_then(|v| v.as_type::<T>())
+    }
+
+    /// Returns a value inserted with [`insert_dyn`](Self::insert_dyn) as the
+    /// unsized type `Dyn`, typically a trait object.
+    #[inline]
+    pub fn get_dyn<Dyn: ?Sized + 'static>(&self, key: &(impl ?Sized + LookupKey<K, B>)) -> Option<&Dyn> {
+        key.get_in(&self.map).and_then(|v| v.as_dyn::<Dyn>())
+    }
+
+    #[inline]
+    pub fn get_dyn_mut<Dyn: ?Sized + 'static>(&mut self, key: &(impl ?Sized + LookupKey<K, B>)) -> Option<&mut Dyn> {
+        key.get_mut_in(&mut self.map).and_then(|v| v.as_dyn_mut::<Dyn>())
+    }
+
+    /// Returns a clone of an `Arc<T>` stored under `key`, e.g. through
+    /// [`insert_arc`](Self::insert_arc).
+    #[inline]
+    pub fn get_arc<T: ?Sized + 'static>(&self, key: &(impl ?Sized + LookupKey<K, B>)) -> Option<Arc<T>> {
+        self.get_typed::<Arc<T>>(key).cloned()
+    }
+
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        B: MapLookup<K, Q>,
+        Q: ?Sized,
     {
-        self.map.get(key).and_then(|v| v.as_type::<T>())
+        self.map.get(key).is_some()
+    }
+
+    /// Returns whether `key` is present and holds a `T`.
+    #[inline]
+    pub fn contains_typed<T: Any>(&self, key: &(impl ?Sized + LookupKey<K, B>)) -> bool {
+        key.get_in(&self.map).is_some_and(|v| v.is::<T>())
     }
 
     #[inline]
-    pub fn contains_key<Q: ?Sized>(&self, key: &Q) -> bool
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut Value>
     where
-        K: Borrow<Q>,
-        Q: Hash + Eq,
+        B: MapLookup<K, Q>,
+        Q: ?Sized,
+    {
+        self.map.get_mut(key)
+    }
+
+    /// Returns a stored primitive number of any type converted to `T`. See
+    /// [`Number`] for the conversion rules.
+    #[inline]
+    pub fn get_lossy<T: Number>(&self, key: &(impl ?Sized + LookupKey<K, B>)) -> Option<T> {
+        key.get_in(&self.map).and_then(|v| v.coerce::<T>())
+    }
+
+    #[inline]
+    pub fn get_typed_mut<T: Any>(&mut self, key: &(impl ?Sized + LookupKey<K, B>)) -> Option<&mut T> {
+        key.get_mut_in(&mut self.map).and_then(|v| v.as_type_mut::<T>())
+    }
+
+    #[inline]
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, B> {
+        self.map.entry(key)
+    }
+
+    /// Like [`entry`](Self::entry), but takes the key by reference and only
+    /// converts it into a `K` if the entry is vacant and gets filled.
+    ///
+    /// ```
+    /// use anymap::{AnyMap, Value};
+    ///
+    /// let mut counters: AnyMap<String> = AnyMap::new();
+    /// for word in ["a", "b", "a"] {
+    ///     let count = counters.entry_ref(word).or_insert(Value::new(0));
+    ///     *count.as_type_mut::<i32>().unwrap() += 1;
+    /// }
+    /// assert_eq!(counters.get_typed::<i32>("a"), Some(&2));
+    /// ```
+    #[inline]
+    pub fn entry_ref<'b, Q>(&mut self, key: &'b Q) -> EntryRef<'_, 'b, K, Q, B>
+    where
+        K: Borrow<Q> + From<&'b Q>,
+        B: MapLookup<K, Q>,
+        Q: ?Sized,
     {
-        self.map.contains_key(key)
+        EntryRef::new(&mut self.map, key)
+    }
+
+    #[inline]
+    pub fn entry_typed<T: Any>(&mut self, key: K) -> TypedEntry<'_, K, T, B> {
+        TypedEntry::new(self.entry(key))
     }
 
-    // TODO: get_mut
+    /// Returns the `T` stored under `key`, inserting the result of `default`
+    /// first if the key is vacant.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` holds a value of a type other than `T`. Use
+    /// [`entry_typed`](Self::entry_typed) to handle that case instead.
+    #[inline]
+    pub fn get_or_insert_with<T: Any, F: FnOnce() -> T>(&mut self, key: K, default: F) -> &mut T {
+        match self.entry_typed::<T>(key).or_insert_with(default) {
+            Ok(value) => value,
+            Err(err) => panic!("{err}"),
+        }
+    }
 
     #[inline]
     pub fn insert_val(&mut self, key: K, value: Value) -> Option<Value> {
@@ -144,44 +593,259 @@ impl<K: Eq + Hash> AnyMap<K> {
         self.map.insert(key, Value::new(value))
     }
 
+    /// Inserts an already boxed value without boxing it again.
     #[inline]
-    pub fn remove<Q: ?Sized>(&mut self, key: &Q) -> Option<Value>
+    pub fn insert_boxed(&mut self, key: K, value: Box<dyn Any>) -> Option<Value> {
+        self.map.insert(key, Value::from_box(value))
+    }
+
+    /// Inserts a boxed unsized value such as `Box<dyn Handler>`, to be
+    /// retrieved with [`get_dyn`](Self::get_dyn) without knowing the concrete
+    /// type.
+    #[inline]
+    pub fn insert_dyn<Dyn: ?Sized + 'static>(&mut self, key: K, value: Box<Dyn>) -> Option<Value> {
+        self.map.insert(key, Value::new(value))
+    }
+
+    /// Parses `input` as a `T` and inserts it, leaving the map unchanged if
+    /// parsing fails.
+    #[inline]
+    pub fn insert_parsed<T: Any + FromStr>(&mut self, key: K, input: &str) -> Result<Option<Value>, T::Err> {
+        let value = input.parse::<T>()?;
+        Ok(self.insert(key, value))
+    }
+
+    /// Parses `input` as the type registered under `type_name` with
+    /// [`registry::register_parse`] and inserts it. Primitives and `String`
+    /// are registered under their Rust names.
+    #[cfg(feature = "std")]
+    pub fn insert_parsed_as(&mut self, key: K, type_name: &str, input: &str) -> Result<Option<Value>, ParseValueError> {
+        let parse = registry::lookup_parser(type_name)
+            .ok_or_else(|| ParseValueError::UnknownType(type_name.to_owned()))?;
+        let value = parse(input).map_err(|message| ParseValueError::Invalid {
+            type_name: type_name.to_owned(),
+            message,
+        })?;
+        Ok(self.insert_val(key, value))
+    }
+
+    #[inline]
+    pub fn insert_arc<T: ?Sized + 'static>(&mut self, key: K, value: Arc<T>) -> Option<Value> {
+        self.map.insert(key, Value::new(value))
+    }
+
+    /// Inserts `value` only if `key` is vacant. Never overwrites an existing
+    /// value.
+    #[inline]
+    pub fn try_insert<T: Any>(&mut self, key: K, value: T) -> Result<&mut Value, OccupiedError<'_, K, T, B>> {
+        match self.entry(key) {
+            Entry::Occupied(entry) => Err(OccupiedError { entry, value }),
+            Entry::Vacant(entry) => Ok(entry.insert(Value::new(value))),
+        }
+    }
+
+    /// Inserts `value` and returns the map, for building maps in expression
+    /// position.
+    #[inline]
+    #[must_use]
+    pub fn with<T: Any>(mut self, key: K, value: T) -> Self {
+        self.insert(key, value);
+        self
+    }
+
+    /// Inserts `value`, returning the value it replaced if that was also a
+    /// `T`. A replaced value of a different type is dropped.
+    #[inline]
+    pub fn insert_replace<T: Any>(&mut self, key: K, value: T) -> Option<T> {
+        self.insert(key, value)?.downcast::<T>().ok()
+    }
+
+    #[inline]
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<Value>
     where
-        K: Borrow<Q>,
-        Q: Hash + Eq,
+        B: MapLookup<K, Q>,
+        Q: ?Sized,
     {
         self.map.remove(key)
     }
+
+    /// Removes and returns the value stored under `key` if it is of type `T`.
+    ///
+    /// If the value has a different type it is left in the map.
+    #[inline]
+    pub fn remove_typed<T: Any>(&mut self, key: &(impl ?Sized + LookupKey<K, B>)) -> Option<T> {
+        if !key.get_in(&self.map)?.is::<T>() {
+            return None;
+        }
+        self.try_remove_typed(key)?.ok()
+    }
+
+    /// Removes the value stored under `key`, returning it as a `T`, or as the
+    /// original [`Value`] if it has a different type.
+    #[inline]
+    pub fn try_remove_typed<T: Any>(&mut self, key: &(impl ?Sized + LookupKey<K, B>)) -> Option<Result<T, Value>> {
+        let value = key.remove_from(&mut self.map)?;
+        Some(value.downcast::<T>())
+    }
 }
 
-impl<K> Default for AnyMap<K> {
+impl<B: MapBackend<String>> AnyMap<String, B> {
+    /// Inserts `value` under `key`, which may be a `&str` or anything else
+    /// convertible to a `String`.
+    ///
+    /// ```
+    /// use anymap::AnyMap;
+    ///
+    /// let mut map: AnyMap = AnyMap::new();
+    /// map.set("user", "alice");
+    /// assert_eq!(map.get_typed::<&str>("user"), Some(&"alice"));
+    /// ```
+    #[inline]
+    pub fn set<T: Any>(&mut self, key: impl Into<String>, value: T) -> Option<Value> {
+        self.insert(key.into(), value)
+    }
+}
+
+/// A map with values of any type whose keys are string literals or owned
+/// strings, so that literal keys are stored without an allocation.
+///
+/// ```
+/// use anymap::CowAnyMap;
+///
+/// let mut extensions = CowAnyMap::new();
+/// extensions.set("request_id", 7u64);
+/// extensions.set(format!("header.{}", "accept"), "text/html");
+/// assert_eq!(extensions.get_typed::<u64>("request_id"), Some(&7));
+/// assert!(extensions.contains_key("header.accept"));
+/// ```
+pub type CowAnyMap<S = DefaultHashBuilder> = AnyMap<Cow<'static, str>, HashBackend<Cow<'static, str>, S>>;
+
+impl<B: MapBackend<Cow<'static, str>>> AnyMap<Cow<'static, str>, B> {
+    /// Inserts `value` under `key`, borrowing it if it is a `&'static str`.
+    #[inline]
+    pub fn set<T: Any>(&mut self, key: impl Into<Cow<'static, str>>, value: T) -> Option<Value> {
+        self.insert(key.into(), value)
+    }
+}
+
+impl<K, B: Default> Default for AnyMap<K, B> {
     #[inline]
     fn default() -> Self {
-        AnyMap::new()
+        AnyMap {
+            map: B::default(),
+            marker: PhantomData,
+        }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Clones every value through the [`registry`].
+///
+/// # Panics
+///
+/// Panics if a stored value's type was not registered with
+/// [`registry::register_clone`]. Use [`AnyMap::try_clone`] to handle that
+/// case instead.
+#[cfg(feature = "std")]
+impl<K: Clone + Eq + Hash, S: BuildHasher + Clone> Clone for AnyMap<K, HashBackend<K, S>> {
+    fn clone(&self) -> Self {
+        let mut map = HashMap::with_capacity_and_hasher(self.map.len(), self.map.hasher().clone());
+        map.extend(
+            self.map
+                .iter()
+                .map(|(k, v)| (k.clone(), v.try_clone().expect("value type is not registered as cloneable"))),
+        );
+        AnyMap::from_backend(map)
+    }
+}
 
-    #[test]
-    fn value_stores() {
-        let val = Value::new(1);
-        assert!(val.is::<i32>());
-        assert!(!val.is::<String>());
-        assert_eq!(val.as_type::<i32>().unwrap(), &1);
+/// Lists every key with its [`Value`], which shows the stored type name and,
+/// if registered with [`registry::register_debug`], the value itself.
+impl<K: fmt::Debug, B: MapBackend<K>> fmt::Debug for AnyMap<K, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.map.iter()).finish()
     }
+}
 
-    #[test]
-    fn value_stores_any() {
-        let val = Value::new(1);
-        let val2 = Value::new("hello");
-        assert!(val.is::<i32>());
-        assert!(val2.is::<&str>());
-        assert_eq!(val.as_type::<i32>().unwrap(), &1);
-        assert_eq!(val2.as_type::<&str>().unwrap(), &"hello");
+/// Maps are equal if they hold the same keys and each pair of values compares
+/// equal through [`registry::register_eq`]. Values of unregistered types never
+/// compare equal, so maps containing them are not even equal to themselves,
+/// which is why maps are not [`Eq`].
+#[cfg(feature = "std")]
+impl<K, B: MapLookup<K, K>> PartialEq for AnyMap<K, B> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.map.get(k).is_some_and(|other| v == other))
     }
+}
+
+impl<K, Q, B> Index<&Q> for AnyMap<K, B>
+where
+    B: MapLookup<K, Q>,
+    Q: ?Sized,
+{
+    type Output = Value;
+
+    /// # Panics
+    ///
+    /// Panics if `key` is not present in the map.
+    #[inline]
+    fn index(&self, key: &Q) -> &Value {
+        self.get(key).expect("key not found in AnyMap")
+    }
+}
+
+/// Collects `(key, value)` pairs of a single type `T`, which may itself be
+/// [`Value`] to collect values of mixed types.
+impl<K, T: Any, B: MapBackend<K> + Default> FromIterator<(K, T)> for AnyMap<K, B> {
+    fn from_iter<I: IntoIterator<Item = (K, T)>>(iter: I) -> Self {
+        let mut map = AnyMap::default();
+        map.extend(iter);
+        map
+    }
+}
+
+/// Inserts `(key, value)` pairs of a single type `T`, which may itself be
+/// [`Value`] to insert values of mixed types.
+impl<K, T: Any, B: MapBackend<K>> Extend<(K, T)> for AnyMap<K, B> {
+    fn extend<I: IntoIterator<Item = (K, T)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.map.insert(key, Value::new(value));
+        }
+    }
+}
+
+impl<K, B: MapBackend<K>> IntoIterator for AnyMap<K, B> {
+    type Item = (K, Value);
+    type IntoIter = B::IntoIter;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.into_entries()
+    }
+}
+
+impl<'a, K, B: MapBackend<K>> IntoIterator for &'a AnyMap<K, B> {
+    type Item = (&'a K, &'a Value);
+    type IntoIter = B::Iter<'a>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, B: MapBackend<K>> IntoIterator for &'a mut AnyMap<K, B> {
+    type Item = (&'a K, &'a mut Value);
+    type IntoIter = B::IterMut<'a>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
     #[test]
     fn any_map_stores() {
@@ -203,10 +867,587 @@ mod tests {
         assert!(map.contains_key("world"));
     }
 
+    #[test]
+    fn any_map_get_typed_borrowed() {
+        let mut map = AnyMap::new();
+        map.insert(String::from("key"), 1);
+        assert_eq!(map.get_typed::<i32>("key"), Some(&1));
+        assert_eq!(map.get_typed::<u8>("key"), None);
+        assert_eq!(map.get_typed::<i32>("missing"), None);
+    }
+
+    #[test]
+    fn any_map_get_lossy() {
+        let mut map = AnyMap::new();
+        map.insert("port", 8080u16);
+        map.insert("ratio", 0.5f32);
+        assert_eq!(map.get_lossy::<u64>("port"), Some(8080));
+        assert_eq!(map.get_lossy::<f64>("port"), Some(8080.0));
+        assert_eq!(map.get_lossy::<f64>("ratio"), Some(0.5));
+        assert_eq!(map.get_lossy::<u8>("port"), None);
+    }
+
+    #[test]
+    fn any_map_contains_typed() {
+        let mut map = AnyMap::new();
+        map.insert("a", 1);
+        assert!(map.contains_typed::<i32>("a"));
+        assert!(!map.contains_typed::<u8>("a"));
+        assert!(!map.contains_typed::<i32>("b"));
+    }
+
+    #[test]
+    fn any_map_get_mut() {
+        let mut map = AnyMap::new();
+        map.insert("counter", 1);
+        *map.get_mut("counter").unwrap().as_type_mut::<i32>().unwrap() += 1;
+        assert_eq!(map.get("counter").unwrap().as_type::<i32>().unwrap(), &2);
+        assert!(map.get_mut("counter").unwrap().as_type_mut::<u8>().is_none());
+        assert!(map.get_mut("missing").is_none());
+    }
+
+    #[test]
+    fn any_map_values_mut() {
+        let mut map = AnyMap::new();
+        map.insert("a", vec![1, 2]);
+        map.insert("b", vec![3]);
+        map.insert("c", "not a vec");
+        for value in map.values_mut() {
+            if let Some(buffer) = value.as_type_mut::<Vec<i32>>() {
+                buffer.clear();
+            }
+        }
+        assert!(map.get("a").unwrap().as_type::<Vec<i32>>().unwrap().is_empty());
+        assert!(map.get("b").unwrap().as_type::<Vec<i32>>().unwrap().is_empty());
+        assert_eq!(map.get("c").unwrap().as_type::<&str>().unwrap(), &"not a vec");
+    }
+
+    #[test]
+    fn any_map_values_typed() {
+        struct Timer(u32);
+        let mut map = AnyMap::new();
+        map.insert("a", Timer(1));
+        map.insert("b", Timer(2));
+        map.insert("c", "not a timer");
+        map.values_typed_mut::<Timer>().for_each(|t| t.0 += 10);
+        let total: u32 = map.values_typed::<Timer>().map(|t| t.0).sum();
+        assert_eq!(total, 23);
+        assert_eq!(map.values_typed::<&str>().count(), 1);
+    }
+
+    #[test]
+    fn any_map_iter_mut() {
+        let mut map = AnyMap::new();
+        map.insert("one", 1);
+        map.insert("two", 2);
+        for (key, value) in map.iter_mut() {
+            if *key == "two" {
+                *value.as_type_mut::<i32>().unwrap() *= 10;
+            }
+        }
+        assert_eq!(map.get("one").unwrap().as_type::<i32>().unwrap(), &1);
+        assert_eq!(map.get("two").unwrap().as_type::<i32>().unwrap(), &20);
+    }
+
+    #[test]
+    fn any_map_insert_replace() {
+        let mut map = AnyMap::new();
+        assert_eq!(map.insert_replace("a", 1), None);
+        assert_eq!(map.insert_replace("a", 2), Some(1));
+        map.insert("b", "b");
+        assert_eq!(map.insert_replace("b", 3), None);
+        assert_eq!(map.get_typed::<i32>("b"), Some(&3));
+    }
+
+    #[test]
+    fn any_map_insert_boxed() {
+        let mut map = AnyMap::new();
+        let boxed: Box<dyn Any> = Box::new(1i64);
+        map.insert_boxed("a", boxed);
+        assert_eq!(map.get_typed::<i64>("a"), Some(&1));
+    }
+
+    #[test]
+    fn any_map_insert_val_from() {
+        let mut map = AnyMap::new();
+        map.insert_val("port", 8080u16.into());
+        map.insert_val("host", String::from("localhost").into());
+        assert_eq!(map.get_typed::<u16>("port"), Some(&8080));
+        assert_eq!(map.get_typed::<String>("host").unwrap(), "localhost");
+    }
+
+    #[test]
+    fn any_map_insert_dyn() {
+        trait Handler {
+            fn handle(&mut self, input: u32) -> u32;
+        }
+
+        struct Double;
+        struct Sum(u32);
+
+        impl Handler for Double {
+            fn handle(&mut self, input: u32) -> u32 {
+                input * 2
+            }
+        }
+
+        impl Handler for Sum {
+            fn handle(&mut self, input: u32) -> u32 {
+                self.0 += input;
+                self.0
+            }
+        }
+
+        let mut map = AnyMap::new();
+        map.insert_dyn::<dyn Handler>("double", Box::new(Double));
+        map.insert_dyn::<dyn Handler>("sum", Box::new(Sum(1)));
+        assert_eq!(map.get_dyn_mut::<dyn Handler>("double").unwrap().handle(4), 8);
+        assert_eq!(map.get_dyn_mut::<dyn Handler>("sum").unwrap().handle(4), 5);
+        assert!(map.get_dyn::<dyn Handler>("sum").is_some());
+        assert!(map.get_dyn::<dyn fmt::Debug>("sum").is_none());
+        let slice: Box<[u8]> = Box::new([1, 2]);
+        map.insert_dyn("slice", slice);
+        assert_eq!(map.get_dyn::<[u8]>("slice"), Some(&[1, 2][..]));
+    }
+
+    #[test]
+    fn any_map_arc() {
+        let service = Arc::new(String::from("db"));
+        let mut map = AnyMap::new();
+        map.insert_arc("service", service.clone());
+        let shared = map.get_arc::<String>("service").unwrap();
+        assert!(Arc::ptr_eq(&shared, &service));
+        assert_eq!(Arc::strong_count(&service), 3);
+        assert!(map.get_arc::<u8>("service").is_none());
+
+        let name: Arc<str> = Arc::from("name");
+        map.insert_arc("unsized", name);
+        assert_eq!(&*map.get_arc::<str>("unsized").unwrap(), "name");
+    }
+
+    #[test]
+    fn any_map_try_insert() {
+        let mut map = AnyMap::new();
+        assert!(map.try_insert("a", 1).unwrap().is::<i32>());
+        let err = map.try_insert("a", "other").unwrap_err();
+        assert_eq!(err.value, "other");
+        assert_eq!(err.entry.get().as_type::<i32>(), Some(&1));
+        assert_eq!(err.to_string(), "failed to insert, key \"a\" already exists");
+        assert_eq!(map.get_typed::<i32>("a"), Some(&1));
+    }
+
+    #[test]
+    fn any_map_get_or_insert_with() {
+        let mut map = AnyMap::new();
+        map.get_or_insert_with("log", Vec::new).push("first");
+        map.get_or_insert_with("log", Vec::new).push("second");
+        assert_eq!(map.get_typed::<Vec<&str>>("log").unwrap(), &["first", "second"]);
+    }
+
+    #[test]
+    #[should_panic = "not of type `i32`"]
+    fn any_map_get_or_insert_with_mismatch() {
+        let mut map = AnyMap::new();
+        map.insert("a", "a");
+        map.get_or_insert_with("a", || 0i32);
+    }
+
+    #[test]
+    fn any_map_insert_parsed() {
+        let mut map = AnyMap::new();
+        map.insert_parsed::<u16>("port", "8080").unwrap();
+        assert_eq!(map.get_typed::<u16>("port"), Some(&8080));
+        assert!(map.insert_parsed::<u16>("bad", "x").is_err());
+        assert!(!map.contains_key("bad"));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn any_map_insert_parsed_as() {
+        #[derive(Debug, PartialEq)]
+        struct Level(u8);
+
+        impl FromStr for Level {
+            type Err = String;
+
+            fn from_str(s: &str) -> Result<Self, String> {
+                s.strip_prefix('L').and_then(|n| n.parse().ok()).map(Level).ok_or_else(|| format!("bad level {s:?}"))
+            }
+        }
+
+        let mut map = AnyMap::new();
+        map.insert_parsed_as("debug", "bool", "true").unwrap();
+        assert_eq!(map.get_typed::<bool>("debug"), Some(&true));
+        assert_eq!(
+            map.insert_parsed_as("level", "Level", "L3").unwrap_err(),
+            ParseValueError::UnknownType(String::from("Level")),
+        );
+        registry::register_parse::<Level>("Level");
+        map.insert_parsed_as("level", "Level", "L3").unwrap();
+        assert_eq!(map.get_typed::<Level>("level"), Some(&Level(3)));
+        let err = map.insert_parsed_as("level", "Level", "3").unwrap_err();
+        assert_eq!(err.to_string(), "invalid value for type `Level`: bad level \"3\"");
+    }
+
+    #[test]
+    fn any_map_remove_typed() {
+        let mut map = AnyMap::new();
+        map.insert("name", String::from("anymap"));
+        assert_eq!(map.remove_typed::<i32>("name"), None);
+        assert!(map.contains_key("name"));
+        assert_eq!(map.remove_typed::<String>("name").unwrap(), "anymap");
+        assert!(!map.contains_key("name"));
+        assert_eq!(map.remove_typed::<String>("name"), None);
+    }
+
+    #[test]
+    fn any_map_try_remove_typed() {
+        let mut map = AnyMap::new();
+        map.insert("a", 1u8);
+        map.insert("b", 2u8);
+        assert_eq!(map.try_remove_typed::<u8>("a").unwrap().unwrap(), 1);
+        let value = map.try_remove_typed::<i32>("b").unwrap().unwrap_err();
+        assert!(value.is::<u8>());
+        assert!(map.try_remove_typed::<u8>("b").is_none());
+    }
+
+    #[test]
+    fn any_map_iter_typed() {
+        let mut map = AnyMap::new();
+        map.insert("a", 1);
+        map.insert("b", "b");
+        map.insert("c", 3);
+        let mut ints: Vec<_> = map.iter_typed::<i32>().collect();
+        ints.sort();
+        assert_eq!(ints, [(&"a", &1), (&"c", &3)]);
+        assert_eq!(map.iter_typed::<u8>().count(), 0);
+    }
+
+    #[test]
+    fn any_map_into_iter() {
+        let mut map = AnyMap::new();
+        map.insert("a", 1);
+        map.insert("b", 2);
+        for (_, value) in &mut map {
+            *value.as_type_mut::<i32>().unwrap() += 1;
+        }
+        let sum: i32 = (&map).into_iter().map(|(_, v)| v.as_type::<i32>().unwrap()).sum();
+        assert_eq!(sum, 5);
+        let mut keys: Vec<_> = map.into_iter().map(|(k, _)| k).collect();
+        keys.sort();
+        assert_eq!(keys, ["a", "b"]);
+    }
+
+    #[test]
+    fn any_map_from_iter() {
+        let map: AnyMap<&str> = [("a", 1), ("b", 2)].into_iter().collect();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get("b").unwrap().as_type::<i32>().unwrap(), &2);
+
+        let map: AnyMap<&str> = [("a", Value::new(1)), ("b", Value::new("two"))].into_iter().collect();
+        assert!(map.get("a").unwrap().is::<i32>());
+        assert!(map.get("b").unwrap().is::<&str>());
+    }
+
+    #[test]
+    fn any_map_extend() {
+        let mut map = AnyMap::new();
+        map.insert("a", 1);
+        map.extend([("a", 10), ("b", 20)]);
+        map.extend([("c", Value::new("c"))]);
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get("a").unwrap().as_type::<i32>().unwrap(), &10);
+        assert!(map.get("c").unwrap().is::<&str>());
+    }
+
+    #[test]
+    fn any_map_stores_values_as_values() {
+        let mut map = AnyMap::new();
+        assert!(map.insert_replace("v", Value::new(1)).is_none());
+        assert!(map.insert_replace("v", Value::new(2)).unwrap().is::<i32>());
+        assert!(map.get_typed::<Value>("v").unwrap().is::<i32>());
+        assert!(map.entry_typed::<Value>("v").or_insert(Value::new(3)).unwrap().is::<i32>());
+        map.insert_val("flat", Value::new(4));
+        assert_eq!(map.get_typed::<i32>("flat"), Some(&4));
+    }
+
+    #[test]
+    fn any_map_drain() {
+        let mut map = AnyMap::new();
+        map.insert("a", 1);
+        map.insert("b", "two");
+        let capacity = map.capacity();
+        let mut other: AnyMap<&str> = map.drain().collect();
+        assert!(map.is_empty());
+        assert_eq!(map.capacity(), capacity);
+        assert_eq!(other.remove_typed::<&str>("b"), Some("two"));
+    }
+
+    #[test]
+    fn any_map_reserve_and_shrink() {
+        let mut map: AnyMap<u32> = AnyMap::new();
+        map.reserve(100);
+        assert!(map.capacity() >= 100);
+        assert!(map.try_reserve(usize::MAX).is_err());
+        map.extend((0..10).map(|i| (i, i)));
+        map.shrink_to(50);
+        assert!(map.capacity() >= 50 && map.capacity() < 100);
+        map.shrink_to_fit();
+        assert!(map.capacity() >= 10 && map.capacity() < 50);
+        assert_eq!(map.get_typed::<u32>(&9), Some(&9));
+    }
+
+    #[test]
+    fn any_map_retain() {
+        let mut map = AnyMap::new();
+        map.insert("a", 1);
+        map.insert("b", "b");
+        map.insert("c", 3);
+        map.retain(|k, v| *k != "c" && v.is::<i32>());
+        assert_eq!(map.len(), 1);
+        assert!(map.contains_key("a"));
+    }
+
+    #[test]
+    fn any_map_extract_if() {
+        let mut map = AnyMap::new();
+        map.insert("a", 1);
+        map.insert("b", "b");
+        map.insert("c", 3);
+        let ints: AnyMap<&str> = map.extract_if(|_, v| v.is::<i32>()).collect();
+        assert_eq!(ints.len(), 2);
+        assert_eq!(map.len(), 1);
+        assert!(map.contains_key("b"));
+    }
+
+    #[test]
+    fn any_map_index() {
+        let mut map = AnyMap::new();
+        map.insert(String::from("config"), 42);
+        assert_eq!(map["config"].as_type::<i32>().unwrap(), &42);
+    }
+
+    #[test]
+    #[should_panic = "key not found"]
+    fn any_map_index_missing() {
+        let map = AnyMap::<String>::new();
+        let _ = &map["missing"];
+    }
+
+    #[test]
+    fn any_map_get_typed_mut() {
+        let mut map = AnyMap::new();
+        map.insert(String::from("count"), 1);
+        *map.get_typed_mut::<i32>("count").unwrap() += 1;
+        assert_eq!(map.get_typed::<i32>("count"), Some(&2));
+        assert!(map.get_typed_mut::<u8>("count").is_none());
+    }
+
+    #[test]
+    fn any_map_get_many_mut() {
+        let mut map = AnyMap::new();
+        map.insert("left", vec![1, 2, 3]);
+        map.insert("right", Vec::<i32>::new());
+        let [Some(left), Some(right), None] = map.get_many_mut(["left", "right", "missing"]) else {
+            panic!("expected both keys to be present");
+        };
+        right.as_type_mut::<Vec<i32>>().unwrap().append(left.as_type_mut::<Vec<i32>>().unwrap());
+        assert!(map["left"].as_type::<Vec<i32>>().unwrap().is_empty());
+        assert_eq!(map["right"].as_type::<Vec<i32>>().unwrap(), &[1, 2, 3]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn any_map_get_many_mut_overlapping() {
+        let mut map = AnyMap::new();
+        map.insert("a", 1);
+        let _ = map.get_many_mut(["a", "a"]);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn any_map_clone() {
+        let mut map = AnyMap::new();
+        map.insert("a", 1);
+        map.insert("b", String::from("b"));
+        let mut copy = map.clone();
+        *copy.get_typed_mut::<i32>("a").unwrap() = 2;
+        assert_eq!(map.get_typed::<i32>("a"), Some(&1));
+        assert_eq!(copy.get_typed::<String>("b").unwrap(), "b");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn any_map_try_clone() {
+        struct Unregistered;
+        let mut map = AnyMap::new();
+        map.insert("a", 1);
+        let copy = map.try_clone().unwrap();
+        assert_eq!(copy.get_typed::<i32>("a"), Some(&1));
+        map.insert("b", Unregistered);
+        map.insert("c", Unregistered);
+        let mut failed = map.try_clone().unwrap_err();
+        failed.sort();
+        assert_eq!(failed, [&"b", &"c"]);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    #[should_panic = "not registered"]
+    fn any_map_clone_unregistered() {
+        struct Unregistered;
+        let mut map = AnyMap::new();
+        map.insert("a", Unregistered);
+        let _ = map.clone();
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn any_map_to_string_map() {
+        struct Opaque;
+        let mut map = AnyMap::new();
+        map.insert("port", 8080);
+        map.insert("host", "localhost");
+        map.insert("opaque", Opaque);
+        let strings = map.to_string_map();
+        assert_eq!(strings[&"port"], "8080");
+        assert_eq!(strings[&"host"], "localhost");
+        assert!(strings[&"opaque"].starts_with('<'));
+    }
+
+    #[test]
+    fn anymap_macro() {
+        let map = anymap! {
+            "host" => "localhost",
+            "port" => 8080u16,
+        };
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get_typed::<&str>("host"), Some(&"localhost"));
+        let empty: AnyMap<String> = anymap!();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn any_map_with() {
+        let map = AnyMap::new().with("a", 1).with("b", "x").with("a", 2);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get_typed::<i32>("a"), Some(&2));
+    }
+
     #[test]
     fn value_reports_type() {
         let mut map = AnyMap::new();
         map.insert("key", "value");
         assert!(map.get("key").unwrap().is::<&str>());
     }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn any_map_debug() {
+        struct Opaque;
+
+        let mut map = AnyMap::new();
+        map.insert("num", 1);
+        let shown = format!("{:?}", map);
+        #[cfg(feature = "type-names")]
+        assert_eq!(shown, r#"{"num": Value { type: "i32", value: 1 }}"#);
+        #[cfg(not(feature = "type-names"))]
+        assert_eq!(shown, r#"{"num": Value { value: 1 }}"#);
+
+        map.clear();
+        map.insert("opaque", Opaque);
+        assert!(format!("{:?}", map).ends_with(".. }}"));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn any_map_eq() {
+        struct Opaque;
+
+        let a = anymap! { "num" => 1, "name" => "x" };
+        let mut b = anymap! { "name" => "x", "num" => 1 };
+        assert_eq!(a, b);
+
+        b.insert("num", 1u8);
+        assert_ne!(a, b);
+        b.insert("num", 1);
+        b.insert("extra", ());
+        assert_ne!(a, b);
+
+        let mut c = AnyMap::new();
+        c.insert("opaque", Opaque);
+        assert!(!c.eq(&c));
+        let mut d = AnyMap::new();
+        d.insert("nan", f64::NAN);
+        assert!(d.ne(&d));
+    }
+
+    #[test]
+    fn any_map_entries() {
+        let map = anymap! { "b" => 2, "a" => 1, "c" => "three" };
+        let keys: Vec<_> = map.to_sorted_entries().into_iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, ["a", "b", "c"]);
+
+        let mut entries = map.into_entries();
+        entries.sort_unstable_by_key(|(k, _)| *k);
+        assert_eq!(entries[1].1.as_type::<i32>(), Some(&2));
+        assert_eq!(entries.len(), 3);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn any_map_custom_hasher() {
+        use std::hash::{BuildHasherDefault, DefaultHasher};
+
+        type Deterministic = BuildHasherDefault<DefaultHasher>;
+
+        let mut map = AnyMap::with_hasher(Deterministic::default());
+        map.insert("a", 1);
+        map.entry_typed::<i32>("b").or_insert(2).unwrap();
+        assert_eq!(map.get_typed::<i32>("b"), Some(&2));
+        assert_eq!(map.clone(), map);
+
+        let collected: AnyMap<&str, HashBackend<&str, Deterministic>> = map.into_iter().collect();
+        assert_eq!(collected.len(), 2);
+    }
+
+    #[test]
+    fn any_map_const_new() {
+        const EMPTY: FxAnyMap<&str> = FxAnyMap::const_new();
+        let mut map = EMPTY;
+        assert!(map.is_empty());
+        map.insert("a", 1);
+        assert_eq!(map.get_typed::<i32>("a"), Some(&1));
+    }
+
+    #[test]
+    fn any_map_with_capacity_and_hasher() {
+        use std::hash::RandomState;
+
+        let map: AnyMap<&str, HashBackend<&str, RandomState>> = AnyMap::with_capacity_and_hasher(10, RandomState::new());
+        assert!(map.capacity() >= 10);
+        let copy: AnyMap<&str, HashBackend<&str, RandomState>> = AnyMap::with_hasher(map.hasher().clone());
+        assert!(copy.is_empty());
+    }
+
+    #[test]
+    fn any_map_string_keys() {
+        let mut map: AnyMap = AnyMap::new();
+        map.set("name", "alice");
+        map.set(String::from("age"), 30);
+        assert_eq!(map.get_typed::<i32>("age"), Some(&30));
+        assert!(map.set("age", 31).is_some());
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn any_map_cow_keys() {
+        let mut map = CowAnyMap::new();
+        map.set("name", "alice");
+        map.set(String::from("age"), 30);
+        assert!(map.set(String::from("name"), "bob").is_some());
+        assert_eq!(map.get_typed::<&str>("name"), Some(&"bob"));
+        assert!(map.keys().any(|key| matches!(key, Cow::Borrowed("name"))));
+        assert!(map.keys().any(|key| matches!(key, Cow::Owned(_))));
+    }
 }
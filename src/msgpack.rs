@@ -0,0 +1,86 @@
+//! MessagePack snapshots of maps of registered types.
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{AnyMap, MapBackend};
+
+impl<K: Serialize, B: MapBackend<K>> AnyMap<K, B> {
+    /// Encodes the map as MessagePack. Requires the `msgpack` feature.
+    ///
+    /// Every value's type must be registered with
+    /// [`register_serde`](crate::registry::register_serde). Values are written
+    /// without field names, so the output is compact but can only be read
+    /// back by [`from_msgpack`](Self::from_msgpack).
+    ///
+    /// ```
+    /// use anymap::AnyMap;
+    ///
+    /// let mut map = AnyMap::<String>::new();
+    /// map.insert("port".to_owned(), 8080u16);
+    /// let bytes = map.to_msgpack().unwrap();
+    /// let loaded = AnyMap::<String>::from_msgpack(&bytes).unwrap();
+    /// assert_eq!(loaded.get_typed::<u16>("port"), Some(&8080));
+    /// ```
+    pub fn to_msgpack(&self) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+        rmp_serde::to_vec(self)
+    }
+}
+
+impl<K: DeserializeOwned, B: MapBackend<K> + Default> AnyMap<K, B> {
+    /// Decodes a map written by [`to_msgpack`](Self::to_msgpack). Requires
+    /// the `msgpack` feature.
+    pub fn from_msgpack(bytes: &[u8]) -> Result<Self, rmp_serde::decode::Error> {
+        rmp_serde::from_slice(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::registry;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Session {
+        user: String,
+        scopes: Vec<String>,
+    }
+
+    #[test]
+    fn round_trip() {
+        registry::register_serde::<Session>("msgpack::Session");
+
+        let session = Session {
+            user: "alice".to_owned(),
+            scopes: vec!["read".to_owned(), "write".to_owned()],
+        };
+        let mut map = AnyMap::<String>::new();
+        map.insert("retries".to_owned(), 3u8);
+        map.insert("ratio".to_owned(), 0.25f64);
+        map.insert("session".to_owned(), session);
+        let bytes = map.to_msgpack().unwrap();
+        assert!(bytes.len() < serde_json::to_vec(&map).unwrap().len());
+
+        let loaded = AnyMap::<String>::from_msgpack(&bytes).unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded.get_typed::<u8>("retries"), Some(&3));
+        assert_eq!(loaded.get_typed::<f64>("ratio"), Some(&0.25));
+        assert_eq!(loaded.get_typed::<Session>("session").unwrap().scopes, ["read", "write"]);
+    }
+
+    #[test]
+    fn unregistered() {
+        struct Opaque;
+
+        let mut map = AnyMap::<String>::new();
+        map.insert("opaque".to_owned(), Opaque);
+        assert!(map.to_msgpack().is_err());
+
+        let bytes = rmp_serde::to_vec(&HashMap::from([("key", ("msgpack::Missing", 1))])).unwrap();
+        let err = AnyMap::<String>::from_msgpack(&bytes).unwrap_err();
+        assert!(err.to_string().contains("type `msgpack::Missing` is not registered"));
+    }
+}
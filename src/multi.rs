@@ -0,0 +1,157 @@
+use core::{any::Any, borrow::Borrow, hash::Hash};
+
+use crate::{hash::HashMap, HashKey, TypeMap};
+
+/// A map holding several values per key, at most one of each type.
+///
+/// The logical key is the pair of key and value type, so `"user" => 42u32`
+/// and `"user" => "alice"` can be stored side by side.
+///
+/// ```
+/// use anymap::AnyMultiTypeMap;
+///
+/// let mut map = AnyMultiTypeMap::new();
+/// map.insert("user", 42u32);
+/// map.insert("user", "alice");
+/// assert_eq!(map.get_typed::<&str>("user"), Some(&"alice"));
+/// assert_eq!(map.get_typed::<u32>("user"), Some(&42));
+/// ```
+pub struct AnyMultiTypeMap<K> {
+    map: HashMap<K, TypeMap>,
+}
+
+impl<K> AnyMultiTypeMap<K> {
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        AnyMultiTypeMap {
+            map: HashMap::new(),
+        }
+    }
+
+    #[inline]
+    pub fn keys(&self) -> impl Iterator<Item = &K> + '_ {
+        self.map.keys()
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &TypeMap)> + '_ {
+        self.map.iter()
+    }
+
+    /// Returns the number of stored values, counting every type separately.
+    pub fn len(&self) -> usize {
+        self.map.values().map(TypeMap::len).sum()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+}
+
+impl<K: Eq + Hash> AnyMultiTypeMap<K> {
+    /// Returns all values stored under `key`.
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<&TypeMap>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.map.get(key)
+    }
+
+    #[inline]
+    pub fn get_typed<T: Any>(&self, key: &(impl ?Sized + HashKey<K>)) -> Option<&T> {
+        key.get_in(&self.map)?.get::<T>()
+    }
+
+    #[inline]
+    pub fn get_typed_mut<T: Any>(&mut self, key: &(impl ?Sized + HashKey<K>)) -> Option<&mut T> {
+        key.get_mut_in(&mut self.map)?.get_mut::<T>()
+    }
+
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.map.contains_key(key)
+    }
+
+    #[inline]
+    pub fn contains_typed<T: Any>(&self, key: &(impl ?Sized + HashKey<K>)) -> bool {
+        key.get_in(&self.map).is_some_and(TypeMap::contains::<T>)
+    }
+
+    /// Inserts `value`, returning the previous value of the same type under
+    /// `key`. Values of other types under `key` are kept.
+    #[inline]
+    pub fn insert<T: Any>(&mut self, key: K, value: T) -> Option<T> {
+        self.map.entry(key).or_default().insert(value)
+    }
+
+    /// Removes only the value of type `T` stored under `key`.
+    pub fn remove_typed<T: Any>(&mut self, key: &(impl ?Sized + HashKey<K>)) -> Option<T> {
+        let values = key.get_mut_in(&mut self.map)?;
+        let value = values.remove::<T>();
+        if values.is_empty() {
+            key.remove_from(&mut self.map);
+        }
+        value
+    }
+
+    /// Removes all values stored under `key`.
+    #[inline]
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<TypeMap>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.map.remove(key)
+    }
+}
+
+impl<K> Default for AnyMultiTypeMap<K> {
+    #[inline]
+    fn default() -> Self {
+        AnyMultiTypeMap::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn several_types_per_key() {
+        let mut map = AnyMultiTypeMap::new();
+        map.insert(String::from("user"), 42u32);
+        map.insert(String::from("user"), "alice");
+        assert_eq!(map.insert(String::from("user"), 43u32), Some(42));
+        assert_eq!(map.len(), 2);
+        assert!(map.contains_typed::<&str>("user"));
+        assert!(!map.contains_typed::<u8>("user"));
+        assert_eq!(map.get("user").unwrap().len(), 2);
+        *map.get_typed_mut::<u32>("user").unwrap() += 1;
+        assert_eq!(map.get_typed::<u32>("user"), Some(&44));
+    }
+
+    #[test]
+    fn removing_last_type_removes_key() {
+        let mut map = AnyMultiTypeMap::new();
+        map.insert("user", 1u32);
+        map.insert("user", "alice");
+        assert_eq!(map.remove_typed::<u32>("user"), Some(1));
+        assert!(map.contains_key("user"));
+        assert_eq!(map.remove_typed::<&str>("user"), Some("alice"));
+        assert!(!map.contains_key("user"));
+        assert!(map.is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+use core::any::Any;
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// Primitive numeric types a stored number can be coerced into with
+/// [`Value::coerce`](crate::Value::coerce).
+///
+/// Integers convert to integers only when the value fits the target type.
+/// Anything converts to a float, rounding if necessary. Floats convert to
+/// integers only when they have no fractional part and fit the target type.
+pub trait Number: private::Sealed + Copy + 'static {
+    #[doc(hidden)]
+    fn from_i128(value: i128) -> Option<Self>;
+    #[doc(hidden)]
+    fn from_u128(value: u128) -> Option<Self>;
+    #[doc(hidden)]
+    fn from_f64(value: f64) -> Option<Self>;
+}
+
+macro_rules! int {
+    ($($ty:ty),*) => {
+        $(
+            impl private::Sealed for $ty {}
+
+            impl Number for $ty {
+                #[inline]
+                fn from_i128(value: i128) -> Option<Self> {
+                    Self::try_from(value).ok()
+                }
+
+                #[inline]
+                fn from_u128(value: u128) -> Option<Self> {
+                    Self::try_from(value).ok()
+                }
+
+                #[inline]
+                fn from_f64(value: f64) -> Option<Self> {
+                    // `as` saturates, so values out of range of the
+                    // intermediate type are rejected first, and truncates, so
+                    // values with a fractional part don't survive the round
+                    // trip. `i128::MIN` converts exactly and `u128::MAX` rounds
+                    // up to 2^128, so the bounds are exact.
+                    if value < 0.0 {
+                        let int = (value >= i128::MIN as f64).then_some(value as i128)?;
+                        (int as f64 == value).then(|| Self::from_i128(int))?
+                    } else {
+                        let int = (value < u128::MAX as f64).then_some(value as u128)?;
+                        (int as f64 == value).then(|| Self::from_u128(int))?
+                    }
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! float {
+    ($($ty:ty),*) => {
+        $(
+            impl private::Sealed for $ty {}
+
+            impl Number for $ty {
+                #[inline]
+                fn from_i128(value: i128) -> Option<Self> {
+                    Some(value as Self)
+                }
+
+                #[inline]
+                fn from_u128(value: u128) -> Option<Self> {
+                    Some(value as Self)
+                }
+
+                #[inline]
+                fn from_f64(value: f64) -> Option<Self> {
+                    Some(value as Self)
+                }
+            }
+        )*
+    };
+}
+
+int!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+float!(f32, f64);
+
+pub(crate) fn coerce<T: Number>(value: &dyn Any) -> Option<T> {
+    macro_rules! coerce {
+        ($($from:ident: $($ty:ty),*;)*) => {$($(
+            if let Some(&v) = value.downcast_ref::<$ty>() {
+                return T::$from(v as _);
+            }
+        )*)*};
+    }
+    coerce! {
+        from_u128: u8, u16, u32, u64, u128, usize;
+        from_i128: i8, i16, i32, i64, i128, isize;
+        from_f64: f32, f64;
+    }
+    None
+}
@@ -0,0 +1,388 @@
+//! Maps remembering the order their keys were inserted in.
+
+use alloc::{
+    string::String,
+    vec::{self, Vec},
+};
+use core::{
+    borrow::Borrow,
+    hash::{BuildHasher, Hash},
+    iter, mem, slice,
+};
+
+use crate::{
+    hash::HashMap,
+    AnyMap, DefaultHashBuilder, Entry, FxBuildHasher, MapBackend, MapLookup, OccupiedBackendEntry, OccupiedEntry,
+    VacantBackendEntry, VacantEntry, Value,
+};
+
+/// Storage keeping entries in insertion order, with index-based access.
+///
+/// Entries live in a `Vec`, and a side table maps key hashes to positions.
+/// Removing by key keeps the order of the remaining entries, which takes
+/// time linear in the number of entries; [`swap_remove_index`] is constant
+/// time but moves the last entry into the gap.
+///
+/// [`swap_remove_index`]: AnyMap::swap_remove_index
+pub struct OrderedBackend<K, S = DefaultHashBuilder> {
+    entries: Vec<(K, Value)>,
+    indices: HashMap<u64, Vec<usize>, FxBuildHasher>,
+    hasher: S,
+}
+
+/// A map with values of any type that iterates in insertion order.
+///
+/// ```
+/// use anymap::OrderedAnyMap;
+///
+/// let mut fields = OrderedAnyMap::<&str>::default();
+/// fields.insert("name", "alice");
+/// fields.insert("age", 30);
+/// fields.insert("email", "alice@example.com");
+///
+/// let keys: Vec<_> = fields.keys().copied().collect();
+/// assert_eq!(keys, ["name", "age", "email"]);
+/// assert_eq!(fields.get_index(1).unwrap().1.as_type::<i32>(), Some(&30));
+/// ```
+pub type OrderedAnyMap<K = String, S = DefaultHashBuilder> = AnyMap<K, OrderedBackend<K, S>>;
+
+impl<K, S: Default> Default for OrderedBackend<K, S> {
+    #[inline]
+    fn default() -> Self {
+        OrderedBackend {
+            entries: Vec::new(),
+            indices: HashMap::default(),
+            hasher: S::default(),
+        }
+    }
+}
+
+impl<K: Eq + Hash, S: BuildHasher> OrderedBackend<K, S> {
+    fn find<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        let bucket = self.indices.get(&self.hasher.hash_one(key))?;
+        bucket.iter().copied().find(|&i| self.entries[i].0.borrow() == key)
+    }
+
+    /// Replaces `from` by `to` in the bucket of the entry at `from`, or drops
+    /// it if `to` is `None`.
+    fn reindex(&mut self, from: usize, to: Option<usize>) {
+        let hash = self.hasher.hash_one(&self.entries[from].0);
+        let bucket = self.indices.get_mut(&hash).unwrap();
+        let slot = bucket.iter().position(|&i| i == from).unwrap();
+        match to {
+            Some(to) => bucket[slot] = to,
+            None => {
+                bucket.swap_remove(slot);
+                if bucket.is_empty() {
+                    self.indices.remove(&hash);
+                }
+            }
+        }
+    }
+
+    /// Appends an entry for a key that is not present and returns its index.
+    fn push(&mut self, hash: u64, key: K, value: Value) -> usize {
+        let index = self.entries.len();
+        self.indices.entry(hash).or_default().push(index);
+        self.entries.push((key, value));
+        index
+    }
+
+    fn swap_remove_index(&mut self, index: usize) -> Option<(K, Value)> {
+        if index >= self.entries.len() {
+            return None;
+        }
+        self.reindex(index, None);
+        let last = self.entries.len() - 1;
+        if index != last {
+            self.reindex(last, Some(index));
+        }
+        Some(self.entries.swap_remove(index))
+    }
+
+    fn shift_remove_index(&mut self, index: usize) -> Option<(K, Value)> {
+        if index >= self.entries.len() {
+            return None;
+        }
+        self.reindex(index, None);
+        for i in self.indices.values_mut().flatten() {
+            if *i > index {
+                *i -= 1;
+            }
+        }
+        Some(self.entries.remove(index))
+    }
+}
+
+type Iter<'a, K> = iter::Map<slice::Iter<'a, (K, Value)>, fn(&(K, Value)) -> (&K, &Value)>;
+type IterMut<'a, K> = iter::Map<slice::IterMut<'a, (K, Value)>, fn(&mut (K, Value)) -> (&K, &mut Value)>;
+
+pub struct Occupied<'a, K, S> {
+    backend: &'a mut OrderedBackend<K, S>,
+    index: usize,
+}
+
+pub struct Vacant<'a, K, S> {
+    backend: &'a mut OrderedBackend<K, S>,
+    hash: u64,
+    key: K,
+}
+
+pub struct ExtractIf<'a, K, S, F> {
+    backend: &'a mut OrderedBackend<K, S>,
+    index: usize,
+    pred: F,
+}
+
+impl<'a, K: Eq + Hash, S: BuildHasher> OccupiedBackendEntry<'a, K> for Occupied<'a, K, S> {
+    #[inline]
+    fn key(&self) -> &K {
+        &self.backend.entries[self.index].0
+    }
+
+    #[inline]
+    fn get(&self) -> &Value {
+        &self.backend.entries[self.index].1
+    }
+
+    #[inline]
+    fn get_mut(&mut self) -> &mut Value {
+        &mut self.backend.entries[self.index].1
+    }
+
+    #[inline]
+    fn into_mut(self) -> &'a mut Value {
+        &mut self.backend.entries[self.index].1
+    }
+
+    /// Removes the entry, keeping the order of the others.
+    #[inline]
+    fn remove_entry(self) -> (K, Value) {
+        self.backend.shift_remove_index(self.index).expect("the index of an occupied entry is in bounds")
+    }
+}
+
+impl<'a, K: Eq + Hash, S: BuildHasher> VacantBackendEntry<'a, K> for Vacant<'a, K, S> {
+    #[inline]
+    fn key(&self) -> &K {
+        &self.key
+    }
+
+    #[inline]
+    fn into_key(self) -> K {
+        self.key
+    }
+
+    #[inline]
+    fn insert(self, value: Value) -> &'a mut Value {
+        let index = self.backend.push(self.hash, self.key, value);
+        &mut self.backend.entries[index].1
+    }
+}
+
+impl<K: Eq + Hash, S: BuildHasher, F> Iterator for ExtractIf<'_, K, S, F>
+where
+    F: FnMut(&K, &mut Value) -> bool,
+{
+    type Item = (K, Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((key, value)) = self.backend.entries.get_mut(self.index) {
+            if (self.pred)(key, value) {
+                return self.backend.shift_remove_index(self.index);
+            }
+            self.index += 1;
+        }
+        None
+    }
+}
+
+impl<K: Eq + Hash, S: BuildHasher> MapBackend<K> for OrderedBackend<K, S> {
+    type Iter<'a>
+        = Iter<'a, K>
+    where
+        Self: 'a,
+        K: 'a;
+    type IterMut<'a>
+        = IterMut<'a, K>
+    where
+        Self: 'a,
+        K: 'a;
+    type IntoIter = vec::IntoIter<(K, Value)>;
+    type Occupied<'a>
+        = Occupied<'a, K, S>
+    where
+        Self: 'a,
+        K: 'a;
+    type Vacant<'a>
+        = Vacant<'a, K, S>
+    where
+        Self: 'a,
+        K: 'a;
+    type ExtractIf<'a, F>
+        = ExtractIf<'a, K, S, F>
+    where
+        Self: 'a,
+        K: 'a,
+        F: FnMut(&K, &mut Value) -> bool;
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Replacing the value of an existing key keeps its position.
+    fn insert(&mut self, key: K, value: Value) -> Option<Value> {
+        if let Some(i) = self.find(&key) {
+            return Some(mem::replace(&mut self.entries[i].1, value));
+        }
+        let hash = self.hasher.hash_one(&key);
+        self.push(hash, key, value);
+        None
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.indices.clear();
+    }
+
+    #[inline]
+    fn iter(&self) -> Self::Iter<'_> {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+
+    #[inline]
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        self.entries.iter_mut().map(|(k, v)| (&*k, v))
+    }
+
+    #[inline]
+    fn into_entries(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+
+    fn entry(&mut self, key: K) -> Entry<'_, K, Self> {
+        match self.find(&key) {
+            Some(index) => Entry::Occupied(OccupiedEntry::new(Occupied { backend: self, index })),
+            None => {
+                let hash = self.hasher.hash_one(&key);
+                Entry::Vacant(VacantEntry::new(Vacant { backend: self, hash, key }))
+            }
+        }
+    }
+
+    /// Yields the matching entries in order, keeping the order of the others.
+    #[inline]
+    fn extract_if<F>(&mut self, pred: F) -> Self::ExtractIf<'_, F>
+    where
+        F: FnMut(&K, &mut Value) -> bool,
+    {
+        ExtractIf {
+            backend: self,
+            index: 0,
+            pred,
+        }
+    }
+}
+
+impl<K: Eq + Hash, S: BuildHasher, Q> MapLookup<K, Q> for OrderedBackend<K, S>
+where
+    K: Borrow<Q>,
+    Q: ?Sized + Hash + Eq,
+{
+    #[inline]
+    fn get(&self, key: &Q) -> Option<&Value> {
+        self.find(key).map(|i| &self.entries[i].1)
+    }
+
+    #[inline]
+    fn get_mut(&mut self, key: &Q) -> Option<&mut Value> {
+        self.find(key).map(|i| &mut self.entries[i].1)
+    }
+
+    /// Removes the entry, keeping the order of the others.
+    fn remove(&mut self, key: &Q) -> Option<Value> {
+        let index = self.find(key)?;
+        self.shift_remove_index(index).map(|(_, v)| v)
+    }
+}
+
+impl<K: Eq + Hash, S: BuildHasher> AnyMap<K, OrderedBackend<K, S>> {
+    #[inline]
+    pub fn get_index(&self, index: usize) -> Option<(&K, &Value)> {
+        self.map.entries.get(index).map(|(k, v)| (k, v))
+    }
+
+    #[inline]
+    pub fn get_index_mut(&mut self, index: usize) -> Option<(&K, &mut Value)> {
+        self.map.entries.get_mut(index).map(|(k, v)| (&*k, v))
+    }
+
+    /// Returns the position of `key` in insertion order.
+    #[inline]
+    pub fn get_index_of<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.map.find(key)
+    }
+
+    #[inline]
+    pub fn first(&self) -> Option<(&K, &Value)> {
+        self.get_index(0)
+    }
+
+    #[inline]
+    pub fn last(&self) -> Option<(&K, &Value)> {
+        self.get_index(self.len().checked_sub(1)?)
+    }
+
+    /// Removes the entry at `index` by moving the last entry into its place.
+    #[inline]
+    pub fn swap_remove_index(&mut self, index: usize) -> Option<(K, Value)> {
+        self.map.swap_remove_index(index)
+    }
+
+    /// Removes the entry at `index`, keeping the order of the others.
+    #[inline]
+    pub fn shift_remove_index(&mut self, index: usize) -> Option<(K, Value)> {
+        self.map.shift_remove_index(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(map: &OrderedAnyMap<&'static str>) -> Vec<&'static str> {
+        map.keys().copied().collect()
+    }
+
+    #[test]
+    fn insertion_order() {
+        let mut map: OrderedAnyMap<&str> = ["a", "b", "c", "d"].into_iter().zip(0..).collect();
+        map.insert("b", "replaced");
+        assert_eq!(keys(&map), ["a", "b", "c", "d"]);
+        assert_eq!(map.get_index_of("c"), Some(2));
+
+        assert!(map.remove("b").unwrap().is::<&str>());
+        assert_eq!(keys(&map), ["a", "c", "d"]);
+        assert_eq!(map.get_typed::<i32>("d"), Some(&3));
+
+        assert_eq!(map.swap_remove_index(0).unwrap().0, "a");
+        assert_eq!(keys(&map), ["d", "c"]);
+        assert_eq!(map.get_index_of("d"), Some(0));
+        assert_eq!(map.get_typed::<i32>("c"), Some(&2));
+
+        assert_eq!(map.shift_remove_index(0).unwrap().0, "d");
+        assert_eq!(map.first().unwrap().0, &"c");
+        assert_eq!(map.last().unwrap().0, &"c");
+        assert!(map.swap_remove_index(1).is_none());
+    }
+}
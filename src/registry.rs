@@ -0,0 +1,517 @@
+//! Process-wide registry of per-type operations.
+//!
+//! `Box<dyn Any>` erases everything but the type, so operations such as
+//! cloning a [`Value`](crate::Value) only work for types that registered the
+//! operation here first. Primitives, `String` and `&'static str` are
+//! registered out of the box.
+//!
+//! ```
+//! use anymap::{registry, Value};
+//!
+//! #[derive(Clone)]
+//! struct Config(u32);
+//!
+//! registry::register_clone::<Config>();
+//! let value = Value::new(Config(1));
+//! assert_eq!(value.try_clone().unwrap().as_type::<Config>().unwrap().0, 1);
+//! ```
+
+use std::{
+    any::{type_name, Any, TypeId},
+    collections::HashMap,
+    fmt,
+    hash::{Hash, Hasher},
+    str::FromStr,
+    sync::{Arc, OnceLock, PoisonError, RwLock},
+};
+
+use crate::Value;
+
+pub(crate) type CloneFn = fn(&dyn Any) -> Box<dyn Any>;
+pub(crate) type DebugFn = fn(&dyn Any, &mut fmt::Formatter<'_>) -> fmt::Result;
+pub(crate) type EqFn = fn(&dyn Any, &dyn Any) -> bool;
+pub(crate) type HashFn = fn(&dyn Any, &mut dyn Hasher);
+pub(crate) type DisplayFn = fn(&dyn Any, &mut fmt::Formatter<'_>) -> fmt::Result;
+
+#[derive(Default, Clone, Copy)]
+pub(crate) struct TypeInfo {
+    pub(crate) clone: Option<CloneFn>,
+    pub(crate) debug: Option<DebugFn>,
+    pub(crate) eq: Option<EqFn>,
+    pub(crate) hash: Option<HashFn>,
+    pub(crate) display: Option<DisplayFn>,
+}
+
+fn registry() -> &'static RwLock<HashMap<TypeId, TypeInfo>> {
+    static REGISTRY: OnceLock<RwLock<HashMap<TypeId, TypeInfo>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let mut types = HashMap::new();
+        builtins(&mut types);
+        RwLock::new(types)
+    })
+}
+
+fn update<T: Any>(types: &mut HashMap<TypeId, TypeInfo>, f: impl FnOnce(&mut TypeInfo)) {
+    f(types.entry(TypeId::of::<T>()).or_default())
+}
+
+pub(crate) fn lookup(id: TypeId) -> Option<TypeInfo> {
+    registry().read().unwrap_or_else(PoisonError::into_inner).get(&id).copied()
+}
+
+fn register<T: Any>(f: impl FnOnce(&mut TypeInfo)) {
+    let mut types = registry().write().unwrap_or_else(PoisonError::into_inner);
+    update::<T>(&mut types, f)
+}
+
+fn clone_any<T: Any + Clone>(value: &dyn Any) -> Box<dyn Any> {
+    Box::new(value.downcast_ref::<T>().unwrap().clone())
+}
+
+/// Registers `T` as cloneable, enabling [`Value::try_clone`](crate::Value::try_clone)
+/// for values of this type.
+pub fn register_clone<T: Any + Clone>() {
+    register::<T>(|info| info.clone = Some(clone_any::<T>))
+}
+
+fn debug_any<T: Any + fmt::Debug>(value: &dyn Any, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt::Debug::fmt(value.downcast_ref::<T>().unwrap(), f)
+}
+
+/// Registers `T` as debuggable, so `{:?}` on a [`Value`](crate::Value) of
+/// this type shows its contents.
+pub fn register_debug<T: Any + fmt::Debug>() {
+    register::<T>(|info| info.debug = Some(debug_any::<T>))
+}
+
+fn display_any<T: Any + fmt::Display>(value: &dyn Any, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt::Display::fmt(value.downcast_ref::<T>().unwrap(), f)
+}
+
+/// Registers `T` as displayable, enabling [`Value::display`](crate::Value::display)
+/// for values of this type.
+pub fn register_display<T: Any + fmt::Display>() {
+    register::<T>(|info| info.display = Some(display_any::<T>))
+}
+
+fn eq_any<T: Any + PartialEq>(a: &dyn Any, b: &dyn Any) -> bool {
+    a.downcast_ref::<T>() == b.downcast_ref::<T>()
+}
+
+/// Registers `T` as comparable, so [`Value`](crate::Value)s holding a `T` on
+/// both sides can be compared with `==`.
+pub fn register_eq<T: Any + PartialEq>() {
+    register::<T>(|info| info.eq = Some(eq_any::<T>))
+}
+
+fn hash_any<T: Any + Hash>(value: &dyn Any, mut state: &mut dyn Hasher) {
+    value.downcast_ref::<T>().unwrap().hash(&mut state)
+}
+
+/// Registers `T` as hashable, so [`Value`](crate::Value)s holding a `T` hash
+/// by their contents. Also registers the equality required to use such values
+/// in sets.
+pub fn register_hash<T: Any + Hash + Eq>() {
+    register::<T>(|info| {
+        info.eq = Some(eq_any::<T>);
+        info.hash = Some(hash_any::<T>);
+    })
+}
+
+type CastRef<Dyn> = dyn for<'a> Fn(&'a dyn Any) -> Option<&'a Dyn> + Send + Sync;
+type CastMut<Dyn> = dyn for<'a> Fn(&'a mut (dyn Any + 'static)) -> Option<&'a mut Dyn> + Send + Sync;
+
+pub(crate) struct Caster<Dyn: ?Sized + 'static> {
+    pub(crate) cast_ref: Box<CastRef<Dyn>>,
+    pub(crate) cast_mut: Box<CastMut<Dyn>>,
+}
+
+type CasterMap = HashMap<(TypeId, TypeId), Arc<dyn Any + Send + Sync>>;
+
+fn casters() -> &'static RwLock<CasterMap> {
+    static CASTERS: OnceLock<RwLock<CasterMap>> = OnceLock::new();
+    CASTERS.get_or_init(Default::default)
+}
+
+pub(crate) fn lookup_caster<Dyn: ?Sized + 'static>(id: TypeId) -> Option<Arc<Caster<Dyn>>> {
+    let casters = casters().read().unwrap_or_else(PoisonError::into_inner);
+    let caster = casters.get(&(id, TypeId::of::<Dyn>()))?.clone();
+    caster.downcast::<Caster<Dyn>>().ok()
+}
+
+/// Registers that `T` implements the trait object type `Dyn`, enabling
+/// [`Value::as_trait`](crate::Value::as_trait) for values of this type.
+///
+/// The casts are usually just `|v| v`, which coerces `&T` to `&Dyn`.
+///
+/// ```
+/// use anymap::{registry, Value};
+///
+/// trait Greet {
+///     fn greet(&self) -> String;
+/// }
+///
+/// struct English;
+///
+/// impl Greet for English {
+///     fn greet(&self) -> String {
+///         "hello".into()
+///     }
+/// }
+///
+/// registry::register_trait::<dyn Greet, English>(|v| v, |v| v);
+/// let value = Value::new(English);
+/// assert_eq!(value.as_trait::<dyn Greet>().unwrap().greet(), "hello");
+/// ```
+pub fn register_trait<Dyn: ?Sized + 'static, T: Any>(
+    cast_ref: fn(&T) -> &Dyn,
+    cast_mut: fn(&mut T) -> &mut Dyn,
+) {
+    let caster = Caster::<Dyn> {
+        cast_ref: Box::new(move |value| value.downcast_ref::<T>().map(cast_ref)),
+        cast_mut: Box::new(move |value| value.downcast_mut::<T>().map(cast_mut)),
+    };
+    casters()
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert((TypeId::of::<T>(), TypeId::of::<Dyn>()), Arc::new(caster));
+}
+
+pub(crate) type ParseFn = fn(&str) -> Result<Value, String>;
+
+#[derive(Clone, Copy)]
+struct Parser {
+    parse: ParseFn,
+    type_id: TypeId,
+    type_name: &'static str,
+}
+
+#[derive(Default)]
+struct Parsers {
+    by_name: HashMap<String, Parser>,
+    names: HashMap<TypeId, String>,
+}
+
+impl Parsers {
+    fn insert<T: Any + FromStr>(&mut self, name: String)
+    where
+        T::Err: fmt::Display,
+    {
+        let parser = Parser {
+            parse: parse_any::<T>,
+            type_id: TypeId::of::<T>(),
+            type_name: type_name::<T>(),
+        };
+        if let Some(old) = self.by_name.insert(name.clone(), parser) {
+            if self.names.get(&old.type_id) == Some(&name) {
+                self.names.remove(&old.type_id);
+            }
+        }
+        self.names.insert(TypeId::of::<T>(), name);
+    }
+}
+
+fn parsers() -> &'static RwLock<Parsers> {
+    static PARSERS: OnceLock<RwLock<Parsers>> = OnceLock::new();
+    PARSERS.get_or_init(|| {
+        let mut parsers = Parsers::default();
+        builtin_parsers(&mut parsers);
+        RwLock::new(parsers)
+    })
+}
+
+pub(crate) fn lookup_parser(name: &str) -> Option<ParseFn> {
+    let parsers = parsers().read().unwrap_or_else(PoisonError::into_inner);
+    parsers.by_name.get(name).map(|parser| parser.parse)
+}
+
+/// Returns the Rust name of the type registered under `name`.
+pub(crate) fn lookup_parser_type(name: &str) -> Option<&'static str> {
+    let parsers = parsers().read().unwrap_or_else(PoisonError::into_inner);
+    parsers.by_name.get(name).map(|parser| parser.type_name)
+}
+
+/// Returns the name a type was last registered under with [`register_parse`].
+pub(crate) fn lookup_name(id: TypeId) -> Option<String> {
+    let parsers = parsers().read().unwrap_or_else(PoisonError::into_inner);
+    parsers.names.get(&id).cloned()
+}
+
+fn parse_any<T: Any + FromStr>(input: &str) -> Result<Value, String>
+where
+    T::Err: fmt::Display,
+{
+    input.parse::<T>().map(Value::new).map_err(|err| err.to_string())
+}
+
+/// Registers `T` under `name` as parseable from strings, enabling
+/// [`AnyMap::insert_parsed_as`](crate::AnyMap::insert_parsed_as) with that
+/// name. Registering another type under the same name replaces it.
+pub fn register_parse<T: Any + FromStr>(name: impl Into<String>)
+where
+    T::Err: fmt::Display,
+{
+    parsers()
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert::<T>(name.into());
+}
+
+type MigrateFn = dyn Fn(Value) -> Value + Send + Sync;
+
+fn migrations() -> &'static RwLock<HashMap<TypeId, Arc<MigrateFn>>> {
+    static MIGRATIONS: OnceLock<RwLock<HashMap<TypeId, Arc<MigrateFn>>>> = OnceLock::new();
+    MIGRATIONS.get_or_init(Default::default)
+}
+
+/// Registers a conversion from `Old` to `New` that runs on every `Old` value
+/// read from a snapshot, e.g. by [`AnyMap::load_from`](crate::AnyMap::load_from).
+///
+/// Keep `Old` registered with [`register_parse`] under the name it was saved
+/// with. Migrations chain, so a value may be upgraded through several
+/// versions while loading. Registering another migration for `Old` replaces
+/// it.
+///
+/// ```
+/// use anymap::{registry, AnyMap};
+///
+/// let path = std::env::temp_dir().join("ports.snapshot");
+/// let mut map = AnyMap::new();
+/// map.insert("port".to_owned(), 8080u16);
+/// map.save_to(&path).unwrap();
+///
+/// // Ports have since been widened to `u32`.
+/// registry::register_migration::<u16, u32>(u32::from);
+/// let map = AnyMap::load_from(&path).unwrap();
+/// assert_eq!(map.get_typed::<u32>("port"), Some(&8080));
+/// # std::fs::remove_file(path).unwrap();
+/// ```
+pub fn register_migration<Old: Any, New: Any>(migrate: fn(Old) -> New) {
+    let migrate = move |value: Value| match value.downcast::<Old>() {
+        Ok(old) => Value::new(migrate(old)),
+        Err(value) => value,
+    };
+    migrations()
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(TypeId::of::<Old>(), Arc::new(migrate));
+}
+
+/// Applies registered migrations to `value` until none is left, stopping
+/// early if they form a cycle.
+pub(crate) fn migrate(mut value: Value) -> Value {
+    let mut seen = Vec::new();
+    loop {
+        let id = value.type_id();
+        if seen.contains(&id) {
+            return value;
+        }
+        let migrate = migrations().read().unwrap_or_else(PoisonError::into_inner).get(&id).cloned();
+        match migrate {
+            Some(migrate) => value = migrate(value),
+            None => return value,
+        }
+        seen.push(id);
+    }
+}
+
+#[cfg(feature = "serde")]
+pub(crate) type SerializeFn = for<'a> fn(&'a dyn Any) -> &'a dyn erased_serde::Serialize;
+#[cfg(feature = "serde")]
+pub(crate) type DeserializeFn = fn(&mut dyn erased_serde::Deserializer<'_>) -> Result<Value, erased_serde::Error>;
+
+#[cfg(feature = "serde")]
+#[derive(Clone)]
+pub(crate) struct SerdeType {
+    pub(crate) name: Arc<str>,
+    pub(crate) serialize: SerializeFn,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Default)]
+struct SerdeTypes {
+    by_type: HashMap<TypeId, SerdeType>,
+    by_name: HashMap<Arc<str>, DeserializeFn>,
+}
+
+#[cfg(feature = "serde")]
+impl SerdeTypes {
+    fn insert_serialize<T: Any + serde::Serialize>(&mut self, name: Arc<str>) {
+        let ty = SerdeType {
+            name,
+            serialize: serialize_any::<T>,
+        };
+        self.by_type.insert(TypeId::of::<T>(), ty);
+    }
+
+    fn insert<T: Any + serde::Serialize + serde::de::DeserializeOwned>(&mut self, name: &str) {
+        let name = Arc::<str>::from(name);
+        self.insert_serialize::<T>(name.clone());
+        self.by_name.insert(name, deserialize_any::<T>);
+    }
+}
+
+#[cfg(feature = "serde")]
+fn serde_types() -> &'static RwLock<SerdeTypes> {
+    static SERDE_TYPES: OnceLock<RwLock<SerdeTypes>> = OnceLock::new();
+    SERDE_TYPES.get_or_init(|| {
+        let mut types = SerdeTypes::default();
+        builtin_serde(&mut types);
+        RwLock::new(types)
+    })
+}
+
+#[cfg(feature = "serde")]
+pub(crate) fn lookup_serde(id: TypeId) -> Option<SerdeType> {
+    serde_types().read().unwrap_or_else(PoisonError::into_inner).by_type.get(&id).cloned()
+}
+
+#[cfg(feature = "serde")]
+pub(crate) fn lookup_deserialize(name: &str) -> Option<DeserializeFn> {
+    serde_types().read().unwrap_or_else(PoisonError::into_inner).by_name.get(name).copied()
+}
+
+#[cfg(feature = "serde")]
+fn serialize_any<T: Any + serde::Serialize>(value: &dyn Any) -> &dyn erased_serde::Serialize {
+    value.downcast_ref::<T>().unwrap()
+}
+
+#[cfg(feature = "serde")]
+fn deserialize_any<T: Any + serde::de::DeserializeOwned>(
+    deserializer: &mut dyn erased_serde::Deserializer<'_>,
+) -> Result<Value, erased_serde::Error> {
+    erased_serde::deserialize::<T>(deserializer).map(Value::new)
+}
+
+/// Registers `T` under `name` for serde, so that maps holding a `T`
+/// implement `Serialize`, and a `T` is read back for values tagged with
+/// `name`. Requires the `serde` feature.
+///
+/// Every value is written as a `type` tag holding `name`, next to the
+/// `value` itself. Primitives and `String` are registered under their Rust
+/// names. Registered migrations run on every value read back, as for
+/// snapshots.
+///
+/// ```
+/// use anymap::{registry, AnyMap};
+///
+/// #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+/// struct Point {
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// registry::register_serde::<Point>("Point");
+/// let mut map = AnyMap::new();
+/// map.insert("origin".to_owned(), Point { x: 0, y: 0 });
+/// let json = serde_json::to_string(&map).unwrap();
+/// assert_eq!(json, r#"{"origin":{"type":"Point","value":{"x":0,"y":0}}}"#);
+///
+/// let map: AnyMap = serde_json::from_str(&json).unwrap();
+/// assert_eq!(map.get_typed::<Point>("origin"), Some(&Point { x: 0, y: 0 }));
+/// ```
+#[cfg(feature = "serde")]
+pub fn register_serde<T: Any + serde::Serialize + serde::de::DeserializeOwned>(name: impl Into<String>) {
+    let mut types = serde_types().write().unwrap_or_else(PoisonError::into_inner);
+    types.insert::<T>(&name.into());
+}
+
+#[cfg(feature = "serde")]
+fn builtin_serde(types: &mut SerdeTypes) {
+    macro_rules! builtin {
+        ($($ty:ident),* $(,)?) => {
+            $(
+                types.insert::<$ty>(stringify!($ty));
+            )*
+        };
+    }
+
+    builtin! {
+        bool, char,
+        u8, u16, u32, u64, u128, usize,
+        i8, i16, i32, i64, i128, isize,
+        f32, f64,
+        String,
+    }
+
+    // Borrowed strings can't be deserialized, but are read back as `String`.
+    types.insert_serialize::<&'static str>("String".into());
+}
+
+fn builtin_parsers(parsers: &mut Parsers) {
+    macro_rules! builtin {
+        ($($ty:ident),* $(,)?) => {
+            $(
+                parsers.insert::<$ty>(stringify!($ty).to_owned());
+            )*
+        };
+    }
+
+    builtin! {
+        bool, char,
+        u8, u16, u32, u64, u128, usize,
+        i8, i16, i32, i64, i128, isize,
+        f32, f64,
+        String,
+    }
+
+    // Borrowed strings can't be parsed back, but are read back as `String`.
+    parsers.names.insert(TypeId::of::<&'static str>(), "String".to_owned());
+}
+
+fn builtins(types: &mut HashMap<TypeId, TypeInfo>) {
+    macro_rules! builtin {
+        ($($ty:ty),* $(,)?) => {
+            $(
+                update::<$ty>(types, |info| {
+                    info.clone = Some(clone_any::<$ty>);
+                    info.debug = Some(debug_any::<$ty>);
+                    info.eq = Some(eq_any::<$ty>);
+                });
+            )*
+        };
+    }
+
+    macro_rules! builtin_hash {
+        ($($ty:ty),* $(,)?) => {
+            $(
+                update::<$ty>(types, |info| {
+                    info.hash = Some(hash_any::<$ty>);
+                });
+            )*
+        };
+    }
+
+    builtin! {
+        (), bool, char,
+        u8, u16, u32, u64, u128, usize,
+        i8, i16, i32, i64, i128, isize,
+        f32, f64,
+        String, &'static str,
+    }
+
+    macro_rules! builtin_display {
+        ($($ty:ty),* $(,)?) => {
+            $(
+                update::<$ty>(types, |info| {
+                    info.display = Some(display_any::<$ty>);
+                });
+            )*
+        };
+    }
+
+    builtin_display! {
+        bool, char,
+        u8, u16, u32, u64, u128, usize,
+        i8, i16, i32, i64, i128, isize,
+        f32, f64,
+        String, &'static str,
+    }
+
+    builtin_hash! {
+        (), bool, char,
+        u8, u16, u32, u64, u128, usize,
+        i8, i16, i32, i64, i128, isize,
+        String, &'static str,
+    }
+}
@@ -0,0 +1,84 @@
+//! RON snapshots of maps of registered types.
+
+use ron::{error::SpannedError, ser::PrettyConfig};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{AnyMap, MapBackend};
+
+impl<K: Serialize, B: MapBackend<K>> AnyMap<K, B> {
+    /// Writes the map as pretty-printed RON, one entry per line, so it stays
+    /// easy to edit by hand. Requires the `ron` feature.
+    ///
+    /// Every value's type must be registered with
+    /// [`register_serde`](crate::registry::register_serde).
+    ///
+    /// ```
+    /// use anymap::AnyMap;
+    ///
+    /// let mut map = AnyMap::<String>::new();
+    /// map.insert("port".to_owned(), 8080u16);
+    /// let ron = map.to_ron().unwrap();
+    /// assert_eq!(ron, "{\n    \"port\": (\n        type: \"u16\",\n        value: 8080,\n    ),\n}");
+    /// ```
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, PrettyConfig::default())
+    }
+}
+
+impl<K: DeserializeOwned, B: MapBackend<K> + Default> AnyMap<K, B> {
+    /// Reads a map written by [`to_ron`](Self::to_ron), or edited from one.
+    /// Requires the `ron` feature.
+    ///
+    /// ```
+    /// use anymap::AnyMap;
+    ///
+    /// let map = AnyMap::<String>::from_ron(r#"{ "port": (type: "u16", value: 8080) }"#).unwrap();
+    /// assert_eq!(map.get_typed::<u16>("port"), Some(&8080));
+    /// ```
+    pub fn from_ron(ron: &str) -> Result<Self, SpannedError> {
+        ron::from_str(ron)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::{registry, UnknownTypes, UnknownValue};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Window {
+        title: String,
+        size: (u32, u32),
+    }
+
+    #[test]
+    fn round_trip() {
+        registry::register_serde::<Window>("ron::Window");
+
+        let window = Window {
+            title: "main".to_owned(),
+            size: (800, 600),
+        };
+        let mut map = AnyMap::<String>::new();
+        map.insert("fullscreen".to_owned(), false);
+        map.insert("window".to_owned(), window);
+        let ron = map.to_ron().unwrap();
+
+        let loaded = AnyMap::<String>::from_ron(&ron).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.get_typed::<bool>("fullscreen"), Some(&false));
+        assert_eq!(loaded.get_typed::<Window>("window").unwrap().size, (800, 600));
+    }
+
+    #[test]
+    fn unknown() {
+        let ron = r#"{ "theme": (type: "ron::Theme", value: (dark: true)) }"#;
+        assert!(AnyMap::<String>::from_ron(ron).is_err());
+
+        let mut de = ron::Deserializer::from_str(ron).unwrap();
+        let map = AnyMap::<String>::deserialize_with(&mut de, UnknownTypes::Keep).unwrap();
+        assert_eq!(map.get_typed::<UnknownValue>("theme").unwrap().type_tag(), "ron::Theme");
+    }
+}
@@ -0,0 +1,434 @@
+//! Serde support for maps of registered types.
+//!
+//! Values are written as a struct of a `type` tag, the name the value's type
+//! was registered under with [`registry::register_serde`], and the `value`
+//! itself. Reading them back looks the tag up in the same registry.
+
+use std::{fmt, marker::PhantomData};
+
+use serde::{
+    de::{self, DeserializeSeed, Error as _, MapAccess, SeqAccess, Visitor},
+    ser::{Error as _, SerializeMap, SerializeSeq, SerializeStruct},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{registry, AnyMap, MapBackend, Value};
+
+const FIELDS: &[&str] = &["type", "value"];
+
+/// What to do with values whose type tag is not registered with
+/// [`registry::register_serde`], when deserializing with
+/// [`AnyMap::deserialize_with`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnknownTypes {
+    /// Fail the whole map. This is what `Deserialize` does.
+    #[default]
+    Error,
+    /// Keep them as [`UnknownValue`]s. Requires a self-describing format
+    /// such as JSON.
+    Keep,
+}
+
+/// A value whose type tag was not registered, kept by deserializing with
+/// [`UnknownTypes::Keep`].
+///
+/// Serializing it writes back the tag and the data as they were read, so
+/// entries of types a program doesn't know about survive a round trip.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownValue {
+    type_tag: String,
+    content: Content,
+}
+
+impl UnknownValue {
+    #[inline]
+    pub fn type_tag(&self) -> &str {
+        &self.type_tag
+    }
+}
+
+/// Data of any self-describing format, as far as serde's data model goes.
+#[derive(Debug, Clone, PartialEq)]
+enum Content {
+    Unit,
+    Bool(bool),
+    U64(u64),
+    I64(i64),
+    F64(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    None,
+    Some(Box<Content>),
+    Seq(Vec<Content>),
+    Map(Vec<(Content, Content)>),
+}
+
+impl Serialize for Content {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Content::Unit => serializer.serialize_unit(),
+            Content::Bool(v) => serializer.serialize_bool(*v),
+            Content::U64(v) => serializer.serialize_u64(*v),
+            Content::I64(v) => serializer.serialize_i64(*v),
+            Content::F64(v) => serializer.serialize_f64(*v),
+            Content::String(v) => serializer.serialize_str(v),
+            Content::Bytes(v) => serializer.serialize_bytes(v),
+            Content::None => serializer.serialize_none(),
+            Content::Some(v) => serializer.serialize_some(v),
+            Content::Seq(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Content::Map(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Content {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ContentVisitor)
+    }
+}
+
+struct ContentVisitor;
+
+impl<'de> Visitor<'de> for ContentVisitor {
+    type Value = Content;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Content, E> {
+        Ok(Content::Unit)
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Content, E> {
+        Ok(Content::Bool(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Content, E> {
+        Ok(Content::U64(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Content, E> {
+        Ok(Content::I64(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Content, E> {
+        Ok(Content::F64(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Content, E> {
+        Ok(Content::String(v.to_owned()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Content, E> {
+        Ok(Content::String(v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Content, E> {
+        Ok(Content::Bytes(v.to_owned()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Content, E> {
+        Ok(Content::Bytes(v))
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Content, E> {
+        Ok(Content::None)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Content, D::Error> {
+        Content::deserialize(deserializer).map(|v| Content::Some(Box::new(v)))
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, deserializer: D) -> Result<Content, D::Error> {
+        Content::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Content, A::Error> {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Content::Seq(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Content, A::Error> {
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(Content::Map(entries))
+    }
+}
+
+/// Fails for values whose type is not registered with
+/// [`registry::register_serde`].
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tagged = serializer.serialize_struct("Value", 2)?;
+        if let Some(unknown) = self.as_type::<UnknownValue>() {
+            tagged.serialize_field("type", &unknown.type_tag)?;
+            tagged.serialize_field("value", &unknown.content)?;
+            return tagged.end();
+        }
+        let ty = registry::lookup_serde(self.type_id()).ok_or_else(|| {
+            S::Error::custom(format_args!(
+                "type `{}` is not registered with `register_serde`",
+                self.describe_type(),
+            ))
+        })?;
+        tagged.serialize_field("type", &*ty.name)?;
+        tagged.serialize_field("value", (ty.serialize)(self.as_any()))?;
+        tagged.end()
+    }
+}
+
+impl<K: Serialize, B: MapBackend<K>> Serialize for AnyMap<K, B> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (key, value) in self.iter() {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+/// Reads the data of a value whose tag is registered.
+struct Registered(registry::DeserializeFn);
+
+impl<'de> DeserializeSeed<'de> for Registered {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        let mut deserializer = <dyn erased_serde::Deserializer<'_>>::erase(deserializer);
+        (self.0)(&mut deserializer).map_err(D::Error::custom)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ValueSeed {
+    unknown: UnknownTypes,
+}
+
+impl ValueSeed {
+    fn read_value<'de, A: MapAccess<'de>>(self, tag: String, map: &mut A) -> Result<Value, A::Error> {
+        match registry::lookup_deserialize(&tag) {
+            Some(deserialize) => map.next_value_seed(Registered(deserialize)).map(registry::migrate),
+            None if self.unknown == UnknownTypes::Keep => {
+                let content = map.next_value()?;
+                Ok(Value::new(UnknownValue { type_tag: tag, content }))
+            }
+            None => Err(unknown_tag(&tag)),
+        }
+    }
+}
+
+fn unknown_tag<E: de::Error>(tag: &str) -> E {
+    E::custom(format_args!("type `{tag}` is not registered with `register_serde`"))
+}
+
+impl<'de> DeserializeSeed<'de> for ValueSeed {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_struct("Value", FIELDS, self)
+    }
+}
+
+impl<'de> Visitor<'de> for ValueSeed {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a value with a type tag")
+    }
+
+    // Formats without field names can't hold unknown values, since those
+    // can only be read back by a self-describing format.
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let tag: String = seq.next_element()?.ok_or_else(|| A::Error::invalid_length(0, &self))?;
+        let deserialize = registry::lookup_deserialize(&tag).ok_or_else(|| unknown_tag::<A::Error>(&tag))?;
+        let value = seq
+            .next_element_seed(Registered(deserialize))?
+            .ok_or_else(|| A::Error::invalid_length(1, &self))?;
+        Ok(registry::migrate(value))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        match map.next_key::<String>()?.as_deref() {
+            Some("type") => {}
+            Some("value") => return Err(A::Error::custom("`type` must come before `value`")),
+            Some(field) => return Err(A::Error::unknown_field(field, FIELDS)),
+            None => return Err(A::Error::missing_field("type")),
+        }
+        let tag = map.next_value::<String>()?;
+        match map.next_key::<String>()?.as_deref() {
+            Some("value") => {}
+            Some(field) => return Err(A::Error::unknown_field(field, FIELDS)),
+            None => return Err(A::Error::missing_field("value")),
+        }
+        let value = self.read_value(tag, &mut map)?;
+        if let Some(field) = map.next_key::<String>()? {
+            return Err(A::Error::unknown_field(&field, FIELDS));
+        }
+        Ok(value)
+    }
+}
+
+/// Fails for values whose type tag is not registered with
+/// [`registry::register_serde`].
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        ValueSeed { unknown: UnknownTypes::Error }.deserialize(deserializer)
+    }
+}
+
+struct MapVisitor<K, B> {
+    unknown: UnknownTypes,
+    marker: PhantomData<AnyMap<K, B>>,
+}
+
+impl<'de, K: Deserialize<'de>, B: MapBackend<K> + Default> Visitor<'de> for MapVisitor<K, B> {
+    type Value = AnyMap<K, B>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a map of values with type tags")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut map = AnyMap::default();
+        let seed = ValueSeed { unknown: self.unknown };
+        while let Some(key) = access.next_key()? {
+            let value = access.next_value_seed(seed)?;
+            map.insert_val(key, value);
+        }
+        Ok(map)
+    }
+}
+
+impl<K, B: MapBackend<K> + Default> AnyMap<K, B> {
+    /// Deserializes a map, handling values of unregistered types as told by
+    /// `unknown`. Requires the `serde` feature.
+    ///
+    /// ```
+    /// use anymap::{AnyMap, UnknownTypes, UnknownValue};
+    ///
+    /// let json = r#"{"port":{"type":"u16","value":8080},"theme":{"type":"Theme","value":"dark"}}"#;
+    /// assert!(serde_json::from_str::<AnyMap>(json).is_err());
+    ///
+    /// let mut de = serde_json::Deserializer::from_str(json);
+    /// let map = AnyMap::<String>::deserialize_with(&mut de, UnknownTypes::Keep).unwrap();
+    /// assert_eq!(map.get_typed::<u16>("port"), Some(&8080));
+    /// assert_eq!(map.get_typed::<UnknownValue>("theme").unwrap().type_tag(), "Theme");
+    /// assert_eq!(serde_json::to_string(&map["theme"]).unwrap(), r#"{"type":"Theme","value":"dark"}"#);
+    /// ```
+    pub fn deserialize_with<'de, D>(deserializer: D, unknown: UnknownTypes) -> Result<Self, D::Error>
+    where
+        K: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(MapVisitor {
+            unknown,
+            marker: PhantomData,
+        })
+    }
+}
+
+/// Fails for values whose type tag is not registered with
+/// [`registry::register_serde`]. See [`AnyMap::deserialize_with`] for how to
+/// keep them instead.
+impl<'de, K: Deserialize<'de>, B: MapBackend<K> + Default> Deserialize<'de> for AnyMap<K, B> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        AnyMap::deserialize_with(deserializer, UnknownTypes::Error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Celsius(f64);
+
+    #[test]
+    fn serialize_tagged() {
+        registry::register_serde::<Celsius>("serialize::Celsius");
+
+        let mut map = AnyMap::<&str>::new();
+        map.insert("port", 8080u16);
+        map.insert("name", "static");
+        map.insert("temp", Celsius(21.5));
+        assert_eq!(
+            serde_json::to_value(&map).unwrap(),
+            json!({
+                "port": { "type": "u16", "value": 8080 },
+                "name": { "type": "String", "value": "static" },
+                "temp": { "type": "serialize::Celsius", "value": 21.5 },
+            }),
+        );
+    }
+
+    #[test]
+    fn serialize_unregistered() {
+        struct Opaque;
+
+        let mut map = AnyMap::<&str>::new();
+        map.insert("opaque", Opaque);
+        let err = serde_json::to_string(&map).unwrap_err();
+        assert!(err.to_string().contains("is not registered with `register_serde`"));
+    }
+
+    #[test]
+    fn deserialize_round_trip() {
+        registry::register_serde::<Celsius>("deserialize::Celsius");
+
+        let mut map = AnyMap::<String>::new();
+        map.insert("port".to_owned(), 8080u16);
+        map.insert("name".to_owned(), "static");
+        map.insert("temp".to_owned(), Celsius(21.5));
+        let json = serde_json::to_string(&map).unwrap();
+        let loaded: AnyMap<String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded.get_typed::<u16>("port"), Some(&8080));
+        assert_eq!(loaded.get_typed::<String>("name").unwrap(), "static");
+        assert_eq!(loaded.get_typed::<Celsius>("temp"), Some(&Celsius(21.5)));
+    }
+
+    #[test]
+    fn deserialize_unknown() {
+        let json = json!({
+            "port": { "type": "u16", "value": 8080 },
+            "theme": { "type": "deserialize::Theme", "value": { "dark": [true, null, -1, 0.5] } },
+        });
+        let err = serde_json::from_value::<AnyMap<String>>(json.clone()).unwrap_err();
+        assert!(err.to_string().contains("type `deserialize::Theme` is not registered"));
+
+        let map = AnyMap::<String>::deserialize_with(&json, UnknownTypes::Keep).unwrap();
+        assert_eq!(map.get_typed::<u16>("port"), Some(&8080));
+        assert_eq!(map.get_typed::<UnknownValue>("theme").unwrap().type_tag(), "deserialize::Theme");
+        assert_eq!(serde_json::to_value(&map).unwrap(), json);
+    }
+
+    #[test]
+    fn deserialize_malformed() {
+        let err = serde_json::from_str::<Value>(r#"{"value":1,"type":"u8"}"#).unwrap_err();
+        assert!(err.to_string().contains("`type` must come before `value`"));
+        let err = serde_json::from_str::<Value>(r#"{"type":"u8"}"#).unwrap_err();
+        assert!(err.to_string().contains("missing field `value`"));
+        let err = serde_json::from_str::<Value>(r#"{"type":"u8","value":256}"#).unwrap_err();
+        assert!(err.to_string().contains("256"));
+    }
+}
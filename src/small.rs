@@ -0,0 +1,386 @@
+//! Maps storing a few entries inline before falling back to a hash table.
+
+use alloc::string::String;
+use core::{
+    borrow::Borrow,
+    hash::{BuildHasher, Hash},
+};
+
+use crate::{
+    hash::{self, hash_map, HashMap},
+    fixed, AnyMap, DefaultHashBuilder, Entry, FixedBackend, MapBackend, MapLookup, OccupiedBackendEntry, OccupiedEntry,
+    VacantBackendEntry, VacantEntry, Value,
+};
+
+/// Storage holding up to `N` entries in an inline array, searched linearly,
+/// and moving them into a `HashMap` once more are inserted.
+///
+/// Small maps thus need no allocation for the table and never hash a key.
+/// Once spilled to the heap, the map stays there even if entries are
+/// removed.
+pub struct SmallBackend<K, S = DefaultHashBuilder, const N: usize = 8> {
+    repr: Repr<K, S, N>,
+}
+
+enum Repr<K, S, const N: usize> {
+    Inline(FixedBackend<K, N>, S),
+    Heap(HashMap<K, Value, S>),
+}
+
+/// A map with values of any type that stores up to `N` entries inline.
+///
+/// ```
+/// use anymap::SmallAnyMap;
+///
+/// let mut extensions = SmallAnyMap::<&str>::default();
+/// extensions.insert("request_id", 7u64);
+/// extensions.insert("user", "alice");
+/// assert_eq!(extensions.get_typed::<u64>("request_id"), Some(&7));
+/// assert!(!extensions.backend().spilled());
+/// ```
+pub type SmallAnyMap<K = String, S = DefaultHashBuilder, const N: usize = 8> = AnyMap<K, SmallBackend<K, S, N>>;
+
+impl<K, S: Default, const N: usize> Default for SmallBackend<K, S, N> {
+    #[inline]
+    fn default() -> Self {
+        SmallBackend {
+            repr: Repr::Inline(FixedBackend::default(), S::default()),
+        }
+    }
+}
+
+impl<K, S, const N: usize> SmallBackend<K, S, N> {
+    /// Returns whether the entries were moved to a heap-allocated table.
+    #[inline]
+    pub fn spilled(&self) -> bool {
+        matches!(self.repr, Repr::Heap(_))
+    }
+}
+
+impl<K: Eq + Hash, S: BuildHasher + Clone, const N: usize> SmallBackend<K, S, N> {
+    /// Moves the inline entries into a heap-allocated table.
+    fn spill(&mut self) {
+        if let Repr::Inline(entries, hasher) = &mut self.repr {
+            let mut map = HashMap::with_capacity_and_hasher(N * 2 + 1, hasher.clone());
+            map.extend(entries.drain());
+            self.repr = Repr::Heap(map);
+        }
+    }
+}
+
+pub enum Iter<'a, K> {
+    Inline(fixed::Iter<'a, K>),
+    Heap(hash_map::Iter<'a, K, Value>),
+}
+
+impl<'a, K> Iterator for Iter<'a, K> {
+    type Item = (&'a K, &'a Value);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Iter::Inline(iter) => iter.next(),
+            Iter::Heap(iter) => iter.next(),
+        }
+    }
+}
+
+pub enum IterMut<'a, K> {
+    Inline(fixed::IterMut<'a, K>),
+    Heap(hash_map::IterMut<'a, K, Value>),
+}
+
+impl<'a, K> Iterator for IterMut<'a, K> {
+    type Item = (&'a K, &'a mut Value);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            IterMut::Inline(iter) => iter.next(),
+            IterMut::Heap(iter) => iter.next(),
+        }
+    }
+}
+
+pub enum IntoIter<K, const N: usize> {
+    Inline(fixed::IntoIter<K, N>),
+    Heap(hash_map::IntoIter<K, Value>),
+}
+
+impl<K, const N: usize> Iterator for IntoIter<K, N> {
+    type Item = (K, Value);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            IntoIter::Inline(iter) => iter.next(),
+            IntoIter::Heap(iter) => iter.next(),
+        }
+    }
+}
+
+pub enum Occupied<'a, K, const N: usize> {
+    Inline(fixed::Occupied<'a, K, N>),
+    Heap(hash_map::OccupiedEntry<'a, K, Value>),
+}
+
+impl<'a, K, const N: usize> OccupiedBackendEntry<'a, K> for Occupied<'a, K, N> {
+    #[inline]
+    fn key(&self) -> &K {
+        match self {
+            Occupied::Inline(entry) => entry.key(),
+            Occupied::Heap(entry) => entry.key(),
+        }
+    }
+
+    #[inline]
+    fn get(&self) -> &Value {
+        match self {
+            Occupied::Inline(entry) => entry.get(),
+            Occupied::Heap(entry) => entry.get(),
+        }
+    }
+
+    #[inline]
+    fn get_mut(&mut self) -> &mut Value {
+        match self {
+            Occupied::Inline(entry) => entry.get_mut(),
+            Occupied::Heap(entry) => entry.get_mut(),
+        }
+    }
+
+    #[inline]
+    fn into_mut(self) -> &'a mut Value {
+        match self {
+            Occupied::Inline(entry) => entry.into_mut(),
+            Occupied::Heap(entry) => entry.into_mut(),
+        }
+    }
+
+    #[inline]
+    fn remove_entry(self) -> (K, Value) {
+        match self {
+            Occupied::Inline(entry) => entry.remove_entry(),
+            Occupied::Heap(entry) => entry.remove_entry(),
+        }
+    }
+}
+
+pub enum Vacant<'a, K, const N: usize> {
+    Inline(fixed::Vacant<'a, K, N>),
+    Heap(hash_map::VacantEntry<'a, K, Value>),
+}
+
+impl<'a, K, const N: usize> VacantBackendEntry<'a, K> for Vacant<'a, K, N> {
+    #[inline]
+    fn key(&self) -> &K {
+        match self {
+            Vacant::Inline(entry) => entry.key(),
+            Vacant::Heap(entry) => entry.key(),
+        }
+    }
+
+    #[inline]
+    fn into_key(self) -> K {
+        match self {
+            Vacant::Inline(entry) => entry.into_key(),
+            Vacant::Heap(entry) => entry.into_key(),
+        }
+    }
+
+    #[inline]
+    fn insert(self, value: Value) -> &'a mut Value {
+        match self {
+            Vacant::Inline(entry) => entry.insert(value),
+            Vacant::Heap(entry) => entry.insert(value),
+        }
+    }
+}
+
+pub enum ExtractIf<'a, K, const N: usize, F> {
+    Inline(fixed::ExtractIf<'a, K, N, F>),
+    Heap(hash_map::ExtractIf<'a, K, Value, F>),
+}
+
+impl<K, const N: usize, F> Iterator for ExtractIf<'_, K, N, F>
+where
+    F: FnMut(&K, &mut Value) -> bool,
+{
+    type Item = (K, Value);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            ExtractIf::Inline(iter) => iter.next(),
+            ExtractIf::Heap(iter) => iter.next(),
+        }
+    }
+}
+
+impl<K: Eq + Hash, S: BuildHasher + Clone, const N: usize> MapBackend<K> for SmallBackend<K, S, N> {
+    type Iter<'a>
+        = Iter<'a, K>
+    where
+        Self: 'a,
+        K: 'a;
+    type IterMut<'a>
+        = IterMut<'a, K>
+    where
+        Self: 'a,
+        K: 'a;
+    type IntoIter = IntoIter<K, N>;
+    type Occupied<'a>
+        = Occupied<'a, K, N>
+    where
+        Self: 'a,
+        K: 'a;
+    type Vacant<'a>
+        = Vacant<'a, K, N>
+    where
+        Self: 'a,
+        K: 'a;
+    type ExtractIf<'a, F>
+        = ExtractIf<'a, K, N, F>
+    where
+        Self: 'a,
+        K: 'a,
+        F: FnMut(&K, &mut Value) -> bool;
+
+    #[inline]
+    fn len(&self) -> usize {
+        match &self.repr {
+            Repr::Inline(entries, _) => entries.len(),
+            Repr::Heap(map) => map.len(),
+        }
+    }
+
+    fn insert(&mut self, key: K, value: Value) -> Option<Value> {
+        let entries = match &mut self.repr {
+            Repr::Inline(entries, _) => entries,
+            Repr::Heap(map) => return map.insert(key, value),
+        };
+        let (key, value) = match entries.try_insert(key, value) {
+            Ok(old) => return old,
+            Err(full) => full,
+        };
+        self.spill();
+        self.insert(key, value)
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        match &mut self.repr {
+            Repr::Inline(entries, _) => entries.clear(),
+            Repr::Heap(map) => map.clear(),
+        }
+    }
+
+    #[inline]
+    fn iter(&self) -> Self::Iter<'_> {
+        match &self.repr {
+            Repr::Inline(entries, _) => Iter::Inline(entries.iter()),
+            Repr::Heap(map) => Iter::Heap(map.iter()),
+        }
+    }
+
+    #[inline]
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        match &mut self.repr {
+            Repr::Inline(entries, _) => IterMut::Inline(entries.iter_mut()),
+            Repr::Heap(map) => IterMut::Heap(map.iter_mut()),
+        }
+    }
+
+    #[inline]
+    fn into_entries(self) -> Self::IntoIter {
+        match self.repr {
+            Repr::Inline(entries, _) => IntoIter::Inline(entries.into_entries()),
+            Repr::Heap(map) => IntoIter::Heap(map.into_iter()),
+        }
+    }
+
+    /// A vacant entry of a full inline table spills it up front, so that
+    /// filling the entry never has to.
+    fn entry(&mut self, key: K) -> Entry<'_, K, Self> {
+        if let Repr::Inline(entries, _) = &self.repr {
+            if entries.is_full() && entries.position(&key).is_none() {
+                self.spill();
+            }
+        }
+        match &mut self.repr {
+            Repr::Inline(backend, _) => match backend.position(&key) {
+                Some(index) => Entry::Occupied(OccupiedEntry::new(Occupied::Inline(fixed::Occupied { backend, index }))),
+                None => Entry::Vacant(VacantEntry::new(Vacant::Inline(fixed::Vacant { backend, key }))),
+            },
+            Repr::Heap(map) => match hash::entry(map, key) {
+                hash_map::Entry::Occupied(entry) => Entry::Occupied(OccupiedEntry::new(Occupied::Heap(entry))),
+                hash_map::Entry::Vacant(entry) => Entry::Vacant(VacantEntry::new(Vacant::Heap(entry))),
+            },
+        }
+    }
+
+    #[inline]
+    fn extract_if<F>(&mut self, pred: F) -> Self::ExtractIf<'_, F>
+    where
+        F: FnMut(&K, &mut Value) -> bool,
+    {
+        match &mut self.repr {
+            Repr::Inline(entries, _) => ExtractIf::Inline(entries.extract_if(pred)),
+            Repr::Heap(map) => ExtractIf::Heap(map.extract_if(pred)),
+        }
+    }
+}
+
+impl<K: Eq + Hash, S: BuildHasher + Clone, const N: usize, Q> MapLookup<K, Q> for SmallBackend<K, S, N>
+where
+    K: Borrow<Q>,
+    Q: ?Sized + Hash + Eq,
+{
+    #[inline]
+    fn get(&self, key: &Q) -> Option<&Value> {
+        match &self.repr {
+            Repr::Inline(entries, _) => entries.get(key),
+            Repr::Heap(map) => map.get(key),
+        }
+    }
+
+    #[inline]
+    fn get_mut(&mut self, key: &Q) -> Option<&mut Value> {
+        match &mut self.repr {
+            Repr::Inline(entries, _) => entries.get_mut(key),
+            Repr::Heap(map) => map.get_mut(key),
+        }
+    }
+
+    #[inline]
+    fn remove(&mut self, key: &Q) -> Option<Value> {
+        match &mut self.repr {
+            Repr::Inline(entries, _) => entries.remove(key),
+            Repr::Heap(map) => map.remove(key),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spills_past_capacity() {
+        let mut map = SmallAnyMap::<u32, DefaultHashBuilder, 2>::default();
+        map.insert(1, "one");
+        map.insert(2, 2);
+        map.insert(2, "two");
+        assert!(!map.backend().spilled());
+        assert_eq!(map.remove(&1).unwrap().as_type::<&str>(), Some(&"one"));
+        assert_eq!(map.get_typed::<&str>(&2), Some(&"two"));
+
+        map.insert(1, 1);
+        map.insert(3, 3);
+        assert!(map.backend().spilled());
+        assert_eq!(map.len(), 3);
+        let mut keys: Vec<_> = map.into_iter().map(|(k, _)| k).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, [1, 2, 3]);
+    }
+}
@@ -0,0 +1,420 @@
+//! Versioned text snapshots of string-keyed maps.
+//!
+//! A snapshot starts with a header line holding the format version, a
+//! fingerprint of the registry and the type names used by the entries,
+//! followed by one `key<TAB>type<TAB>value` line per entry. Values are
+//! written with their registered display function and read back with the
+//! parser registered under `type`.
+//!
+//! [`AnyMap::serialize_into`] and [`SnapshotReader`] stream entries one at a
+//! time, so memory use stays flat for very large maps.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Lines, Write},
+    path::Path,
+};
+
+#[cfg(feature = "compression")]
+use flate2::{bufread::GzDecoder, write::GzEncoder, Compression};
+
+use crate::{registry, AnyMap, ParseValueError, SnapshotError, Value};
+
+const MAGIC: &str = "anymap-snapshot";
+const VERSION: u32 = 2;
+
+impl AnyMap<String> {
+    /// Saves the map to the file at `path`, replacing it if it exists.
+    ///
+    /// Every value's type must be registered with both
+    /// [`registry::register_parse`] and [`registry::register_display`], so
+    /// it can be read back by [`load_from`](Self::load_from). `&'static str`
+    /// values are read back as `String`s.
+    pub fn save_to(&self, path: impl AsRef<Path>) -> Result<(), SnapshotError> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.serialize_into(&mut out)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Loads a map saved with [`save_to`](Self::save_to).
+    ///
+    /// Fails if the snapshot's type names are no longer registered for the
+    /// same types, rather than reading values back as the wrong type.
+    pub fn load_from(path: impl AsRef<Path>) -> Result<Self, SnapshotError> {
+        SnapshotReader::new(BufReader::new(File::open(path)?))?.collect()
+    }
+
+    /// Like [`save_to`](Self::save_to), but gzip-compresses the snapshot.
+    /// Requires the `compression` feature.
+    #[cfg(feature = "compression")]
+    pub fn save_compressed(&self, path: impl AsRef<Path>) -> Result<(), SnapshotError> {
+        let mut out = GzEncoder::new(BufWriter::new(File::create(path)?), Compression::default());
+        self.serialize_into(&mut out)?;
+        out.finish()?.flush()?;
+        Ok(())
+    }
+
+    /// Loads a map saved with [`save_compressed`](Self::save_compressed).
+    /// Requires the `compression` feature.
+    #[cfg(feature = "compression")]
+    pub fn load_compressed(path: impl AsRef<Path>) -> Result<Self, SnapshotError> {
+        let input = GzDecoder::new(BufReader::new(File::open(path)?));
+        SnapshotReader::new(BufReader::new(input))?.collect()
+    }
+
+    /// Writes the map as a snapshot to `out`, one entry at a time. The output
+    /// can be read back with [`SnapshotReader`].
+    ///
+    /// `out` is not buffered, so wrap it in a [`BufWriter`] when writing to
+    /// a file or socket.
+    pub fn serialize_into<W: Write>(&self, mut out: W) -> Result<(), SnapshotError> {
+        let mut types = BTreeMap::new();
+        for (key, value) in self.iter() {
+            let name = type_tag(key, value)?;
+            let type_name = registry::lookup_parser_type(&name).unwrap_or_default();
+            types.insert(name, type_name);
+        }
+        write!(out, "{MAGIC} {VERSION} {:016x}", fingerprint(&types))?;
+        for name in types.keys() {
+            write!(out, "\t{}", escape(name))?;
+        }
+        writeln!(out)?;
+        for (key, value) in self.iter() {
+            let display = value.display().ok_or_else(|| unregistered(key, value))?;
+            writeln!(
+                out,
+                "{}\t{}\t{}",
+                escape(key),
+                escape(&type_tag(key, value)?),
+                escape(&display.to_string()),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the entries of a snapshot one at a time.
+///
+/// The header lists every type name used by the entries, so a snapshot whose
+/// type names map to different types in the current registry is rejected by
+/// [`new`](Self::new) before any entry is read.
+///
+/// ```
+/// use anymap::{AnyMap, SnapshotReader};
+///
+/// let mut map = AnyMap::new();
+/// map.insert("port".to_owned(), 8080u16);
+/// let mut out = Vec::new();
+/// map.serialize_into(&mut out).unwrap();
+///
+/// for entry in SnapshotReader::new(&out[..]).unwrap() {
+///     let (key, value) = entry.unwrap();
+///     assert_eq!(key, "port");
+///     assert_eq!(value.as_type::<u16>(), Some(&8080));
+/// }
+/// ```
+pub struct SnapshotReader<R> {
+    lines: Lines<R>,
+    line_no: usize,
+    types: BTreeMap<String, &'static str>,
+    done: bool,
+}
+
+impl<R: BufRead> SnapshotReader<R> {
+    /// Reads the snapshot header from `input`, and checks that its type names
+    /// still map to the same types.
+    pub fn new(input: R) -> Result<Self, SnapshotError> {
+        let mut lines = input.lines();
+        let header = lines.next().transpose()?.ok_or(SnapshotError::InvalidHeader)?;
+        let (expected, names) = parse_header(&header)?;
+        let types = names
+            .into_iter()
+            .map(|name| {
+                let type_name = registry::lookup_parser_type(&name).unwrap_or_default();
+                (name, type_name)
+            })
+            .collect();
+        if fingerprint(&types) != expected {
+            return Err(SnapshotError::RegistryMismatch);
+        }
+        Ok(SnapshotReader {
+            lines,
+            line_no: 1,
+            types,
+            done: false,
+        })
+    }
+
+    fn read_entry(&mut self, line: &str) -> Result<(String, Value), SnapshotError> {
+        let line_no = self.line_no;
+        let (key, name, value) = parse_entry(line, line_no)?;
+        if !self.types.contains_key(&name) {
+            return Err(SnapshotError::Malformed { line: line_no });
+        }
+        let parse = registry::lookup_parser(&name).ok_or_else(|| SnapshotError::Parse {
+            line: line_no,
+            error: ParseValueError::UnknownType(name.clone()),
+        })?;
+        let value = parse(&value).map_err(|message| SnapshotError::Parse {
+            line: line_no,
+            error: ParseValueError::Invalid { type_name: name.clone(), message },
+        })?;
+        Ok((key, registry::migrate(value)))
+    }
+}
+
+impl<R: BufRead> Iterator for SnapshotReader<R> {
+    type Item = Result<(String, Value), SnapshotError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        self.line_no += 1;
+        let result = match self.lines.next() {
+            Some(Ok(line)) => self.read_entry(&line),
+            Some(Err(error)) => Err(error.into()),
+            None => {
+                self.done = true;
+                return None;
+            }
+        };
+        self.done = result.is_err();
+        Some(result)
+    }
+}
+
+/// Returns the fingerprint and the type names listed in `header`.
+fn parse_header(header: &str) -> Result<(u64, Vec<String>), SnapshotError> {
+    let mut fields = header.split('\t');
+    let mut parts = fields.next().unwrap_or_default().split(' ');
+    if parts.next() != Some(MAGIC) {
+        return Err(SnapshotError::InvalidHeader);
+    }
+    let version = parts
+        .next()
+        .and_then(|version| version.parse::<u32>().ok())
+        .ok_or(SnapshotError::InvalidHeader)?;
+    if version != VERSION {
+        return Err(SnapshotError::UnsupportedVersion(version));
+    }
+    let fingerprint = match (parts.next().map(|hex| u64::from_str_radix(hex, 16)), parts.next()) {
+        (Some(Ok(fingerprint)), None) => fingerprint,
+        _ => return Err(SnapshotError::InvalidHeader),
+    };
+    let names = fields.map(unescape).collect::<Option<_>>().ok_or(SnapshotError::InvalidHeader)?;
+    Ok((fingerprint, names))
+}
+
+fn parse_entry(line: &str, line_no: usize) -> Result<(String, String, String), SnapshotError> {
+    let malformed = || SnapshotError::Malformed { line: line_no };
+    let mut fields = line.split('\t').map(unescape);
+    match (fields.next(), fields.next(), fields.next(), fields.next()) {
+        (Some(Some(key)), Some(Some(name)), Some(Some(value)), None) => Ok((key, name, value)),
+        _ => Err(malformed()),
+    }
+}
+
+fn type_tag(key: &str, value: &Value) -> Result<String, SnapshotError> {
+    registry::lookup_name(value.type_id()).ok_or_else(|| unregistered(key, value))
+}
+
+fn unregistered(key: &str, value: &Value) -> SnapshotError {
+    SnapshotError::Unregistered {
+        key: key.to_owned(),
+        type_name: value.describe_type(),
+    }
+}
+
+/// FNV-1a over the type names and the Rust types they are registered for.
+/// Unlike `DefaultHasher`, it is stable across Rust releases.
+fn fingerprint(types: &BTreeMap<String, &'static str>) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for (name, type_name) in types {
+        for byte in name.bytes().chain([0]).chain(type_name.bytes()).chain([0]) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+fn escape(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(field: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        unescaped.push(match chars.next()? {
+            '\\' => '\\',
+            't' => '\t',
+            'n' => '\n',
+            'r' => '\r',
+            _ => return None,
+        });
+    }
+    Some(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fmt, fs,
+        num::{NonZeroI32, NonZeroI64, NonZeroU16, NonZeroU32, NonZeroU8},
+        str::FromStr,
+    };
+
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Celsius(f64);
+
+    impl fmt::Display for Celsius {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.fmt(f)
+        }
+    }
+
+    impl FromStr for Celsius {
+        type Err = std::num::ParseFloatError;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            s.parse().map(Celsius)
+        }
+    }
+
+    fn snapshot(map: &AnyMap<String>) -> Vec<u8> {
+        let mut out = Vec::new();
+        map.serialize_into(&mut out).unwrap();
+        out
+    }
+
+    fn read(input: &[u8]) -> Result<AnyMap<String>, SnapshotError> {
+        SnapshotReader::new(input)?.collect()
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        registry::register_parse::<Celsius>("snapshot::Celsius");
+        registry::register_display::<Celsius>();
+
+        let mut map = AnyMap::new();
+        map.insert("port".to_owned(), 8080u16);
+        map.insert("motd".to_owned(), "tab\there\nline \\ two".to_owned());
+        map.insert("name".to_owned(), "static");
+        map.insert("temp".to_owned(), Celsius(21.5));
+
+        let path = env::temp_dir().join(format!("anymap-snapshot-{}", std::process::id()));
+        map.save_to(&path).unwrap();
+        let loaded = AnyMap::load_from(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.len(), 4);
+        assert_eq!(loaded.get_typed::<u16>("port"), Some(&8080));
+        assert_eq!(loaded.get_typed::<String>("motd").unwrap(), "tab\there\nline \\ two");
+        assert_eq!(loaded.get_typed::<String>("name").unwrap(), "static");
+        assert_eq!(loaded.get_typed::<Celsius>("temp"), Some(&Celsius(21.5)));
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn snapshot_compressed() {
+        let mut map = AnyMap::new();
+        for i in 0..100 {
+            map.insert(format!("key{i}"), "a rather repetitive value".to_owned());
+        }
+
+        let path = env::temp_dir().join(format!("anymap-snapshot-compressed-{}", std::process::id()));
+        map.save_compressed(&path).unwrap();
+        let size = fs::metadata(&path).unwrap().len();
+        let loaded = AnyMap::load_compressed(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(size < snapshot(&map).len() as u64 / 4);
+        assert_eq!(loaded.len(), 100);
+        assert_eq!(loaded.get_typed::<String>("key42").unwrap(), "a rather repetitive value");
+    }
+
+    #[test]
+    fn snapshot_rejects_incompatible() {
+        struct Opaque;
+
+        let mut map = AnyMap::new();
+        map.insert("opaque".to_owned(), Opaque);
+        let err = map.serialize_into(Vec::new()).unwrap_err();
+        assert!(matches!(err, SnapshotError::Unregistered { key, .. } if key == "opaque"));
+
+        registry::register_parse::<NonZeroU8>("snapshot::Level");
+        registry::register_display::<NonZeroU8>();
+        let mut map = AnyMap::new();
+        map.insert("level".to_owned(), NonZeroU8::new(3).unwrap());
+        let saved = snapshot(&map);
+        registry::register_parse::<NonZeroU32>("snapshot::Level");
+        let err = SnapshotReader::new(&saved[..]).err().unwrap();
+        assert!(matches!(err, SnapshotError::RegistryMismatch));
+
+        let err = read(&b"{}"[..]).unwrap_err();
+        assert!(matches!(err, SnapshotError::InvalidHeader));
+        let err = read(&b"anymap-snapshot 9 0"[..]).unwrap_err();
+        assert!(matches!(err, SnapshotError::UnsupportedVersion(9)));
+
+        let mut saved = snapshot(&AnyMap::new());
+        saved.extend_from_slice(b"key\tu8\n");
+        let err = read(&saved[..]).unwrap_err();
+        assert!(matches!(err, SnapshotError::Malformed { line: 2 }));
+
+        // Entries may only use the type names listed in the header.
+        let mut saved = snapshot(&[("a".to_owned(), 1)].into_iter().collect());
+        saved.extend_from_slice(b"b\tu8\t2\n");
+        let err = read(&saved[..]).unwrap_err();
+        assert!(matches!(err, SnapshotError::Malformed { line: 3 }));
+    }
+
+    #[test]
+    fn snapshot_reader_streams() {
+        let map: AnyMap<String> = (0..100).map(|i| (i.to_string(), i)).collect();
+        let saved = snapshot(&map);
+
+        let mut reader = SnapshotReader::new(&saved[..]).unwrap();
+        let (key, value) = reader.next().unwrap().unwrap();
+        assert_eq!(value.as_type::<i32>().unwrap().to_string(), key);
+        assert_eq!(reader.count(), 99);
+
+        let mut saved = saved;
+        saved.extend_from_slice(b"bad\ti32\tx\nnext\ti32\t1\n");
+        let mut reader = SnapshotReader::new(&saved[..]).unwrap().skip(100);
+        assert!(matches!(reader.next(), Some(Err(SnapshotError::Parse { line: 102, .. }))));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn snapshot_migrations_chain() {
+        registry::register_parse::<NonZeroU16>("snapshot::Version@1");
+        registry::register_display::<NonZeroU16>();
+        registry::register_migration::<NonZeroU16, NonZeroI32>(NonZeroI32::from);
+        registry::register_migration::<NonZeroI32, NonZeroI64>(NonZeroI64::from);
+
+        let mut map = AnyMap::new();
+        map.insert("version".to_owned(), NonZeroU16::new(2).unwrap());
+        let loaded = read(&snapshot(&map)).unwrap();
+        assert_eq!(loaded.get_typed::<NonZeroI64>("version"), NonZeroI64::new(2).as_ref());
+    }
+}
@@ -0,0 +1,105 @@
+//! Maps keeping their keys in order.
+
+use alloc::{
+    collections::{btree_map, BTreeMap},
+    string::String,
+};
+use core::{any::Any, borrow::Borrow, ops::RangeBounds};
+
+use crate::{AnyMap, Value};
+
+/// A map with values of any type whose keys are kept sorted, backed by a
+/// `BTreeMap`.
+///
+/// ```
+/// use anymap::SortedAnyMap;
+///
+/// let mut events = SortedAnyMap::<u64>::default();
+/// events.insert(1_700_000_300, "deploy");
+/// events.insert(1_700_000_100, "build");
+/// events.insert(1_700_000_200, "test");
+///
+/// let window: Vec<_> = events.range(1_700_000_150..).map(|(t, _)| *t).collect();
+/// assert_eq!(window, [1_700_000_200, 1_700_000_300]);
+/// assert_eq!(events.first_key_value().unwrap().0, &1_700_000_100);
+/// ```
+pub type SortedAnyMap<K = String> = AnyMap<K, BTreeMap<K, Value>>;
+
+impl<K: Ord> AnyMap<K, BTreeMap<K, Value>> {
+    /// Returns the entries whose keys lie in `range`, in key order.
+    #[inline]
+    pub fn range<Q, R>(&self, range: R) -> btree_map::Range<'_, K, Value>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+        R: RangeBounds<Q>,
+    {
+        self.map.range(range)
+    }
+
+    #[inline]
+    pub fn range_mut<Q, R>(&mut self, range: R) -> btree_map::RangeMut<'_, K, Value>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+        R: RangeBounds<Q>,
+    {
+        self.map.range_mut(range)
+    }
+
+    /// Returns the entries in `range` whose values are a `T`.
+    pub fn range_typed<T: Any, Q, R>(&self, range: R) -> impl Iterator<Item = (&K, &T)> + '_
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+        R: RangeBounds<Q>,
+    {
+        self.map.range(range).filter_map(|(k, v)| Some((k, v.as_type::<T>()?)))
+    }
+
+    #[inline]
+    pub fn first_key_value(&self) -> Option<(&K, &Value)> {
+        self.map.first_key_value()
+    }
+
+    #[inline]
+    pub fn last_key_value(&self) -> Option<(&K, &Value)> {
+        self.map.last_key_value()
+    }
+
+    #[inline]
+    pub fn pop_first(&mut self) -> Option<(K, Value)> {
+        self.map.pop_first()
+    }
+
+    #[inline]
+    pub fn pop_last(&mut self) -> Option<(K, Value)> {
+        self.map.pop_last()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sorted_ranges() {
+        let mut map: SortedAnyMap<u32> = (1..=5).map(|i| (i * 10, i)).collect();
+        map.insert(25, "between");
+
+        let keys: Vec<_> = map.range(20..=30).map(|(k, _)| *k).collect();
+        assert_eq!(keys, [20, 25, 30]);
+        let ints: Vec<_> = map.range_typed::<u32, _, _>(20..=30).map(|(_, v)| *v).collect();
+        assert_eq!(ints, [2, 3]);
+
+        for (_, value) in map.range_mut(..20) {
+            *value.as_type_mut::<u32>().unwrap() *= 100;
+        }
+        assert_eq!(map.get_typed::<u32>(&10), Some(&100));
+
+        assert_eq!(map.last_key_value().unwrap().0, &50);
+        assert_eq!(map.pop_first().unwrap().0, 10);
+        assert_eq!(map.pop_last().unwrap().0, 50);
+        assert_eq!(map.len(), 4);
+    }
+}
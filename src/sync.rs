@@ -0,0 +1,250 @@
+//! Maps that can be sent to or shared with other threads.
+
+use std::{
+    any::Any,
+    borrow::Borrow,
+    collections::hash_map::Iter,
+    fmt,
+    hash::{BuildHasher, Hash},
+};
+
+use crate::{AnyMap, DefaultHashBuilder, HashBackend, Keys, LookupKey, Value};
+
+macro_rules! bounded {
+    ($(#[$attr:meta])* $name:ident: $first:ident $(+ $rest:ident)*) => {
+        $(#[$attr])*
+        pub struct $name<K = String, S = DefaultHashBuilder> {
+            // Only holds values with all of the map's bounds. Handing out
+            // `&mut Value` would allow storing others, so mutable access is
+            // typed.
+            map: AnyMap<K, HashBackend<K, S>>,
+        }
+
+        // SAFETY: `insert` only accepts values with these bounds.
+        unsafe impl<K: $first, S: $first> $first for $name<K, S> {}
+        $(unsafe impl<K: $rest, S: $rest> $rest for $name<K, S> {})*
+
+        impl<K> $name<K> {
+            #[inline]
+            #[must_use]
+            pub fn new() -> Self {
+                Self::default()
+            }
+        }
+
+        impl<K, S: Default> Default for $name<K, S> {
+            #[inline]
+            fn default() -> Self {
+                $name {
+                    map: AnyMap::default(),
+                }
+            }
+        }
+
+        impl<K, S> $name<K, S> {
+            #[inline]
+            #[must_use]
+            pub fn with_hasher(hasher: S) -> Self {
+                $name {
+                    map: AnyMap::with_hasher(hasher),
+                }
+            }
+
+            /// Returns the map as a plain [`AnyMap`], for read-only use.
+            #[inline]
+            pub fn as_map(&self) -> &AnyMap<K, HashBackend<K, S>> {
+                &self.map
+            }
+
+            #[inline]
+            pub fn into_map(self) -> AnyMap<K, HashBackend<K, S>> {
+                self.map
+            }
+        }
+
+        impl<K: Eq + Hash, S: BuildHasher> $name<K, S> {
+            #[inline]
+            pub fn len(&self) -> usize {
+                self.map.len()
+            }
+
+            #[inline]
+            pub fn is_empty(&self) -> bool {
+                self.map.is_empty()
+            }
+
+            #[inline]
+            pub fn clear(&mut self) {
+                self.map.clear()
+            }
+
+            #[inline]
+            pub fn keys(&self) -> Keys<'_, K, HashBackend<K, S>> {
+                self.map.keys()
+            }
+
+            #[inline]
+            pub fn iter(&self) -> Iter<'_, K, Value> {
+                self.map.iter()
+            }
+
+            #[inline]
+            pub fn insert<T: Any + $first $(+ $rest)*>(&mut self, key: K, value: T) -> Option<Value> {
+                self.map.insert(key, value)
+            }
+
+            #[inline]
+            pub fn get<Q>(&self, key: &Q) -> Option<&Value>
+            where
+                K: Borrow<Q>,
+                Q: ?Sized + Hash + Eq,
+            {
+                self.map.get(key)
+            }
+
+            #[inline]
+            pub fn get_typed<T: Any>(&self, key: &(impl ?Sized + LookupKey<K, HashBackend<K, S>>)) -> Option<&T> {
+                self.map.get_typed(key)
+            }
+
+            #[inline]
+            pub fn get_typed_mut<T: Any>(&mut self, key: &(impl ?Sized + LookupKey<K, HashBackend<K, S>>)) -> Option<&mut T> {
+                self.map.get_typed_mut(key)
+            }
+
+            #[inline]
+            pub fn contains_key<Q>(&self, key: &Q) -> bool
+            where
+                K: Borrow<Q>,
+                Q: ?Sized + Hash + Eq,
+            {
+                self.map.contains_key(key)
+            }
+
+            #[inline]
+            pub fn remove<Q>(&mut self, key: &Q) -> Option<Value>
+            where
+                K: Borrow<Q>,
+                Q: ?Sized + Hash + Eq,
+            {
+                self.map.remove(key)
+            }
+
+            #[inline]
+            pub fn remove_typed<T: Any>(&mut self, key: &(impl ?Sized + LookupKey<K, HashBackend<K, S>>)) -> Option<T> {
+                self.map.remove_typed(key)
+            }
+        }
+
+        impl<K: fmt::Debug + Eq + Hash, S: BuildHasher> fmt::Debug for $name<K, S> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Debug::fmt(&self.map, f)
+            }
+        }
+
+        impl<K: Eq + Hash, T: Any + $first $(+ $rest)*, S: BuildHasher + Default> FromIterator<(K, T)> for $name<K, S> {
+            fn from_iter<I: IntoIterator<Item = (K, T)>>(iter: I) -> Self {
+                $name {
+                    map: iter.into_iter().collect(),
+                }
+            }
+        }
+
+        impl<K: Eq + Hash, S: BuildHasher> IntoIterator for $name<K, S> {
+            type Item = (K, Value);
+            type IntoIter = std::collections::hash_map::IntoIter<K, Value>;
+
+            #[inline]
+            fn into_iter(self) -> Self::IntoIter {
+                self.map.into_iter()
+            }
+        }
+    };
+}
+
+bounded! {
+    /// A map with values of any `Send` type, which can itself be moved to
+    /// other threads.
+    ///
+    /// ```
+    /// use anymap::SendAnyMap;
+    ///
+    /// let mut map = SendAnyMap::<&str>::new();
+    /// map.insert("buffer", vec![1u8, 2, 3]);
+    /// let map = std::thread::spawn(move || {
+    ///     map.get_typed_mut::<Vec<u8>>("buffer").unwrap().push(4);
+    ///     map
+    /// }).join().unwrap();
+    /// assert_eq!(map.get_typed::<Vec<u8>>("buffer").unwrap().len(), 4);
+    /// ```
+    SendAnyMap: Send
+}
+
+bounded! {
+    /// A map with values of any `Send + Sync` type, which can itself be
+    /// shared between threads, e.g. behind an `Arc` or `RwLock`.
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use anymap::SyncAnyMap;
+    ///
+    /// let mut map = SyncAnyMap::<&str>::new();
+    /// map.insert("name", String::from("shared"));
+    /// let map = Arc::new(map);
+    /// let other = Arc::clone(&map);
+    /// std::thread::spawn(move || {
+    ///     assert_eq!(other.get_typed::<String>("name").unwrap(), "shared");
+    /// }).join().unwrap();
+    /// ```
+    SyncAnyMap: Send + Sync
+}
+
+impl<K, S> From<SyncAnyMap<K, S>> for SendAnyMap<K, S> {
+    #[inline]
+    fn from(map: SyncAnyMap<K, S>) -> Self {
+        SendAnyMap { map: map.map }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Mutex, thread};
+
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    #[test]
+    fn send_map_across_threads() {
+        let mut map: SendAnyMap<u8> = [(1, 1u32)].into_iter().collect();
+        map.insert(2, String::from("two"));
+        let mut map = thread::spawn(move || {
+            *map.get_typed_mut::<u32>(&1).unwrap() += 1;
+            map
+        })
+        .join()
+        .unwrap();
+        assert_eq!(map.remove_typed::<u32>(&1), Some(2));
+        assert_eq!(map.into_map().get_typed::<String>(&2).unwrap(), "two");
+    }
+
+    #[test]
+    fn sync_map_shared() {
+        let shared = Mutex::new(SyncAnyMap::<&str>::new());
+        assert_send_sync(&shared);
+        thread::scope(|scope| {
+            for i in 0..4u64 {
+                let shared = &shared;
+                scope.spawn(move || {
+                    shared.lock().unwrap().insert(["a", "b", "c", "d"][i as usize], i);
+                });
+            }
+        });
+        let map = shared.into_inner().unwrap();
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.get_typed::<u64>("c"), Some(&2));
+        let map = SendAnyMap::from(map);
+        assert!(map.contains_key("a"));
+    }
+}
@@ -0,0 +1,603 @@
+//! Loading maps from TOML.
+//!
+//! The parser covers TOML 1.0 except for dates and times, which are rejected
+//! since there is no standard type to store them as.
+
+use alloc::{
+    borrow::ToOwned,
+    format,
+    string::String,
+    vec,
+    vec::Vec,
+};
+
+use crate::{hash::HashSet, AnyMap, ConfigError, Value};
+
+type Table = AnyMap<String>;
+
+impl AnyMap<String> {
+    /// Parses a TOML document. Integers are stored as `i64`, floats as
+    /// `f64`, booleans as `bool`, strings as `String`, arrays as `Vec<Value>`
+    /// and tables, including inline tables, as nested `AnyMap<String>`s.
+    ///
+    /// ```
+    /// use anymap::{AnyMap, Value};
+    ///
+    /// let config = AnyMap::from_toml(r#"
+    /// name = "example"
+    /// ratio = 0.5
+    ///
+    /// [server]
+    /// port = 8080
+    /// hosts = ["a", "b"]
+    /// "#).unwrap();
+    /// assert_eq!(config.get_typed::<String>("name").unwrap(), "example");
+    /// assert_eq!(config.get_typed::<f64>("ratio"), Some(&0.5));
+    /// let server = config.get_typed::<AnyMap>("server").unwrap();
+    /// assert_eq!(server.get_typed::<i64>("port"), Some(&8080));
+    /// assert_eq!(server.get_typed::<Vec<Value>>("hosts").unwrap().len(), 2);
+    /// ```
+    pub fn from_toml(input: &str) -> Result<Self, ConfigError> {
+        Parser { input, pos: 0, line: 1 }.document()
+    }
+}
+
+/// Returns the table `value` stands for in a dotted key or table header,
+/// which for an array of tables is its last element.
+fn as_table(value: &mut Value) -> Option<&mut Table> {
+    if value.is::<Table>() {
+        return value.as_type_mut();
+    }
+    value.as_type_mut::<Vec<Value>>()?.last_mut()?.as_type_mut()
+}
+
+/// Walks `path` down from `table`, creating tables that don't exist yet.
+fn table_at<'a>(mut table: &'a mut Table, path: &[String]) -> Result<&'a mut Table, String> {
+    for (i, key) in path.iter().enumerate() {
+        table = as_table(table.entry(key.clone()).or_insert_with(|| Value::new(Table::new())))
+            .ok_or_else(|| format!("key `{}` is not a table", path[..=i].join(".")))?;
+    }
+    Ok(table)
+}
+
+fn insert(table: &mut Table, path: &[String], value: Value) -> Result<(), String> {
+    let (key, parent) = path.split_last().expect("keys are never empty");
+    let table = table_at(table, parent)?;
+    if table.contains_key(key.as_str()) {
+        return Err(format!("duplicate key `{}`", path.join(".")));
+    }
+    table.insert_val(key.clone(), value);
+    Ok(())
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: impl Into<String>) -> ConfigError {
+        ConfigError::new(self.line, message)
+    }
+
+    fn unexpected(&self, expected: &str) -> ConfigError {
+        match self.peek() {
+            None => self.error(format!("expected {expected}, found end of input")),
+            Some('\n' | '\r') => self.error(format!("expected {expected}, found end of line")),
+            Some(c) => self.error(format!("expected {expected}, found `{c}`")),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.bump();
+        }
+        found
+    }
+
+    fn eat_str(&mut self, s: &str) -> bool {
+        let found = self.input[self.pos..].starts_with(s);
+        if found {
+            for _ in s.chars() {
+                self.bump();
+            }
+        }
+        found
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ConfigError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{c}`")))
+        }
+    }
+
+    fn eat_newline(&mut self) -> bool {
+        self.eat_str("\r\n") || self.eat('\n')
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.bump();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n' | '\r')) {
+                self.bump();
+            }
+        }
+    }
+
+    /// Skips whitespace, comments and newlines, as allowed between entries
+    /// and between array items.
+    fn skip_blank(&mut self) {
+        loop {
+            self.skip_whitespace();
+            self.skip_comment();
+            if !self.eat_newline() {
+                return;
+            }
+        }
+    }
+
+    fn end_of_line(&mut self) -> Result<(), ConfigError> {
+        self.skip_whitespace();
+        self.skip_comment();
+        if self.peek().is_none() || self.eat_newline() {
+            Ok(())
+        } else {
+            Err(self.unexpected("end of line"))
+        }
+    }
+
+    fn document(mut self) -> Result<Table, ConfigError> {
+        let mut root = Table::new();
+        let mut current = Vec::new();
+        // Tables defined by a `[header]`, which may only appear once.
+        let mut defined = HashSet::new();
+        loop {
+            self.skip_blank();
+            if self.peek().is_none() {
+                return Ok(root);
+            }
+            let line = self.line;
+            if self.eat('[') {
+                let array = self.eat('[');
+                self.skip_whitespace();
+                let path = self.key()?;
+                self.skip_whitespace();
+                self.expect(']')?;
+                if array {
+                    self.expect(']')?;
+                    self.push_table(&mut root, &path).map_err(|message| ConfigError::new(line, message))?;
+                    // Each element of an array of tables gets its own subtables.
+                    defined.retain(|defined: &Vec<String>| !defined.starts_with(&path));
+                } else {
+                    if !defined.insert(path.clone()) {
+                        return Err(ConfigError::new(line, format!("duplicate table `{}`", path.join("."))));
+                    }
+                    table_at(&mut root, &path).map_err(|message| ConfigError::new(line, message))?;
+                }
+                current = path;
+            } else {
+                let (path, value) = self.key_value()?;
+                let table = table_at(&mut root, &current).map_err(|message| ConfigError::new(line, message))?;
+                insert(table, &path, value).map_err(|message| ConfigError::new(line, message))?;
+            }
+            self.end_of_line()?;
+        }
+    }
+
+    /// Appends a new table to the array of tables at `path`.
+    fn push_table(&self, root: &mut Table, path: &[String]) -> Result<(), String> {
+        let (key, parent) = path.split_last().expect("keys are never empty");
+        let value = table_at(root, parent)?
+            .entry(key.clone())
+            .or_insert_with(|| Value::new(Vec::<Value>::new()));
+        match value.as_type_mut::<Vec<Value>>() {
+            Some(items) if items.iter().all(Value::is::<Table>) => {
+                items.push(Value::new(Table::new()));
+                Ok(())
+            }
+            _ => Err(format!("key `{}` is not an array of tables", path.join("."))),
+        }
+    }
+
+    fn key_value(&mut self) -> Result<(Vec<String>, Value), ConfigError> {
+        let path = self.key()?;
+        self.skip_whitespace();
+        self.expect('=')?;
+        self.skip_whitespace();
+        Ok((path, self.value()?))
+    }
+
+    fn key(&mut self) -> Result<Vec<String>, ConfigError> {
+        let mut path = vec![self.simple_key()?];
+        loop {
+            self.skip_whitespace();
+            if !self.eat('.') {
+                return Ok(path);
+            }
+            self.skip_whitespace();
+            path.push(self.simple_key()?);
+        }
+    }
+
+    fn simple_key(&mut self) -> Result<String, ConfigError> {
+        if self.eat('"') {
+            return self.basic_string();
+        }
+        if self.eat('\'') {
+            return self.literal_string();
+        }
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            self.bump();
+        }
+        if start == self.pos {
+            return Err(self.unexpected("a key"));
+        }
+        Ok(self.input[start..self.pos].to_owned())
+    }
+
+    fn value(&mut self) -> Result<Value, ConfigError> {
+        if self.eat_str("\"\"\"") {
+            return self.multiline_basic_string().map(Value::new);
+        }
+        if self.eat('"') {
+            return self.basic_string().map(Value::new);
+        }
+        if self.eat_str("'''") {
+            return self.multiline_literal_string().map(Value::new);
+        }
+        if self.eat('\'') {
+            return self.literal_string().map(Value::new);
+        }
+        if self.eat('[') {
+            return self.array();
+        }
+        if self.eat('{') {
+            return self.inline_table();
+        }
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-' | '.' | ':')) {
+            self.bump();
+        }
+        let token = &self.input[start..self.pos];
+        if token.is_empty() {
+            return Err(self.unexpected("a value"));
+        }
+        if is_date_time(token) {
+            return Err(self.error("dates and times are not supported"));
+        }
+        scalar(token).ok_or_else(|| self.error(format!("invalid value `{token}`")))
+    }
+
+    fn array(&mut self) -> Result<Value, ConfigError> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_blank();
+            if self.eat(']') {
+                return Ok(Value::new(items));
+            }
+            items.push(self.value()?);
+            self.skip_blank();
+            if self.eat(']') {
+                return Ok(Value::new(items));
+            }
+            if !self.eat(',') {
+                return Err(self.unexpected("`,` or `]`"));
+            }
+        }
+    }
+
+    fn inline_table(&mut self) -> Result<Value, ConfigError> {
+        let mut table = Table::new();
+        self.skip_whitespace();
+        if self.eat('}') {
+            return Ok(Value::new(table));
+        }
+        loop {
+            let (path, value) = self.key_value()?;
+            insert(&mut table, &path, value).map_err(|message| self.error(message))?;
+            self.skip_whitespace();
+            if self.eat('}') {
+                return Ok(Value::new(table));
+            }
+            if !self.eat(',') {
+                return Err(self.unexpected("`,` or `}`"));
+            }
+            self.skip_whitespace();
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, ConfigError> {
+        let mut s = String::new();
+        loop {
+            match self.peek() {
+                None | Some('\n' | '\r') => return Err(self.error("unterminated string")),
+                Some('"') => {
+                    self.bump();
+                    return Ok(s);
+                }
+                Some('\\') => {
+                    self.bump();
+                    s.push(self.escape()?);
+                }
+                Some(c) => {
+                    self.bump();
+                    s.push(c);
+                }
+            }
+        }
+    }
+
+    fn multiline_basic_string(&mut self) -> Result<String, ConfigError> {
+        self.eat_newline();
+        let mut s = String::new();
+        loop {
+            if self.eat_str("\"\"\"") {
+                // Up to two quotes right before the closing ones belong to
+                // the string.
+                for _ in 0..2 {
+                    if self.eat('"') {
+                        s.push('"');
+                    }
+                }
+                return Ok(s);
+            }
+            if self.eat_newline() {
+                s.push('\n');
+                continue;
+            }
+            match self.bump() {
+                None => return Err(self.error("unterminated string")),
+                Some('\\') if matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) => {
+                    // A backslash at the end of a line trims all whitespace
+                    // up to the next non-blank character.
+                    self.skip_whitespace();
+                    if !self.eat_newline() {
+                        return Err(self.error("invalid escape sequence"));
+                    }
+                    while matches!(self.peek(), Some(' ' | '\t')) || self.eat_newline() {
+                        self.skip_whitespace();
+                    }
+                }
+                Some('\\') => s.push(self.escape()?),
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String, ConfigError> {
+        let start = self.pos;
+        loop {
+            match self.peek() {
+                None | Some('\n' | '\r') => return Err(self.error("unterminated string")),
+                Some('\'') => {
+                    let s = self.input[start..self.pos].to_owned();
+                    self.bump();
+                    return Ok(s);
+                }
+                Some(_) => {
+                    self.bump();
+                }
+            }
+        }
+    }
+
+    fn multiline_literal_string(&mut self) -> Result<String, ConfigError> {
+        self.eat_newline();
+        let mut s = String::new();
+        loop {
+            if self.eat_str("'''") {
+                for _ in 0..2 {
+                    if self.eat('\'') {
+                        s.push('\'');
+                    }
+                }
+                return Ok(s);
+            }
+            if self.eat_newline() {
+                s.push('\n');
+                continue;
+            }
+            match self.bump() {
+                None => return Err(self.error("unterminated string")),
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, ConfigError> {
+        Ok(match self.bump() {
+            Some('b') => '\u{8}',
+            Some('t') => '\t',
+            Some('n') => '\n',
+            Some('f') => '\u{c}',
+            Some('r') => '\r',
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('u') => self.unicode(4)?,
+            Some('U') => self.unicode(8)?,
+            _ => return Err(self.error("invalid escape sequence")),
+        })
+    }
+
+    fn unicode(&mut self, len: usize) -> Result<char, ConfigError> {
+        let c = self
+            .input
+            .get(self.pos..self.pos + len)
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        self.pos += len;
+        Ok(c)
+    }
+}
+
+fn is_date_time(token: &str) -> bool {
+    let bytes = token.as_bytes();
+    token.contains(':') || (bytes.len() > 4 && bytes[..4].iter().all(u8::is_ascii_digit) && bytes[4] == b'-')
+}
+
+/// Parses a bare value: a boolean, an integer or a float.
+fn scalar(token: &str) -> Option<Value> {
+    match token {
+        "true" => return Some(Value::new(true)),
+        "false" => return Some(Value::new(false)),
+        "inf" | "+inf" => return Some(Value::new(f64::INFINITY)),
+        "-inf" => return Some(Value::new(f64::NEG_INFINITY)),
+        "nan" | "+nan" | "-nan" => return Some(Value::new(f64::NAN)),
+        _ => {}
+    }
+    for (prefix, radix) in [("0x", 16), ("0o", 8), ("0b", 2)] {
+        if let Some(digits) = token.strip_prefix(prefix) {
+            let digits = without_underscores(digits, radix)?;
+            if digits.starts_with(['+', '-']) {
+                return None;
+            }
+            return i64::from_str_radix(&digits, radix).ok().map(Value::new);
+        }
+    }
+    let digits = without_underscores(token, 10)?;
+    if digits.contains(['.', 'e', 'E']) {
+        let mantissa = digits.split(['e', 'E']).next()?.trim_start_matches(['+', '-']);
+        if mantissa.starts_with('.') || mantissa.ends_with('.') {
+            return None;
+        }
+        return digits.parse::<f64>().ok().map(Value::new);
+    }
+    let unsigned = digits.trim_start_matches(['+', '-']);
+    if unsigned.len() > 1 && unsigned.starts_with('0') {
+        return None;
+    }
+    digits.parse::<i64>().ok().map(Value::new)
+}
+
+/// Removes the underscores allowed between the digits of a number.
+fn without_underscores(token: &str, radix: u32) -> Option<String> {
+    let bytes = token.as_bytes();
+    let is_digit = |i: Option<usize>| i.and_then(|i| bytes.get(i)).is_some_and(|&b| char::from(b).is_digit(radix));
+    for (i, &b) in bytes.iter().enumerate() {
+        if b == b'_' && !(is_digit(i.checked_sub(1)) && is_digit(Some(i + 1))) {
+            return None;
+        }
+    }
+    Some(token.replace('_', ""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values() {
+        let map = AnyMap::from_toml(
+            r#"
+            # comment
+            int = +1_000 # trailing comment
+            hex = 0xdead_beef
+            neg = -17
+            float = 6.626e-34
+            inf = -inf
+            yes = true
+            basic = "tab\there \u00e9"
+            literal = 'C:\path'
+            multi = """
+            one \
+              two"""
+            raw = '''
+            line'''
+            empty = []
+            mixed = [1, "two", [3.0], { four = 4 }]
+            inline = { a.b = 1, c = "d" }
+            "quoted key" = 1
+            "#,
+        )
+        .unwrap();
+        assert_eq!(map.get_typed::<i64>("int"), Some(&1000));
+        assert_eq!(map.get_typed::<i64>("hex"), Some(&0xdead_beef));
+        assert_eq!(map.get_typed::<i64>("neg"), Some(&-17));
+        assert_eq!(map.get_typed::<f64>("float"), Some(&6.626e-34));
+        assert_eq!(map.get_typed::<f64>("inf"), Some(&f64::NEG_INFINITY));
+        assert_eq!(map.get_typed::<bool>("yes"), Some(&true));
+        assert_eq!(map.get_typed::<String>("basic").unwrap(), "tab\there é");
+        assert_eq!(map.get_typed::<String>("literal").unwrap(), "C:\\path");
+        assert_eq!(map.get_typed::<String>("multi").unwrap(), "            one two");
+        assert_eq!(map.get_typed::<String>("raw").unwrap(), "            line");
+        assert!(map.get_typed::<Vec<Value>>("empty").unwrap().is_empty());
+        let mixed = map.get_typed::<Vec<Value>>("mixed").unwrap();
+        assert_eq!(mixed[1].as_type::<String>().unwrap(), "two");
+        assert_eq!(mixed[3].as_type::<Table>().unwrap().get_typed::<i64>("four"), Some(&4));
+        let inline = map.get_typed::<Table>("inline").unwrap();
+        assert_eq!(inline.get_typed::<Table>("a").unwrap().get_typed::<i64>("b"), Some(&1));
+        assert_eq!(map.get_typed::<i64>("quoted key"), Some(&1));
+    }
+
+    #[test]
+    fn tables() {
+        let map = AnyMap::from_toml(
+            "\
+            title = \"top\"\n\
+            [server.http]\n\
+            port = 80\n\
+            [server]\n\
+            name = \"main\"\n\
+            [[fruit]]\n\
+            name = \"apple\"\n\
+            [fruit.color]\n\
+            red = true\n\
+            [[fruit]]\n\
+            name = \"banana\"\n\
+            [fruit.color]\n\
+            red = false\n",
+        )
+        .unwrap();
+        let server = map.get_typed::<Table>("server").unwrap();
+        assert_eq!(server.get_typed::<String>("name").unwrap(), "main");
+        assert_eq!(server.get_typed::<Table>("http").unwrap().get_typed::<i64>("port"), Some(&80));
+        let fruit = map.get_typed::<Vec<Value>>("fruit").unwrap();
+        assert_eq!(fruit.len(), 2);
+        let banana = fruit[1].as_type::<Table>().unwrap();
+        assert_eq!(banana.get_typed::<String>("name").unwrap(), "banana");
+        assert_eq!(banana.get_typed::<Table>("color").unwrap().get_typed::<bool>("red"), Some(&false));
+    }
+
+    #[test]
+    fn errors() {
+        let error = |input| AnyMap::from_toml(input).unwrap_err();
+        assert_eq!(error("a = 1\na = 2").to_string(), "line 2: duplicate key `a`");
+        assert_eq!(error("[a]\n[a]").to_string(), "line 2: duplicate table `a`");
+        assert_eq!(error("a = 1\n[a.b]").to_string(), "line 2: key `a` is not a table");
+        assert_eq!(error("a = [1]\n[[a]]").to_string(), "line 2: key `a` is not an array of tables");
+        assert_eq!(error("a = 1979-05-27").message(), "dates and times are not supported");
+        assert_eq!(error("a = 1 2").message(), "expected end of line, found `2`");
+        assert_eq!(error("a = 012").message(), "invalid value `012`");
+        assert_eq!(error("a = 1__0").message(), "invalid value `1__0`");
+        assert_eq!(error("a = .5").message(), "invalid value `.5`");
+        assert_eq!(error("a = \"open").message(), "unterminated string");
+        assert_eq!(error("a = [1,\n2").line(), 2);
+        assert_eq!(error("a = \"\\q\"").message(), "invalid escape sequence");
+        assert_eq!(error("= 1").message(), "expected a key, found `=`");
+    }
+}
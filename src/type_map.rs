@@ -0,0 +1,184 @@
+use core::any::{Any, TypeId};
+
+use crate::{
+    hash::{hash_map, HashMap},
+    value::SLOT_INVARIANT,
+    Value,
+};
+
+/// A map holding at most one value of each type, keyed by the type itself.
+///
+/// ```
+/// use anymap::TypeMap;
+///
+/// let mut map = TypeMap::new();
+/// map.insert(8080u16);
+/// map.insert(String::from("localhost"));
+/// assert_eq!(map.get::<u16>(), Some(&8080));
+/// assert_eq!(map.get::<String>().unwrap(), "localhost");
+/// ```
+#[derive(Default)]
+pub struct TypeMap {
+    pub(crate) map: HashMap<TypeId, Value>,
+}
+
+impl TypeMap {
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        TypeMap {
+            map: HashMap::new(),
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        TypeMap {
+            map: HashMap::with_capacity(capacity),
+        }
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+
+    #[inline]
+    pub fn values(&self) -> hash_map::Values<'_, TypeId, Value> {
+        self.map.values()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+
+    #[inline]
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.map.get(&TypeId::of::<T>()).and_then(|v| v.as_type::<T>())
+    }
+
+    #[inline]
+    pub fn get_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.map.get_mut(&TypeId::of::<T>()).and_then(|v| v.as_type_mut::<T>())
+    }
+
+    #[inline]
+    pub fn contains<T: Any>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Inserts `value`, returning the previous value of the same type.
+    #[inline]
+    pub fn insert<T: Any>(&mut self, value: T) -> Option<T> {
+        self.map
+            .insert(TypeId::of::<T>(), Value::new(value))
+            .and_then(|v| v.downcast::<T>().ok())
+    }
+
+    /// Inserts a [`Value`] under the type it holds.
+    #[inline]
+    pub fn insert_val(&mut self, value: Value) -> Option<Value> {
+        self.map.insert(value.type_id(), value)
+    }
+
+    #[inline]
+    pub fn remove<T: Any>(&mut self) -> Option<T> {
+        self.map.remove(&TypeId::of::<T>()).and_then(|v| v.downcast::<T>().ok())
+    }
+
+    /// Returns the `T`, inserting `value` first if there is none.
+    #[inline]
+    pub fn get_or_insert<T: Any>(&mut self, value: T) -> &mut T {
+        self.get_or_insert_with(|| value)
+    }
+
+    #[inline]
+    pub fn get_or_insert_default<T: Any + Default>(&mut self) -> &mut T {
+        self.get_or_insert_with(T::default)
+    }
+
+    /// Moves all values of `other` into `self`, replacing values of the same
+    /// type.
+    #[inline]
+    pub fn extend(&mut self, other: TypeMap) {
+        self.map.extend(other.map)
+    }
+
+    /// Returns the `T`, inserting the result of `default` first if there is
+    /// none.
+    #[inline]
+    pub fn get_or_insert_with<T: Any, F: FnOnce() -> T>(&mut self, default: F) -> &mut T {
+        self.map
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Value::new(default()))
+            .as_type_mut::<T>()
+            .expect(SLOT_INVARIANT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_value_per_type() {
+        let mut map = TypeMap::new();
+        assert_eq!(map.insert(1u8), None);
+        assert_eq!(map.insert(2u8), Some(1));
+        map.insert("str");
+        assert_eq!(map.len(), 2);
+        *map.get_mut::<u8>().unwrap() += 1;
+        assert_eq!(map.get::<u8>(), Some(&3));
+        assert_eq!(map.remove::<u8>(), Some(3));
+        assert!(!map.contains::<u8>());
+        assert!(map.contains::<&str>());
+    }
+
+    #[test]
+    fn insert_val_and_get_or_insert_with() {
+        let mut map = TypeMap::new();
+        map.insert_val(Value::new(String::from("a")));
+        assert_eq!(map.get::<String>().unwrap(), "a");
+        map.get_or_insert_with(Vec::new).push(1);
+        map.get_or_insert_with(Vec::new).push(2);
+        assert_eq!(map.get::<Vec<i32>>().unwrap(), &[1, 2]);
+    }
+
+    #[test]
+    fn get_or_insert_and_extend() {
+        let mut map = TypeMap::new();
+        *map.get_or_insert(1u8) += 1;
+        *map.get_or_insert(5u8) += 1;
+        assert_eq!(map.get::<u8>(), Some(&3));
+        map.get_or_insert_default::<String>().push('a');
+
+        let mut other = TypeMap::new();
+        other.insert(9u8);
+        other.insert(1u16);
+        map.extend(other);
+        assert_eq!(map.get::<u8>(), Some(&9));
+        assert_eq!(map.len(), 3);
+    }
+
+    #[test]
+    fn values_as_values() {
+        let mut map = TypeMap::new();
+        assert!(map.get_or_insert_with(|| Value::new(1)).is::<i32>());
+        assert!(map.insert(Value::new("a")).unwrap().is::<i32>());
+        assert!(map.get::<Value>().unwrap().is::<&str>());
+        assert_eq!(map.get::<&str>(), None);
+        assert!(map.get_or_insert(Value::new(())).is::<&str>());
+    }
+}
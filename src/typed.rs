@@ -0,0 +1,366 @@
+//! Bulk operations over the values of one type.
+
+use alloc::vec::{self, Vec};
+use core::{
+    any::{Any, TypeId},
+    hash::{BuildHasher, Hash},
+};
+
+use crate::{hash::HashMap, AnyMap, HashBackend, MapBackend, TypeMismatchError, Value};
+
+/// Number of values of one type in a map, as reported by
+/// [`AnyMap::type_histogram`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeCount {
+    pub count: usize,
+    /// Name of the type, if recorded. Requires the `type-names` feature.
+    pub type_name: Option<&'static str>,
+}
+
+impl<K, B: MapBackend<K>> AnyMap<K, B> {
+    /// Returns the number of values that are a `T`.
+    #[inline]
+    pub fn count_of_type<T: Any>(&self) -> usize {
+        self.values().filter(|v| v.is::<T>()).count()
+    }
+
+    /// Returns the number of values of every stored type.
+    pub fn type_histogram(&self) -> HashMap<TypeId, TypeCount> {
+        let mut histogram = HashMap::new();
+        for value in self.values() {
+            histogram
+                .entry(value.type_id())
+                .or_insert_with(|| TypeCount {
+                    count: 0,
+                    #[cfg(feature = "type-names")]
+                    type_name: Some(value.type_name()),
+                    #[cfg(not(feature = "type-names"))]
+                    type_name: None,
+                })
+                .count += 1;
+        }
+        histogram
+    }
+
+    /// Returns the first entry of type `T` for which `f` returns `true`.
+    #[inline]
+    pub fn find_typed<T: Any, F>(&self, mut f: F) -> Option<(&K, &T)>
+    where
+        F: FnMut(&K, &T) -> bool,
+    {
+        self.iter_typed::<T>().find(|(k, v)| f(k, v))
+    }
+
+    /// Returns whether `f` returns `true` for any entry of type `T`.
+    #[inline]
+    pub fn any_typed<T: Any, F>(&self, mut f: F) -> bool
+    where
+        F: FnMut(&K, &T) -> bool,
+    {
+        self.iter_typed::<T>().any(|(k, v)| f(k, v))
+    }
+
+    /// Returns whether `f` returns `true` for every entry of type `T`, which
+    /// is the case if there are none.
+    #[inline]
+    pub fn all_typed<T: Any, F>(&self, mut f: F) -> bool
+    where
+        F: FnMut(&K, &T) -> bool,
+    {
+        self.iter_typed::<T>().all(|(k, v)| f(k, v))
+    }
+
+    /// Retains only the entries of type `T` for which `f` returns `true`.
+    /// Entries of any other type are kept.
+    pub fn retain_typed<T: Any, F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut T) -> bool,
+    {
+        self.retain(|k, v| v.as_type_mut::<T>().is_none_or(|v| f(k, v)))
+    }
+
+    /// Groups the entries by the type of their value.
+    pub fn group_by_type(&self) -> HashMap<TypeId, Vec<(&K, &Value)>> {
+        let mut groups: HashMap<TypeId, Vec<_>> = HashMap::new();
+        for (key, value) in self.iter() {
+            groups.entry(value.type_id()).or_default().push((key, value));
+        }
+        groups
+    }
+
+    /// Removes every entry whose value is a `T` and returns them.
+    pub fn remove_type<T: Any>(&mut self) -> Vec<(K, T)> {
+        self.extract_if(|_, v| v.is::<T>())
+            .filter_map(|(k, v)| Some((k, v.downcast::<T>().ok()?)))
+            .collect()
+    }
+}
+
+impl<K, B: MapBackend<K>> AnyMap<K, B> {
+    /// Replaces every value of type `T` with the `U` returned by `f`, keeping
+    /// the keys in place.
+    ///
+    /// If `f` panics, the entry it was called with is removed, and entries
+    /// not yet visited keep their `T`.
+    pub fn transform_typed<T: Any, U: Any, F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, T) -> U,
+    {
+        let pending = self.remove_type::<T>().into_iter();
+        let mut guard = Restore { map: self, pending };
+        for (key, old) in guard.pending.by_ref() {
+            let new = f(&key, old);
+            guard.map.insert(key, new);
+        }
+    }
+}
+
+/// Puts the entries not yet transformed back into the map, also when
+/// unwinding.
+struct Restore<'a, K, B: MapBackend<K>, T: Any> {
+    map: &'a mut AnyMap<K, B>,
+    pending: vec::IntoIter<(K, T)>,
+}
+
+impl<K, B: MapBackend<K>, T: Any> Drop for Restore<'_, K, B, T> {
+    fn drop(&mut self) {
+        for (key, value) in self.pending.by_ref() {
+            self.map.insert(key, value);
+        }
+    }
+}
+
+impl<K: Eq + Hash, S: BuildHasher + Clone> AnyMap<K, HashBackend<K, S>> {
+    /// Splits the map into the entries whose value is a `T`, as a typed map,
+    /// and everything else.
+    pub fn partition_by_type<T: Any>(self) -> (HashMap<K, T, S>, Self) {
+        let mut typed = HashMap::with_hasher(self.map.hasher().clone());
+        let mut rest = AnyMap::with_hasher(self.map.hasher().clone());
+        for (key, value) in self {
+            match value.downcast::<T>() {
+                Ok(value) => {
+                    typed.insert(key, value);
+                }
+                Err(value) => {
+                    rest.insert_val(key, value);
+                }
+            }
+        }
+        (typed, rest)
+    }
+
+    /// Converts a map whose values are all a `T` into a typed map.
+    ///
+    /// If any value has another type, the map is returned unchanged along
+    /// with the offending key.
+    #[allow(clippy::type_complexity)]
+    pub fn into_typed_hashmap<T: Any>(self) -> Result<HashMap<K, T, S>, (Self, K, TypeMismatchError)>
+    where
+        K: Clone,
+    {
+        if let Some((key, value)) = self.iter().find(|(_, v)| !v.is::<T>()) {
+            let key = key.clone();
+            let error = TypeMismatchError::new::<T>(value);
+            return Err((self, key, error));
+        }
+        let mut typed = HashMap::with_capacity_and_hasher(self.len(), self.map.hasher().clone());
+        typed.extend(self.into_iter().filter_map(|(k, v)| Some((k, v.downcast::<T>().ok()?))));
+        Ok(typed)
+    }
+}
+
+/// Lifts a typed map into an `AnyMap`, e.g. when a subsystem starts needing
+/// values of other types alongside.
+impl<K: Eq + Hash, T: Any, S: BuildHasher + Default> From<HashMap<K, T, S>> for AnyMap<K, HashBackend<K, S>> {
+    #[inline]
+    fn from(map: HashMap<K, T, S>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+/// Same as [`AnyMap::into_typed_hashmap`].
+impl<K: Clone + Eq + Hash, T: Any, S: BuildHasher + Clone> TryFrom<AnyMap<K, HashBackend<K, S>>> for HashMap<K, T, S> {
+    type Error = (AnyMap<K, HashBackend<K, S>>, K, TypeMismatchError);
+
+    #[inline]
+    fn try_from(map: AnyMap<K, HashBackend<K, S>>) -> Result<Self, Self::Error> {
+        map.into_typed_hashmap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_of_type() {
+        let mut map = AnyMap::new();
+        map.insert("a", 1);
+        map.insert("b", 2);
+        map.insert("c", "c");
+        assert_eq!(map.count_of_type::<i32>(), 2);
+        assert_eq!(map.count_of_type::<&str>(), 1);
+        assert_eq!(map.count_of_type::<u8>(), 0);
+    }
+
+    #[test]
+    fn partition_by_type() {
+        let mut map = AnyMap::new();
+        map.insert("a", 1);
+        map.insert("b", "b");
+        map.insert("c", 3);
+        let (ints, rest) = map.partition_by_type::<i32>();
+        assert_eq!(ints, HashMap::from_iter([("a", 1), ("c", 3)]));
+        assert_eq!(rest.len(), 1);
+        assert!(rest.get("b").unwrap().is::<&str>());
+    }
+
+    #[test]
+    fn into_typed_hashmap() {
+        let mut map = AnyMap::new();
+        map.insert("a", 1);
+        map.insert("b", 2);
+        let typed = map.into_typed_hashmap::<i32>().ok().unwrap();
+        assert_eq!(typed, HashMap::from_iter([("a", 1), ("b", 2)]));
+
+        let mut map = AnyMap::new();
+        map.insert("a", 1);
+        map.insert("b", "b");
+        let Err((map, key, error)) = map.into_typed_hashmap::<i32>() else {
+            panic!("expected a type mismatch");
+        };
+        assert_eq!(key, "b");
+        assert_eq!(error.expected(), "i32");
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn transform_typed() {
+        struct UserV1 {
+            name: &'static str,
+        }
+        struct UserV2 {
+            name: String,
+            id: usize,
+        }
+
+        let mut map = AnyMap::new();
+        map.insert("alice", UserV1 { name: "Alice" });
+        map.insert("bob", UserV1 { name: "Bob" });
+        map.insert("count", 2);
+        map.transform_typed(|k: &&str, v: UserV1| UserV2 {
+            name: v.name.to_owned(),
+            id: k.len(),
+        });
+        assert_eq!(map.count_of_type::<UserV1>(), 0);
+        let bob = map.get_typed::<UserV2>("bob").unwrap();
+        assert_eq!((bob.name.as_str(), bob.id), ("Bob", 3));
+        assert_eq!(map.get_typed::<i32>("count"), Some(&2));
+    }
+
+    #[test]
+    fn transform_typed_panic() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let mut map = AnyMap::new();
+        map.insert("a", 1);
+        map.insert("b", 2);
+        map.insert("c", 3);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut calls = 0;
+            map.transform_typed(|_, v: i32| {
+                calls += 1;
+                assert!(calls < 2, "second call");
+                v.to_string()
+            });
+        }));
+        assert!(result.is_err());
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.count_of_type::<String>(), 1);
+        assert_eq!(map.count_of_type::<i32>(), 1);
+        assert_eq!(map.count_of_type::<()>(), 0);
+    }
+
+    #[test]
+    fn remove_type() {
+        let mut map = AnyMap::new();
+        map.insert("a", 1);
+        map.insert("b", "b");
+        map.insert("c", 3);
+        let mut removed = map.remove_type::<i32>();
+        removed.sort();
+        assert_eq!(removed, [("a", 1), ("c", 3)]);
+        assert_eq!(map.len(), 1);
+        assert!(map.remove_type::<i32>().is_empty());
+    }
+
+    #[test]
+    fn find_any_all_typed() {
+        let mut map = AnyMap::new();
+        map.insert("a", 1);
+        map.insert("b", "big");
+        map.insert("c", 30);
+        assert_eq!(map.find_typed::<i32, _>(|_, v| *v > 10), Some((&"c", &30)));
+        assert_eq!(map.find_typed::<i32, _>(|k, _| *k == "b"), None);
+        assert!(map.any_typed::<&str, _>(|_, v| v.starts_with('b')));
+        assert!(!map.all_typed::<i32, _>(|_, v| *v > 10));
+        assert!(map.all_typed::<i32, _>(|_, v| *v > 0));
+        assert!(map.all_typed::<u8, _>(|_, _| false));
+    }
+
+    #[test]
+    fn retain_typed() {
+        let mut map = AnyMap::new();
+        map.insert("a", 1);
+        map.insert("b", "b");
+        map.insert("c", 3);
+        map.retain_typed::<i32, _>(|_, v| {
+            *v += 1;
+            *v > 2
+        });
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get_typed::<i32>("c"), Some(&4));
+        assert!(map.contains_key("b"));
+    }
+
+    #[test]
+    fn group_by_type() {
+        let mut map = AnyMap::new();
+        map.insert("a", 1);
+        map.insert("b", "b");
+        map.insert("c", 3);
+        let groups = map.group_by_type();
+        assert_eq!(groups.len(), 2);
+        let mut ints: Vec<_> = groups[&TypeId::of::<i32>()].iter().map(|(k, _)| **k).collect();
+        ints.sort();
+        assert_eq!(ints, ["a", "c"]);
+        assert_eq!(groups[&TypeId::of::<&str>()].len(), 1);
+    }
+
+    #[test]
+    fn type_histogram() {
+        let mut map = AnyMap::new();
+        map.insert("a", 1);
+        map.insert("b", 2);
+        map.insert("c", "c");
+        let histogram = map.type_histogram();
+        assert_eq!(histogram.len(), 2);
+        let ints = histogram[&TypeId::of::<i32>()];
+        assert_eq!(ints.count, 2);
+        #[cfg(feature = "type-names")]
+        assert_eq!(ints.type_name, Some("i32"));
+        assert_eq!(histogram[&TypeId::of::<&str>()].count, 1);
+    }
+
+    #[test]
+    fn hashmap_conversions() {
+        let map = AnyMap::from(HashMap::from([("a", 1), ("b", 2)]));
+        assert_eq!(map.get_typed::<i32>("b"), Some(&2));
+
+        let typed: HashMap<&str, i32> = map.try_into().unwrap();
+        assert_eq!(typed, HashMap::from([("a", 1), ("b", 2)]));
+
+        let map = AnyMap::from(HashMap::from([("a", "a")]));
+        assert!(HashMap::<&str, i32>::try_from(map).is_err());
+    }
+}
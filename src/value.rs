@@ -0,0 +1,975 @@
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::{
+    any::{Any, TypeId},
+    cell::UnsafeCell,
+    fmt,
+    marker::PhantomData,
+    mem::{self, ManuallyDrop, MaybeUninit},
+    ptr,
+};
+#[cfg(feature = "std")]
+use core::hash::{Hash, Hasher};
+
+#[cfg(feature = "std")]
+use crate::registry;
+use crate::{number, Number};
+
+/// Panic message for maps keyed by `TypeId`. Their slots are only ever filled
+/// through a typed API, so the value in the slot for `T` is always a `T`.
+pub(crate) const SLOT_INVARIANT: &str = "the slot of `T` holds a `T`";
+
+/// Moves `value` out as a `T` if that is its type.
+fn cast<T: Any, U: Any>(value: U) -> Result<T, U> {
+    let mut slot = Some(value);
+    match (&mut slot as &mut dyn Any).downcast_mut::<Option<T>>() {
+        Some(value) => Ok(value.take().unwrap()),
+        None => Err(slot.unwrap()),
+    }
+}
+
+/// Inline storage of a [`Repr`], two words wide and aligned for `u128`.
+///
+/// Inline values may have interior mutability, like `Cell` or atomics, so the
+/// bytes live in an `UnsafeCell` and can be written through shared borrows.
+#[repr(C, align(16))]
+struct Storage(UnsafeCell<MaybeUninit<[usize; 2]>>);
+
+impl Storage {
+    #[inline]
+    fn get(&self) -> *mut Storage {
+        self.0.get().cast()
+    }
+}
+
+const _: () = assert!(mem::size_of::<Box<dyn Any>>() <= mem::size_of::<Storage>());
+
+/// Operations on the value held in a [`Storage`], specialized for its type.
+struct VTable {
+    as_any: unsafe fn(*const Storage) -> *const dyn Any,
+    as_any_mut: unsafe fn(*mut Storage) -> *mut dyn Any,
+    into_box: unsafe fn(*const Storage) -> Box<dyn Any>,
+    drop: unsafe fn(*mut Storage),
+    /// Whether the storage holds the value itself rather than a box of it.
+    inline: bool,
+}
+
+unsafe fn as_any<T: Any>(data: *const Storage) -> *const dyn Any {
+    data.cast::<T>()
+}
+
+unsafe fn as_any_mut<T: Any>(data: *mut Storage) -> *mut dyn Any {
+    data.cast::<T>()
+}
+
+unsafe fn into_box<T: Any>(data: *const Storage) -> Box<dyn Any> {
+    Box::new(data.cast::<T>().read())
+}
+
+unsafe fn drop_in_place<T>(data: *mut Storage) {
+    data.cast::<T>().drop_in_place()
+}
+
+struct Inline<T>(PhantomData<T>);
+
+impl<T: Any> Inline<T> {
+    const FITS: bool = mem::size_of::<T>() <= mem::size_of::<Storage>()
+        && mem::align_of::<T>() <= mem::align_of::<Storage>();
+
+    const VTABLE: VTable = VTable {
+        as_any: as_any::<T>,
+        as_any_mut: as_any_mut::<T>,
+        into_box: into_box::<T>,
+        drop: drop_in_place::<T>,
+        inline: true,
+    };
+}
+
+const BOXED: VTable = VTable {
+    as_any: |data| unsafe { &**data.cast::<Box<dyn Any>>() },
+    as_any_mut: |data| unsafe { &mut **data.cast::<Box<dyn Any>>() },
+    into_box: |data| unsafe { data.cast::<Box<dyn Any>>().read() },
+    drop: drop_in_place::<Box<dyn Any>>,
+    inline: false,
+};
+
+/// Storage of a [`Value`]. Values of at most two words are kept inline so
+/// they don't need an allocation; everything else is boxed.
+///
+/// Any Rust value may be moved by copying its bytes, so inline values only
+/// need their type's drop glue, which `vtable` provides.
+pub(crate) struct Repr {
+    data: Storage,
+    vtable: &'static VTable,
+    // Stored values need not be `Send` or `Sync`.
+    _marker: PhantomData<Box<dyn Any>>,
+}
+
+impl Repr {
+    /// # Safety
+    ///
+    /// `T` must fit in [`Storage`] and `vtable` must handle values of `T`.
+    unsafe fn store<T>(value: T, vtable: &'static VTable) -> Self {
+        let data = Storage(UnsafeCell::new(MaybeUninit::uninit()));
+        ptr::write(data.get().cast::<T>(), value);
+        Repr {
+            data,
+            vtable,
+            _marker: PhantomData,
+        }
+    }
+
+    fn new<T: Any>(value: T) -> Self {
+        if Inline::<T>::FITS {
+            unsafe { Repr::store(value, &Inline::<T>::VTABLE) }
+        } else {
+            unsafe { Repr::store::<Box<dyn Any>>(Box::new(value), &BOXED) }
+        }
+    }
+
+    /// Primitives, as produced by the registry's parsers and clone functions,
+    /// are moved out of their box again.
+    fn from_box(boxed: Box<dyn Any>) -> Self {
+        macro_rules! unbox {
+            ($boxed:ident: $($ty:ty),*) => {$(
+                let $boxed = match $boxed.downcast::<$ty>() {
+                    Ok(value) => return Repr::new(*value),
+                    Err(boxed) => boxed,
+                };
+            )*};
+        }
+        unbox!(
+            boxed: (), bool, char,
+            u8, u16, u32, u64, u128, usize,
+            i8, i16, i32, i64, i128, isize,
+            f32, f64, &'static str
+        );
+        unsafe { Repr::store(boxed, &BOXED) }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        unsafe { &*(self.vtable.as_any)(self.data.get()) }
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        unsafe { &mut *(self.vtable.as_any_mut)(&mut self.data) }
+    }
+
+    fn into_box(self) -> Box<dyn Any> {
+        let this = ManuallyDrop::new(self);
+        unsafe { (this.vtable.into_box)(this.data.get()) }
+    }
+
+    fn downcast<T: Any>(self) -> Result<T, Self> {
+        if !self.as_any().is::<T>() {
+            return Err(self);
+        }
+        if self.vtable.inline {
+            let this = ManuallyDrop::new(self);
+            return Ok(unsafe { this.data.get().cast::<T>().read() });
+        }
+        match self.into_box().downcast::<T>() {
+            Ok(value) => Ok(*value),
+            Err(_) => unreachable!(),
+        }
+    }
+}
+
+impl Drop for Repr {
+    fn drop(&mut self) {
+        unsafe { (self.vtable.drop)(&mut self.data) }
+    }
+}
+
+pub struct Value {
+    repr: Repr,
+    #[cfg(feature = "type-names")]
+    type_name: &'static str,
+}
+
+impl Value {
+    /// Wraps `value`, or returns it unchanged if it already is a `Value`.
+    ///
+    /// Values never nest, so every path storing a value in a map stores the
+    /// same thing for a `Value` as for the type it holds. In turn, every
+    /// `Value` counts as a `Value` for [`is`](Self::is),
+    /// [`as_type`](Self::as_type) and [`downcast`](Self::downcast), so
+    /// generic code storing a `T` always finds a `T` again.
+    pub fn new<T: Any>(value: T) -> Self {
+        match cast::<Value, T>(value) {
+            Ok(value) => value,
+            Err(value) => Self {
+                repr: Repr::new(value),
+                #[cfg(feature = "type-names")]
+                type_name: core::any::type_name::<T>(),
+            },
+        }
+    }
+
+    /// Wraps an already boxed value without boxing it again.
+    ///
+    /// The concrete type is not known statically here, so
+    /// [`type_name`](Self::type_name) reports `dyn Any` unless the box holds
+    /// a `Value`, which is returned unchanged.
+    pub fn from_box(inner: Box<dyn Any>) -> Self {
+        match inner.downcast::<Value>() {
+            Ok(value) => *value,
+            Err(inner) => Self {
+                repr: Repr::from_box(inner),
+                #[cfg(feature = "type-names")]
+                type_name: "dyn Any",
+            },
+        }
+    }
+
+    pub fn as_type<T: Any>(&self) -> Option<&T> {
+        if let Some(this) = (self as &dyn Any).downcast_ref::<T>() {
+            return Some(this);
+        }
+        self.repr.as_any().downcast_ref::<T>()
+    }
+
+    pub fn as_type_mut<T: Any>(&mut self) -> Option<&mut T> {
+        if TypeId::of::<T>() == TypeId::of::<Value>() {
+            return (self as &mut dyn Any).downcast_mut::<T>();
+        }
+        self.repr.as_any_mut().downcast_mut::<T>()
+    }
+
+    pub fn is<T: Any>(&self) -> bool {
+        TypeId::of::<T>() == TypeId::of::<Value>() || self.repr.as_any().is::<T>()
+    }
+
+    /// Returns the [`TypeId`] of the stored value, not of `Value` itself.
+    pub fn type_id(&self) -> TypeId {
+        self.repr.as_any().type_id()
+    }
+
+    /// Returns the name of the stored type, as recorded by
+    /// [`std::any::type_name`] when the value was created.
+    #[cfg(feature = "type-names")]
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Moves the stored value out if it is a `T`, leaving `()` in its place.
+    pub fn take<T: Any>(&mut self) -> Option<T> {
+        if !self.is::<T>() {
+            return None;
+        }
+        self.replace(()).downcast::<T>().ok()
+    }
+
+    /// Stores `value` in place of the current contents, which may be of a
+    /// different type, and returns the previous contents.
+    pub fn replace<T: Any>(&mut self, value: T) -> Value {
+        mem::replace(self, Value::new(value))
+    }
+
+    /// Calls `f` with the stored value if it is a `T`, returning whether it
+    /// was called.
+    pub fn modify<T: Any, F: FnOnce(&mut T)>(&mut self, f: F) -> bool {
+        match self.as_type_mut::<T>() {
+            Some(value) => {
+                f(value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Converts a stored `T` into a `U`, returning the original `Value` if it
+    /// is not a `T`.
+    pub fn map<T: Any, U: Any, F: FnOnce(T) -> U>(self, f: F) -> Result<Value, Value> {
+        self.downcast::<T>().map(|value| Value::new(f(value)))
+    }
+
+    /// Clones the stored value if its type was registered with
+    /// [`registry::register_clone`].
+    #[cfg(feature = "std")]
+    pub fn try_clone(&self) -> Option<Value> {
+        let clone = registry::lookup(self.type_id())?.clone?;
+        Some(Value {
+            repr: Repr::from_box(clone(self.repr.as_any())),
+            #[cfg(feature = "type-names")]
+            type_name: self.type_name,
+        })
+    }
+
+    /// Compares two values using the comparator registered with
+    /// [`registry::register_eq`]. Returns `None` if the values have different
+    /// types or their type has no registered comparator.
+    #[cfg(feature = "std")]
+    pub fn try_eq(&self, other: &Value) -> Option<bool> {
+        if self.type_id() != other.type_id() {
+            return None;
+        }
+        let eq = registry::lookup(self.type_id())?.eq?;
+        Some(eq(self.repr.as_any(), other.repr.as_any()))
+    }
+
+    /// Feeds the stored value into `state` using the function registered with
+    /// [`registry::register_hash`]. Returns `false`, leaving `state`
+    /// untouched, if the type has no registered hash function.
+    #[cfg(feature = "std")]
+    pub fn try_hash<H: Hasher>(&self, state: &mut H) -> bool {
+        match registry::lookup(self.type_id()).and_then(|info| info.hash) {
+            Some(hash) => {
+                hash(self.repr.as_any(), state);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns a [`Display`](fmt::Display) adapter for the stored value if its
+    /// type was registered with [`registry::register_display`].
+    #[cfg(feature = "std")]
+    pub fn display(&self) -> Option<impl fmt::Display + '_> {
+        let display = registry::lookup(self.type_id())?.display?;
+        Some(DisplayWith(self.repr.as_any(), display))
+    }
+
+    #[cfg(feature = "serde")]
+    #[inline]
+    pub(crate) fn as_any(&self) -> &dyn Any {
+        self.repr.as_any()
+    }
+
+    /// Name of the stored type for diagnostics, or `"?"` if type names are
+    /// not recorded.
+    #[cfg(feature = "std")]
+    pub(crate) fn describe_type(&self) -> &'static str {
+        #[cfg(feature = "type-names")]
+        return self.type_name;
+        #[cfg(not(feature = "type-names"))]
+        return "?";
+    }
+
+    /// Borrows a value stored as `Box<Dyn>`, e.g. through
+    /// [`AnyMap::insert_dyn`](crate::AnyMap::insert_dyn).
+    pub fn as_dyn<Dyn: ?Sized + 'static>(&self) -> Option<&Dyn> {
+        self.as_type::<Box<Dyn>>().map(|boxed| &**boxed)
+    }
+
+    pub fn as_dyn_mut<Dyn: ?Sized + 'static>(&mut self) -> Option<&mut Dyn> {
+        self.as_type_mut::<Box<Dyn>>().map(|boxed| &mut **boxed)
+    }
+
+    /// Borrows the stored value as the trait object `Dyn`, if its type was
+    /// registered with [`registry::register_trait`] for that trait.
+    #[cfg(feature = "std")]
+    pub fn as_trait<Dyn: ?Sized + 'static>(&self) -> Option<&Dyn> {
+        (registry::lookup_caster::<Dyn>(self.type_id())?.cast_ref)(self.repr.as_any())
+    }
+
+    #[cfg(feature = "std")]
+    pub fn as_trait_mut<Dyn: ?Sized + 'static>(&mut self) -> Option<&mut Dyn> {
+        (registry::lookup_caster::<Dyn>(Value::type_id(self))?.cast_mut)(self.repr.as_any_mut())
+    }
+
+    /// Converts a stored primitive number of any type into a `T`. See
+    /// [`Number`] for the conversion rules.
+    pub fn coerce<T: Number>(&self) -> Option<T> {
+        number::coerce::<T>(self.repr.as_any())
+    }
+
+    /// Returns a stored primitive number of any type as an `f64`.
+    pub fn as_number(&self) -> Option<f64> {
+        self.coerce::<f64>()
+    }
+
+    /// Starts matching the stored value against several candidate types.
+    ///
+    /// ```
+    /// use anymap::Value;
+    ///
+    /// let value = Value::new(String::from("hi"));
+    /// let len = value
+    ///     .downcast_switch()
+    ///     .case::<i32>(|i| *i as usize)
+    ///     .case::<String>(|s| s.len())
+    ///     .run();
+    /// assert_eq!(len, Some(2));
+    /// ```
+    pub fn downcast_switch<R>(&self) -> DowncastSwitch<'_, R> {
+        DowncastSwitch {
+            value: self,
+            result: None,
+        }
+    }
+
+    /// Returns the stored value as a box, allocating one for primitives that
+    /// were stored inline.
+    pub fn into_inner(self) -> Box<dyn Any> {
+        self.repr.into_box()
+    }
+
+    /// Attempts to move the stored value out as a `T`, returning the original
+    /// `Value` if it has a different type.
+    pub fn downcast<T: Any>(self) -> Result<T, Value> {
+        let value = match cast::<T, Value>(self) {
+            Ok(value) => return Ok(value),
+            Err(value) => value,
+        };
+        #[cfg(feature = "type-names")]
+        let type_name = value.type_name;
+        value.repr.downcast::<T>().map_err(|repr| Value {
+            repr,
+            #[cfg(feature = "type-names")]
+            type_name,
+        })
+    }
+
+    pub fn downcast_box<T: Any>(self) -> Result<Box<T>, Value> {
+        self.downcast::<T>().map(Box::new)
+    }
+}
+
+/// Builder returned by [`Value::downcast_switch`]. The first case whose type
+/// matches the stored value is run; later cases are skipped.
+#[must_use = "call `run` or `default` to get the result"]
+pub struct DowncastSwitch<'a, R> {
+    value: &'a Value,
+    result: Option<R>,
+}
+
+impl<'a, R> DowncastSwitch<'a, R> {
+    pub fn case<T: Any>(mut self, f: impl FnOnce(&'a T) -> R) -> Self {
+        if self.result.is_none() {
+            self.result = self.value.as_type::<T>().map(f);
+        }
+        self
+    }
+
+    /// Returns the result of the matching case, or `None` if no case matched.
+    pub fn run(self) -> Option<R> {
+        self.result
+    }
+
+    /// Returns the result of the matching case, or calls `f` with the value
+    /// if no case matched.
+    pub fn default(self, f: impl FnOnce(&'a Value) -> R) -> R {
+        match self.result {
+            Some(result) => result,
+            None => f(self.value),
+        }
+    }
+}
+
+impl From<Box<dyn Any>> for Value {
+    #[inline]
+    fn from(inner: Box<dyn Any>) -> Self {
+        Value::from_box(inner)
+    }
+}
+
+// A blanket `impl<T: Any> From<T>` would overlap with the reflexive
+// `From<Value> for Value`, so common types are covered explicitly and anything
+// else goes through `Value::new`.
+macro_rules! impl_from {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl From<$ty> for Value {
+                #[inline]
+                fn from(value: $ty) -> Self {
+                    Value::new(value)
+                }
+            }
+        )*
+    };
+}
+
+impl_from! {
+    (), bool, char,
+    u8, u16, u32, u64, u128, usize,
+    i8, i16, i32, i64, i128, isize,
+    f32, f64,
+    String, &'static str,
+}
+
+impl<T: Any> From<Vec<T>> for Value {
+    #[inline]
+    fn from(value: Vec<T>) -> Self {
+        Value::new(value)
+    }
+}
+
+impl<T: Any> From<Option<T>> for Value {
+    #[inline]
+    fn from(value: Option<T>) -> Self {
+        Value::new(value)
+    }
+}
+
+/// Values are equal if they hold the same type, that type was registered with
+/// [`registry::register_eq`], and the registered comparator says so.
+#[cfg(feature = "std")]
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        self.try_eq(other).unwrap_or(false)
+    }
+}
+
+/// Hashes the stored type and, if registered with
+/// [`registry::register_hash`], the stored value.
+#[cfg(feature = "std")]
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.type_id().hash(state);
+        self.try_hash(state);
+    }
+}
+
+/// A [`Value`] usable as a set element or map key.
+///
+/// Plain values are only [`PartialEq`]: values without a registered
+/// comparator, and floats holding NaN, are not equal to themselves. This
+/// wrapper only holds values whose type was registered with
+/// [`registry::register_hash`], which requires `Eq`, so it can implement
+/// [`Eq`] soundly. Floats are rejected even if registered.
+///
+/// ```
+/// use std::collections::HashSet;
+///
+/// use anymap::{HashableValue, Value};
+///
+/// let set: HashSet<HashableValue> = [Value::new(1), Value::new(1), Value::new("a")]
+///     .into_iter()
+///     .map(|value| HashableValue::new(value).unwrap())
+///     .collect();
+/// assert_eq!(set.len(), 2);
+/// assert!(HashableValue::new(Value::new(f64::NAN)).is_err());
+/// ```
+#[cfg(feature = "std")]
+pub struct HashableValue(Value);
+
+#[cfg(feature = "std")]
+impl HashableValue {
+    /// Wraps `value`, or gives it back if its type has no registered hash
+    /// function or is a float.
+    pub fn new(value: Value) -> Result<Self, Value> {
+        let id = value.type_id();
+        if id == TypeId::of::<f32>() || id == TypeId::of::<f64>() {
+            return Err(value);
+        }
+        match registry::lookup(id) {
+            Some(info) if info.hash.is_some() && info.eq.is_some() => Ok(HashableValue(value)),
+            _ => Err(value),
+        }
+    }
+
+    #[inline]
+    pub fn get(&self) -> &Value {
+        &self.0
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> Value {
+        self.0
+    }
+}
+
+#[cfg(feature = "std")]
+impl PartialEq for HashableValue {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+#[cfg(feature = "std")]
+impl Eq for HashableValue {}
+
+#[cfg(feature = "std")]
+impl Hash for HashableValue {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Debug for HashableValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("HashableValue").field(&self.0).finish()
+    }
+}
+
+#[cfg(feature = "std")]
+struct DebugWith<'a>(&'a dyn Any, registry::DebugFn);
+
+#[cfg(feature = "std")]
+impl fmt::Debug for DebugWith<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (self.1)(self.0, f)
+    }
+}
+
+#[cfg(feature = "std")]
+struct DisplayWith<'a>(&'a dyn Any, registry::DisplayFn);
+
+#[cfg(feature = "std")]
+impl fmt::Display for DisplayWith<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (self.1)(self.0, f)
+    }
+}
+
+/// Shows the stored value if its type was registered with
+/// [`registry::register_debug`].
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut f = f.debug_struct("Value");
+        #[cfg(feature = "type-names")]
+        f.field("type", &self.type_name);
+        #[cfg(feature = "std")]
+        if let Some(debug) = registry::lookup(self.type_id()).and_then(|info| info.debug) {
+            return f.field("value", &DebugWith(self.repr.as_any(), debug)).finish();
+        }
+        f.finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn value_stores() {
+        let val = Value::new(1);
+        assert!(val.is::<i32>());
+        assert!(!val.is::<String>());
+        assert_eq!(val.as_type::<i32>().unwrap(), &1);
+    }
+
+    #[test]
+    fn value_stores_any() {
+        let val = Value::new(1);
+        let val2 = Value::new("hello");
+        assert!(val.is::<i32>());
+        assert!(val2.is::<&str>());
+        assert_eq!(val.as_type::<i32>().unwrap(), &1);
+        assert_eq!(val2.as_type::<&str>().unwrap(), &"hello");
+    }
+
+    #[test]
+    fn value_type_id() {
+        assert_eq!(Value::new(1).type_id(), TypeId::of::<i32>());
+        assert_ne!(Value::new("1").type_id(), TypeId::of::<i32>());
+    }
+
+    #[test]
+    fn value_does_not_nest() {
+        let mut val = Value::new(Value::new(1));
+        assert!(val.is::<i32>());
+        assert!(val.is::<Value>());
+        assert!(val.as_type::<Value>().unwrap().is::<i32>());
+        *val.as_type_mut::<Value>().unwrap() = Value::new("a");
+        assert!(val.is::<&str>());
+        assert!(val.downcast::<Value>().unwrap().is::<&str>());
+    }
+
+    #[test]
+    fn value_downcast() {
+        let val = Value::new(String::from("hello"));
+        let val = val.downcast::<i32>().unwrap_err();
+        assert_eq!(val.downcast::<String>().unwrap(), "hello");
+        assert_eq!(*Value::new(7u64).downcast_box::<u64>().unwrap(), 7);
+    }
+
+    #[test]
+    fn value_from_box() {
+        let boxed: Box<dyn Any> = Box::new(5u16);
+        let val = Value::from_box(boxed);
+        assert!(val.is::<u16>());
+        assert!(Value::from_box(Box::new(Value::new(1))).is::<i32>());
+    }
+
+    #[test]
+    fn value_from() {
+        let val: Value = 1u8.into();
+        assert!(val.is::<u8>());
+        let val: Value = "str".into();
+        assert!(val.is::<&str>());
+        let val: Value = vec![1.0f32].into();
+        assert!(val.is::<Vec<f32>>());
+        let boxed: Box<dyn Any> = Box::new('c');
+        let val: Value = boxed.into();
+        assert!(val.is::<char>());
+    }
+
+    #[test]
+    fn value_inline_primitives() {
+        let mut val = Value::new(1.5f64);
+        assert!(val.repr.vtable.inline);
+        *val.as_type_mut::<f64>().unwrap() += 1.0;
+        assert_eq!(val.as_type::<f64>(), Some(&2.5));
+        assert!(!val.is::<f32>());
+        assert_eq!(val.downcast::<f64>().unwrap(), 2.5);
+
+        let val = Value::from_box(Box::new(7u8));
+        assert!(val.repr.vtable.inline);
+        assert_eq!(*val.into_inner().downcast::<u8>().unwrap(), 7);
+
+        let val = Value::new(String::from("boxed"));
+        assert!(!val.repr.vtable.inline);
+        let val = val.downcast::<i32>().unwrap_err();
+        assert_eq!(val.downcast_box::<String>().unwrap().as_str(), "boxed");
+    }
+
+    #[test]
+    fn value_inline_drop() {
+        use std::{cell::Cell, rc::Rc};
+
+        struct Guard(Rc<Cell<u32>>);
+        impl Drop for Guard {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let drops = Rc::new(Cell::new(0));
+        let val = Value::new(Guard(drops.clone()));
+        assert!(val.repr.vtable.inline);
+        drop(val);
+        assert_eq!(drops.get(), 1);
+
+        let val = Value::new((Guard(drops.clone()), 1u8));
+        let guard = val.downcast::<(Guard, u8)>().unwrap_or_else(|_| unreachable!());
+        assert_eq!(drops.get(), 1);
+        drop(guard);
+        assert_eq!(drops.get(), 2);
+
+        let mut val = Value::new(Guard(drops.clone()));
+        val.replace([0u64; 4]);
+        assert_eq!(drops.get(), 3);
+        assert!(!val.repr.vtable.inline);
+        assert_eq!(val.into_inner().downcast::<[u64; 4]>().unwrap()[3], 0);
+    }
+
+    #[test]
+    fn value_inline_interior_mutability() {
+        use std::{
+            cell::{Cell, RefCell},
+            sync::atomic::{AtomicUsize, Ordering},
+        };
+
+        let val = Value::new(Cell::new(1u32));
+        assert!(val.repr.vtable.inline);
+        val.as_type::<Cell<u32>>().unwrap().set(2);
+        assert_eq!(val.as_type::<Cell<u32>>().unwrap().get(), 2);
+
+        let val = Value::new(AtomicUsize::new(0));
+        assert!(val.repr.vtable.inline);
+        let counter = val.as_type::<AtomicUsize>().unwrap();
+        counter.fetch_add(1, Ordering::Relaxed);
+        counter.fetch_add(1, Ordering::Relaxed);
+        assert_eq!(val.downcast::<AtomicUsize>().unwrap().into_inner(), 2);
+
+        let val = Value::new(RefCell::new(1u8));
+        *val.as_type::<RefCell<u8>>().unwrap().borrow_mut() += 1;
+        assert_eq!(val.downcast::<RefCell<u8>>().unwrap().into_inner(), 2);
+    }
+
+    #[test]
+    fn value_downcast_switch() {
+        fn describe(val: &Value) -> String {
+            val.downcast_switch()
+                .case::<i32>(|i| format!("int {i}"))
+                .case::<&str>(|s| format!("str {s}"))
+                .case::<i32>(|_| unreachable!())
+                .default(|_| String::from("other"))
+        }
+
+        assert_eq!(describe(&Value::new(3)), "int 3");
+        assert_eq!(describe(&Value::new("x")), "str x");
+        assert_eq!(describe(&Value::new(3u8)), "other");
+        assert_eq!(Value::new(1u8).downcast_switch().case::<i32>(|_| ()).run(), None);
+    }
+
+    #[test]
+    fn value_coerce() {
+        assert_eq!(Value::new(3i32).as_number(), Some(3.0));
+        assert_eq!(Value::new(200u8).coerce::<u64>(), Some(200));
+        assert_eq!(Value::new(200u8).coerce::<i8>(), None);
+        assert_eq!(Value::new(-1i64).coerce::<u32>(), None);
+        assert_eq!(Value::new(2.0f32).coerce::<u8>(), Some(2));
+        assert_eq!(Value::new(2.5f64).coerce::<u8>(), None);
+        assert_eq!(Value::new(f64::NAN).coerce::<i32>(), None);
+
+        assert_eq!(Value::new(1e40f64).coerce::<u128>(), None);
+        assert_eq!(Value::new(-1e40f64).coerce::<i128>(), None);
+        assert_eq!(Value::new(2f64.powi(128)).coerce::<u128>(), None);
+        assert_eq!(Value::new(2f64.powi(127)).coerce::<i128>(), None);
+        assert_eq!(Value::new(2f64.powi(127)).coerce::<u128>(), Some(1 << 127));
+        assert_eq!(Value::new(-(2f64.powi(127))).coerce::<i128>(), Some(i128::MIN));
+        assert_eq!(Value::new(2f64.powi(64)).coerce::<u64>(), None);
+        assert_eq!(Value::new("3").as_number(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn value_display() {
+        struct Celsius(f32);
+
+        impl fmt::Display for Celsius {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}°C", self.0)
+            }
+        }
+
+        assert_eq!(Value::new(3).display().unwrap().to_string(), "3");
+        assert!(Value::new(Celsius(1.5)).display().is_none());
+        registry::register_display::<Celsius>();
+        assert_eq!(Value::new(Celsius(1.5)).display().unwrap().to_string(), "1.5°C");
+    }
+
+    #[test]
+    fn value_take() {
+        let mut val = Value::new(String::from("owned"));
+        assert_eq!(val.take::<i32>(), None);
+        assert!(val.is::<String>());
+        assert_eq!(val.take::<String>().unwrap(), "owned");
+        assert!(val.is::<()>());
+    }
+
+    #[test]
+    fn value_replace() {
+        let mut val = Value::new(1);
+        let old = val.replace("now a str");
+        assert_eq!(old.downcast::<i32>().unwrap(), 1);
+        assert_eq!(val.as_type::<&str>(), Some(&"now a str"));
+    }
+
+    #[test]
+    fn value_modify() {
+        let mut val = Value::new(vec![1]);
+        assert!(val.modify(|v: &mut Vec<i32>| v.push(2)));
+        assert!(!val.modify(|v: &mut String| v.clear()));
+        assert_eq!(val.as_type::<Vec<i32>>().unwrap(), &[1, 2]);
+    }
+
+    #[test]
+    fn value_map() {
+        struct ConfigV1(u8);
+        struct ConfigV2(u32);
+        let val = Value::new(ConfigV1(3));
+        let val = val.map(|v: ConfigV1| ConfigV2(v.0.into())).ok().unwrap();
+        assert_eq!(val.as_type::<ConfigV2>().unwrap().0, 3);
+        let val = val.map(|v: ConfigV1| v.0).unwrap_err();
+        assert!(val.is::<ConfigV2>());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn value_try_clone() {
+        #[derive(Clone)]
+        struct Registered(Vec<u8>);
+        struct Unregistered;
+
+        assert_eq!(Value::new(3).try_clone().unwrap().as_type::<i32>(), Some(&3));
+        assert!(Value::new(Unregistered).try_clone().is_none());
+        registry::register_clone::<Registered>();
+        let val = Value::new(Registered(vec![1, 2]));
+        assert_eq!(val.try_clone().unwrap().as_type::<Registered>().unwrap().0, [1, 2]);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn value_debug() {
+        #[allow(dead_code)]
+        #[derive(Debug)]
+        struct Registered(u8);
+        struct Unregistered;
+
+        registry::register_debug::<Registered>();
+        assert!(format!("{:?}", Value::new(Registered(4))).contains("value: Registered(4)"));
+        assert!(format!("{:?}", Value::new(Unregistered)).ends_with(".. }"));
+        assert!(format!("{:?}", Value::new("text")).contains("value: \"text\""));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn value_eq() {
+        #[derive(PartialEq)]
+        struct Registered(u8);
+        struct Unregistered;
+
+        assert_eq!(Value::new(1), Value::new(1));
+        assert_ne!(Value::new(1), Value::new(2));
+        assert_ne!(Value::new(1), Value::new(1u8));
+        assert_eq!(Value::new(Unregistered).try_eq(&Value::new(Unregistered)), None);
+        registry::register_eq::<Registered>();
+        assert_eq!(Value::new(Registered(1)).try_eq(&Value::new(Registered(1))), Some(true));
+        assert_eq!(Value::new(Registered(1)).try_eq(&Value::new(Registered(2))), Some(false));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    // Inline storage is an `UnsafeCell`, but the hash only depends on the
+    // registered `Hash` implementation.
+    #[allow(clippy::mutable_key_type)]
+    fn value_hash() {
+        use std::collections::HashSet;
+
+        #[derive(PartialEq, Eq, Hash)]
+        struct Registered(u8);
+
+        struct Unregistered;
+
+        registry::register_hash::<Registered>();
+        let set: HashSet<HashableValue> = [
+            Value::new(1),
+            Value::new(1),
+            Value::new(1u8),
+            Value::new(Registered(1)),
+            Value::new(Registered(1)),
+            Value::new(String::from("a")),
+        ]
+        .into_iter()
+        .map(|value| HashableValue::new(value).unwrap())
+        .collect();
+        assert_eq!(set.len(), 4);
+        assert!(set.contains(&HashableValue::new(Value::new(Registered(1))).unwrap()));
+
+        assert!(HashableValue::new(Value::new(Unregistered)).is_err());
+        assert!(HashableValue::new(Value::new(f32::NAN)).is_err());
+        let nan = Value::new(f64::NAN);
+        assert_ne!(nan, nan);
+        assert!(HashableValue::new(nan).unwrap_err().is::<f64>());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn value_as_trait() {
+        trait Counter {
+            fn bump(&mut self) -> u32;
+        }
+
+        struct Clicks(u32);
+
+        impl Counter for Clicks {
+            fn bump(&mut self) -> u32 {
+                self.0 += 1;
+                self.0
+            }
+        }
+
+        let mut val = Value::new(Clicks(0));
+        assert!(val.as_trait::<dyn Counter>().is_none());
+        registry::register_trait::<dyn Counter, Clicks>(|v| v, |v| v);
+        assert_eq!(val.as_trait_mut::<dyn Counter>().unwrap().bump(), 1);
+        assert_eq!(val.as_type::<Clicks>().unwrap().0, 1);
+        assert!(Value::new(1).as_trait::<dyn Counter>().is_none());
+        assert!(val.as_trait::<dyn fmt::Display>().is_none());
+    }
+
+    #[cfg(feature = "type-names")]
+    #[test]
+    fn value_type_name() {
+        assert_eq!(Value::new(1u8).type_name(), "u8");
+        assert_eq!(Value::new(Value::new("a")).type_name(), "&str");
+        assert_eq!(Value::new(1u8).downcast::<i32>().unwrap_err().type_name(), "u8");
+        assert_eq!(format!("{:?}", Value::new(1u8)), "Value { type: \"u8\", value: 1 }");
+    }
+}
@@ -0,0 +1,596 @@
+//! Loading maps from YAML.
+//!
+//! The parser covers what configuration files commonly use: block and flow
+//! mappings and sequences, plain and quoted single-line scalars and comments.
+//! Anchors, aliases, tags, block scalars and multiple documents are rejected.
+
+use alloc::{
+    borrow::ToOwned,
+    format,
+    string::String,
+    vec::Vec,
+};
+
+use crate::{AnyMap, ConfigError, Value};
+
+type Table = AnyMap<String>;
+
+impl AnyMap<String> {
+    /// Parses a YAML document whose top level is a mapping. Scalars are
+    /// resolved as in YAML 1.2's core schema: `null` and `~` are stored as
+    /// `()`, integers as `i64`, floats as `f64`, booleans as `bool` and
+    /// anything else as `String`. Sequences become `Vec<Value>` and mappings
+    /// nested `AnyMap<String>`s.
+    ///
+    /// ```
+    /// use anymap::{AnyMap, Value};
+    ///
+    /// let config = AnyMap::from_yaml("
+    /// name: example
+    /// server:
+    ///   port: 8080
+    ///   hosts: [a, b]
+    /// ").unwrap();
+    /// assert_eq!(config.get_typed::<String>("name").unwrap(), "example");
+    /// let server = config.get_typed::<AnyMap>("server").unwrap();
+    /// assert_eq!(server.get_typed::<i64>("port"), Some(&8080));
+    /// assert_eq!(server.get_typed::<Vec<Value>>("hosts").unwrap().len(), 2);
+    /// ```
+    pub fn from_yaml(input: &str) -> Result<Self, ConfigError> {
+        Parser {
+            lines: lines(input)?,
+            next: 0,
+        }
+        .document()
+    }
+}
+
+/// A line with its comment, trailing whitespace and indentation removed.
+#[derive(Clone, Copy)]
+struct Line<'a> {
+    number: usize,
+    indent: usize,
+    text: &'a str,
+}
+
+fn lines(input: &str) -> Result<Vec<Line<'_>>, ConfigError> {
+    let mut lines = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        let number = i + 1;
+        let line = strip_comment(line).trim_end();
+        let text = line.trim_start_matches(' ');
+        if text.is_empty() {
+            continue;
+        }
+        if text.starts_with('\t') {
+            return Err(ConfigError::new(number, "tabs are not allowed in indentation"));
+        }
+        let indent = line.len() - text.len();
+        if indent == 0 && text == "---" {
+            if !lines.is_empty() {
+                return Err(ConfigError::new(number, "multiple documents are not supported"));
+            }
+            continue;
+        }
+        if indent == 0 && text == "..." {
+            break;
+        }
+        lines.push(Line { number, indent, text });
+    }
+    Ok(lines)
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    let mut prev = ' ';
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            // Quotes only start a scalar at its beginning, not inside words
+            // like `it's`.
+            None if matches!(c, '"' | '\'') && matches!(prev, ' ' | '[' | '{' | ',' | ':' | '-') => quote = Some(c),
+            None if c == '#' && prev.is_whitespace() => return &line[..i],
+            None => {}
+        }
+        prev = c;
+    }
+    line
+}
+
+fn is_sequence_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+/// Splits `key: value`, returning `None` if `text` is not a mapping entry.
+fn split_entry(text: &str) -> Option<(&str, &str)> {
+    let key_end = match text.chars().next()? {
+        '[' | '{' => return None,
+        quote @ ('"' | '\'') => quoted_end(text, quote)?,
+        _ => 0,
+    };
+    let colon = text[key_end..]
+        .match_indices(':')
+        .map(|(i, _)| key_end + i)
+        .find(|&i| matches!(text.as_bytes().get(i + 1), None | Some(b' ')))?;
+    // A quoted key may only be followed by spaces before its colon.
+    if key_end > 0 && !text[key_end..colon].trim().is_empty() {
+        return None;
+    }
+    Some((text[..colon].trim_end(), text[colon + 1..].trim_start()))
+}
+
+/// Returns the index just past the quoted scalar at the start of `text`.
+fn quoted_end(text: &str, quote: char) -> Option<usize> {
+    let mut chars = text.char_indices().skip(1).peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if quote == '"' => {
+                chars.next();
+            }
+            '\'' if quote == '\'' && chars.peek().is_some_and(|&(_, c)| c == '\'') => {
+                chars.next();
+            }
+            c if c == quote => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+struct Parser<'a> {
+    lines: Vec<Line<'a>>,
+    next: usize,
+}
+
+impl Parser<'_> {
+    fn document(mut self) -> Result<Table, ConfigError> {
+        let Some(&first) = self.lines.first() else {
+            return Ok(Table::new());
+        };
+        if is_sequence_item(first.text) {
+            return Err(ConfigError::new(first.number, "top level is not a mapping"));
+        }
+        let map = self.mapping(first.indent)?;
+        match self.lines.get(self.next) {
+            Some(line) => Err(ConfigError::new(line.number, "unexpected indentation")),
+            None => Ok(map),
+        }
+    }
+
+    /// Parses the mapping or sequence starting at the next line.
+    fn block(&mut self, indent: usize) -> Result<Value, ConfigError> {
+        if is_sequence_item(self.lines[self.next].text) {
+            self.sequence(indent).map(Value::new)
+        } else {
+            self.mapping(indent).map(Value::new)
+        }
+    }
+
+    fn mapping(&mut self, indent: usize) -> Result<Table, ConfigError> {
+        let mut map = Table::new();
+        while let Some(&line) = self.lines.get(self.next) {
+            if line.indent < indent {
+                break;
+            }
+            if line.indent > indent {
+                return Err(ConfigError::new(line.number, "unexpected indentation"));
+            }
+            let (key, rest) = split_entry(line.text)
+                .ok_or_else(|| ConfigError::new(line.number, "expected a `key: value` entry"))?;
+            self.next += 1;
+            let key = Flow::new(key, line.number).key()?;
+            let value = if rest.is_empty() {
+                match self.lines.get(self.next) {
+                    Some(next) if next.indent > indent => self.block(next.indent)?,
+                    // A sequence may be indented as far as its key.
+                    Some(next) if next.indent == indent && is_sequence_item(next.text) => {
+                        Value::new(self.sequence(indent)?)
+                    }
+                    _ => Value::new(()),
+                }
+            } else {
+                inline(rest, line.number)?
+            };
+            if map.contains_key(key.as_str()) {
+                return Err(ConfigError::new(line.number, format!("duplicate key `{key}`")));
+            }
+            map.insert_val(key, value);
+        }
+        Ok(map)
+    }
+
+    fn sequence(&mut self, indent: usize) -> Result<Vec<Value>, ConfigError> {
+        let mut items = Vec::new();
+        while let Some(&line) = self.lines.get(self.next) {
+            if line.indent > indent {
+                return Err(ConfigError::new(line.number, "unexpected indentation"));
+            }
+            if line.indent < indent || !is_sequence_item(line.text) {
+                break;
+            }
+            let rest = line.text[1..].trim_start_matches(' ');
+            if rest.is_empty() {
+                self.next += 1;
+                items.push(match self.lines.get(self.next) {
+                    Some(next) if next.indent > indent => self.block(next.indent)?,
+                    _ => Value::new(()),
+                });
+            } else if is_sequence_item(rest) || split_entry(rest).is_some() {
+                // A nested block starting on the item's line continues on the
+                // following lines at the column it starts at, so treat it as
+                // a line of its own.
+                let indent = line.indent + line.text.len() - rest.len();
+                self.lines[self.next] = Line { indent, text: rest, ..line };
+                items.push(self.block(indent)?);
+            } else {
+                self.next += 1;
+                items.push(inline(rest, line.number)?);
+            }
+        }
+        Ok(items)
+    }
+}
+
+/// Parses a value written on the same line as its key or sequence dash.
+fn inline(text: &str, line: usize) -> Result<Value, ConfigError> {
+    match text.chars().next() {
+        Some('|' | '>') => return Err(ConfigError::new(line, "block scalars are not supported")),
+        Some('&' | '*') => return Err(ConfigError::new(line, "anchors and aliases are not supported")),
+        Some('!') => return Err(ConfigError::new(line, "tags are not supported")),
+        Some('[' | '{' | '"' | '\'') => {}
+        // Outside of flow collections, plain scalars may contain any of
+        // `,[]{}`.
+        _ => return Ok(plain(text)),
+    }
+    let mut flow = Flow::new(text, line);
+    let value = flow.value()?;
+    flow.skip_spaces();
+    match flow.peek() {
+        Some(c) => Err(flow.error(format!("unexpected `{c}` after value"))),
+        None => Ok(value),
+    }
+}
+
+/// Parser for flow collections and quoted scalars, which can't span lines
+/// in this subset.
+struct Flow<'a> {
+    text: &'a str,
+    pos: usize,
+    line: usize,
+}
+
+impl<'a> Flow<'a> {
+    fn new(text: &'a str, line: usize) -> Self {
+        Flow { text, pos: 0, line }
+    }
+
+    fn error(&self, message: impl Into<String>) -> ConfigError {
+        ConfigError::new(self.line, message)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.bump();
+        }
+        found
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.eat(' ') {}
+    }
+
+    /// Parses a whole mapping key.
+    fn key(mut self) -> Result<String, ConfigError> {
+        match self.peek() {
+            Some(quote @ ('"' | '\'')) => {
+                self.bump();
+                self.quoted(quote)
+            }
+            _ => Ok(self.text.to_owned()),
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, ConfigError> {
+        self.skip_spaces();
+        match self.peek() {
+            Some('[') => {
+                self.bump();
+                self.sequence()
+            }
+            Some('{') => {
+                self.bump();
+                self.mapping()
+            }
+            Some(quote @ ('"' | '\'')) => {
+                self.bump();
+                self.quoted(quote).map(Value::new)
+            }
+            _ => Ok(plain(self.plain())),
+        }
+    }
+
+    /// Takes a plain scalar, which inside flow collections ends at any of
+    /// `,]}` or at `: `.
+    fn plain(&mut self) -> &'a str {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            let rest = &self.text[self.pos..];
+            if matches!(c, ',' | ']' | '}') || rest.starts_with(": ") || rest == ":" {
+                break;
+            }
+            self.bump();
+        }
+        self.text[start..self.pos].trim_end()
+    }
+
+    fn sequence(&mut self) -> Result<Value, ConfigError> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_spaces();
+            if self.eat(']') {
+                return Ok(Value::new(items));
+            }
+            items.push(self.value()?);
+            self.skip_spaces();
+            if self.eat(']') {
+                return Ok(Value::new(items));
+            }
+            if !self.eat(',') {
+                return Err(self.unexpected("`,` or `]`"));
+            }
+        }
+    }
+
+    fn mapping(&mut self) -> Result<Value, ConfigError> {
+        let mut map = Table::new();
+        loop {
+            self.skip_spaces();
+            if self.eat('}') {
+                return Ok(Value::new(map));
+            }
+            let key = match self.peek() {
+                Some(quote @ ('"' | '\'')) => {
+                    self.bump();
+                    self.quoted(quote)?
+                }
+                _ => self.plain().to_owned(),
+            };
+            self.skip_spaces();
+            if !self.eat(':') {
+                return Err(self.unexpected("`:`"));
+            }
+            let value = self.value()?;
+            if map.contains_key(key.as_str()) {
+                return Err(self.error(format!("duplicate key `{key}`")));
+            }
+            map.insert_val(key, value);
+            self.skip_spaces();
+            if self.eat('}') {
+                return Ok(Value::new(map));
+            }
+            if !self.eat(',') {
+                return Err(self.unexpected("`,` or `}`"));
+            }
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> ConfigError {
+        match self.peek() {
+            Some(c) => self.error(format!("expected {expected}, found `{c}`")),
+            None => self.error(format!("expected {expected}, found end of line")),
+        }
+    }
+
+    /// Parses the rest of a quoted scalar after its opening quote.
+    fn quoted(&mut self, quote: char) -> Result<String, ConfigError> {
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                None => return Err(self.error("unterminated string")),
+                Some('\'') if quote == '\'' && self.eat('\'') => s.push('\''),
+                Some(c) if c == quote => return Ok(s),
+                Some('\\') if quote == '"' => s.push(self.escape()?),
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, ConfigError> {
+        Ok(match self.bump() {
+            Some('0') => '\0',
+            Some('a') => '\u{7}',
+            Some('b') => '\u{8}',
+            Some('t') => '\t',
+            Some('n') => '\n',
+            Some('v') => '\u{b}',
+            Some('f') => '\u{c}',
+            Some('r') => '\r',
+            Some('e') => '\u{1b}',
+            Some(c @ (' ' | '"' | '/' | '\\')) => c,
+            Some('x') => self.unicode(2)?,
+            Some('u') => self.unicode(4)?,
+            Some('U') => self.unicode(8)?,
+            _ => return Err(self.error("invalid escape sequence")),
+        })
+    }
+
+    fn unicode(&mut self, len: usize) -> Result<char, ConfigError> {
+        let c = self
+            .text
+            .get(self.pos..self.pos + len)
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        self.pos += len;
+        Ok(c)
+    }
+}
+
+/// Resolves a plain scalar as in YAML 1.2's core schema.
+fn plain(text: &str) -> Value {
+    match text {
+        "" | "~" | "null" | "Null" | "NULL" => return Value::new(()),
+        "true" | "True" | "TRUE" => return Value::new(true),
+        "false" | "False" | "FALSE" => return Value::new(false),
+        ".inf" | ".Inf" | ".INF" | "+.inf" | "+.Inf" | "+.INF" => return Value::new(f64::INFINITY),
+        "-.inf" | "-.Inf" | "-.INF" => return Value::new(f64::NEG_INFINITY),
+        ".nan" | ".NaN" | ".NAN" => return Value::new(f64::NAN),
+        _ => {}
+    }
+    let digits = |s: &str, radix| !s.is_empty() && s.chars().all(|c| c.is_digit(radix));
+    for (prefix, radix) in [("0x", 16), ("0o", 8)] {
+        if let Some(s) = text.strip_prefix(prefix).filter(|s| digits(s, radix)) {
+            if let Ok(v) = i64::from_str_radix(s, radix) {
+                return Value::new(v);
+            }
+        }
+    }
+    let unsigned = text.strip_prefix(['+', '-']).unwrap_or(text);
+    if digits(unsigned, 10) {
+        if let Ok(v) = text.parse::<i64>() {
+            return Value::new(v);
+        }
+    }
+    if is_float(unsigned) {
+        if let Ok(v) = text.parse::<f64>() {
+            return Value::new(v);
+        }
+    }
+    Value::new(text.to_owned())
+}
+
+fn is_float(text: &str) -> bool {
+    let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent.strip_prefix(['+', '-']).unwrap_or(exponent))),
+        None => (text, None),
+    };
+    let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    let (int, frac) = match mantissa.split_once('.') {
+        Some((int, frac)) => (int, frac),
+        None => (mantissa, ""),
+    };
+    digits(int)
+        && digits(frac)
+        && !(int.is_empty() && frac.is_empty())
+        && exponent.is_none_or(|exponent| !exponent.is_empty() && digits(exponent))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scalars() {
+        let map = AnyMap::from_yaml(
+            r#"
+            # comment
+            null: ~
+            empty:
+            yes: True
+            int: -42
+            hex: 0x1F
+            float: 1.5e3
+            inf: -.inf
+            plain: it's a string # with a comment
+            url: http://example.com/#anchor
+            double: "tab\t\u00e9 # not a comment"
+            single: 'it''s'
+            "quoted key": 1
+            list, with commas: a, b
+            "#,
+        )
+        .unwrap();
+        assert_eq!(map.get_typed::<()>("null"), Some(&()));
+        assert_eq!(map.get_typed::<()>("empty"), Some(&()));
+        assert_eq!(map.get_typed::<bool>("yes"), Some(&true));
+        assert_eq!(map.get_typed::<i64>("int"), Some(&-42));
+        assert_eq!(map.get_typed::<i64>("hex"), Some(&31));
+        assert_eq!(map.get_typed::<f64>("float"), Some(&1500.0));
+        assert_eq!(map.get_typed::<f64>("inf"), Some(&f64::NEG_INFINITY));
+        assert_eq!(map.get_typed::<String>("plain").unwrap(), "it's a string");
+        assert_eq!(map.get_typed::<String>("url").unwrap(), "http://example.com/#anchor");
+        assert_eq!(map.get_typed::<String>("double").unwrap(), "tab\té # not a comment");
+        assert_eq!(map.get_typed::<String>("single").unwrap(), "it's");
+        assert_eq!(map.get_typed::<i64>("quoted key"), Some(&1));
+        assert_eq!(map.get_typed::<String>("list, with commas").unwrap(), "a, b");
+    }
+
+    #[test]
+    fn collections() {
+        let map = AnyMap::from_yaml(
+            "\
+server:
+  http:
+    port: 80
+  name: main
+hosts:
+- a
+- 'b'
+-
+  - nested
+users:
+  - name: alice
+    roles: [admin, 'dev ops', {level: 2}]
+  - name: bob
+    roles:
+    - guest
+flow: {a: 1, 'b': [x, y], c: {}}
+",
+        )
+        .unwrap();
+        let server = map.get_typed::<Table>("server").unwrap();
+        assert_eq!(server.get_typed::<String>("name").unwrap(), "main");
+        assert_eq!(server.get_typed::<Table>("http").unwrap().get_typed::<i64>("port"), Some(&80));
+
+        let hosts = map.get_typed::<Vec<Value>>("hosts").unwrap();
+        assert_eq!(hosts.len(), 3);
+        assert_eq!(hosts[1].as_type::<String>().unwrap(), "b");
+        assert_eq!(hosts[2].as_type::<Vec<Value>>().unwrap()[0].as_type::<String>().unwrap(), "nested");
+
+        let users = map.get_typed::<Vec<Value>>("users").unwrap();
+        let alice = users[0].as_type::<Table>().unwrap();
+        let roles = alice.get_typed::<Vec<Value>>("roles").unwrap();
+        assert_eq!(roles[1].as_type::<String>().unwrap(), "dev ops");
+        assert_eq!(roles[2].as_type::<Table>().unwrap().get_typed::<i64>("level"), Some(&2));
+        let bob = users[1].as_type::<Table>().unwrap();
+        assert_eq!(bob.get_typed::<Vec<Value>>("roles").unwrap().len(), 1);
+
+        let flow = map.get_typed::<Table>("flow").unwrap();
+        assert_eq!(flow.get_typed::<Vec<Value>>("b").unwrap().len(), 2);
+        assert!(flow.get_typed::<Table>("c").unwrap().is_empty());
+    }
+
+    #[test]
+    fn errors() {
+        let error = |input| AnyMap::from_yaml(input).unwrap_err();
+        assert_eq!(error("a: 1\na: 2").to_string(), "line 2: duplicate key `a`");
+        assert_eq!(error("a: 1\n  b: 2").to_string(), "line 2: unexpected indentation");
+        assert_eq!(error("- a").message(), "top level is not a mapping");
+        assert_eq!(error("a").message(), "expected a `key: value` entry");
+        assert_eq!(error("a: |\n  text").message(), "block scalars are not supported");
+        assert_eq!(error("a: &x 1").message(), "anchors and aliases are not supported");
+        assert_eq!(error("a: 1\n---\nb: 2").message(), "multiple documents are not supported");
+        assert_eq!(error("a: [1, 2").message(), "expected `,` or `]`, found end of line");
+        assert_eq!(error("a: \"open").message(), "unterminated string");
+        assert_eq!(error("a: 'x' y").message(), "unexpected `y` after value");
+        assert_eq!(error("a:\n\tb: 1").message(), "tabs are not allowed in indentation");
+        assert!(AnyMap::from_yaml("# only a comment").unwrap().is_empty());
+        assert_eq!(AnyMap::from_yaml("---\na: 1\n...\n- ignored").unwrap().len(), 1);
+    }
+}
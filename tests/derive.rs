@@ -0,0 +1,72 @@
+use anymap::{AnyMap, FromAnyMapError};
+use anymap_derive::{FromAnyMap, IntoAnyMap};
+
+#[derive(Debug, PartialEq, FromAnyMap, IntoAnyMap)]
+struct Config {
+    host: String,
+    pub port: u16,
+    #[allow(dead_code)]
+    pub(crate) tags: Vec<(String, u8)>,
+    r#type: Option<&'static str>,
+}
+
+fn config_map() -> AnyMap<String> {
+    let mut map = AnyMap::new();
+    map.insert("host".into(), String::from("localhost"));
+    map.insert("port".into(), 8080u16);
+    map.insert("tags".into(), vec![(String::from("env"), 1u8)]);
+    map.insert("type".into(), Some("dev"));
+    map
+}
+
+#[test]
+fn from_any_map_extracts_fields() {
+    let config = Config::try_from(&config_map()).unwrap();
+    assert_eq!(
+        config,
+        Config {
+            host: "localhost".into(),
+            port: 8080,
+            tags: vec![("env".into(), 1)],
+            r#type: Some("dev"),
+        }
+    );
+}
+
+#[test]
+fn from_any_map_reports_missing_field() {
+    let mut map = config_map();
+    map.remove("port");
+    assert_eq!(Config::try_from(&map), Err(FromAnyMapError::Missing { field: "port" }));
+}
+
+#[test]
+fn from_any_map_reports_mismatched_field() {
+    let mut map = config_map();
+    map.insert("port".into(), 8080u32);
+    let err = Config::try_from(&map).unwrap_err();
+    assert!(matches!(err, FromAnyMapError::Mismatch { field: "port", .. }));
+    assert!(err.to_string().starts_with("invalid entry for field `port`: stored value is not of type `u16`"));
+}
+
+#[test]
+fn into_any_map_inserts_fields() {
+    let config = Config {
+        host: "localhost".into(),
+        port: 8080,
+        tags: Vec::new(),
+        r#type: None,
+    };
+    let map = AnyMap::from(config);
+    assert_eq!(map.len(), 4);
+    assert_eq!(map.get_typed::<String>("host").unwrap(), "localhost");
+    assert_eq!(map.get_typed::<u16>("port"), Some(&8080));
+    assert_eq!(map.get_typed::<Option<&str>>("type"), Some(&None));
+}
+
+#[test]
+fn into_any_map_round_trips() {
+    let map = config_map();
+    let config = Config::try_from(&map).unwrap();
+    assert_eq!(Config::try_from(&AnyMap::from(config)).unwrap(), Config::try_from(&map).unwrap());
+}